@@ -1,14 +1,24 @@
-use crate::device::{AudioDevice, DeviceProvider};
+use crate::backend::{
+    AudioBackend, AudioStream, CpalBackend, FileInput, InputCallback, OutputCallback, StreamSpec,
+};
+use crate::device::{AudioDevice, DeviceCapabilities, DeviceProvider};
+use crate::latency::LatencySettings;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, OnceLock};
 
 #[derive(Serialize, Deserialize, Default)]
 struct AppConfig {
     input_device: Option<String>,
     output_device: Option<String>,
+    #[serde(default)]
+    latency: LatencySettings,
 }
 
-fn config_path() -> std::path::PathBuf {
+/// Where the global engine keeps the audio preferences: config.json in the working
+/// directory.
+pub fn default_config_path() -> PathBuf {
     std::env::current_dir()
         .unwrap_or_default()
         .join("config.json")
@@ -17,54 +27,105 @@ fn config_path() -> std::path::PathBuf {
 static AUDIO_ENGINE: OnceLock<Arc<Mutex<AudioEngine>>> = OnceLock::new();
 
 pub struct AudioEngine {
+    input_provider: Arc<dyn DeviceProvider>,
+    output_provider: Arc<dyn DeviceProvider>,
+    // Preferences file; None keeps them in memory only
+    config_path: Option<PathBuf>,
     input_devices: Vec<String>,
     output_devices: Vec<String>,
     selected_input: Option<String>,
     selected_output: Option<String>,
+    latency: LatencySettings,
+    backend: Arc<dyn AudioBackend>,
+    // Recorded from in place of the input device when set
+    input_file: Option<FileInput>,
+    // Queried from the devices when first asked for, until the next refresh
+    input_capabilities: HashMap<String, Result<DeviceCapabilities, String>>,
+    output_capabilities: HashMap<String, Result<DeviceCapabilities, String>>,
 }
 
 impl AudioEngine {
+    /// The engine streams are opened through. Unless one was installed with
+    /// `set_global`, it uses the system's devices and the default config path.
     pub fn global() -> Arc<Mutex<AudioEngine>> {
         AUDIO_ENGINE
-            .get_or_init(|| Arc::new(Mutex::new(AudioEngine::new())))
+            .get_or_init(|| {
+                Arc::new(Mutex::new(AudioEngine::new(
+                    Arc::new(AudioDevice::INPUT),
+                    Arc::new(AudioDevice::OUTPUT),
+                    Some(default_config_path()),
+                )))
+            })
             .clone()
     }
 
-    fn new() -> Self {
-        let input_devices = AudioDevice::INPUT.list().unwrap_or_default();
-        let output_devices = AudioDevice::OUTPUT.list().unwrap_or_default();
+    /// Make `engine` the global engine, replacing the one in use. Streams already open
+    /// keep running.
+    pub fn set_global(engine: AudioEngine) {
+        let mut engine = Some(engine);
+        let global = AUDIO_ENGINE.get_or_init(|| Arc::new(Mutex::new(engine.take().unwrap())));
+        if let Some(engine) = engine {
+            *global.lock().unwrap() = engine;
+        }
+    }
+
+    /// An engine choosing between the devices of `input_provider` and
+    /// `output_provider` and opening them through cpal, with the preferences saved at
+    /// `config_path` applied. Without a path nothing is loaded or saved.
+    pub fn new(
+        input_provider: Arc<dyn DeviceProvider>,
+        output_provider: Arc<dyn DeviceProvider>,
+        config_path: Option<PathBuf>,
+    ) -> Self {
+        let input_devices = input_provider.list().unwrap_or_default();
+        let output_devices = output_provider.list().unwrap_or_default();
 
         // Try loading saved preferences, fall back to OS defaults
-        let config = std::fs::read_to_string(config_path())
-            .ok()
+        let config = config_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
             .and_then(|s| serde_json::from_str::<AppConfig>(&s).ok())
             .unwrap_or_default();
 
         let selected_input = config.input_device
             .filter(|name| input_devices.contains(name))
-            .or_else(|| AudioDevice::INPUT.default_name())
+            .or_else(|| input_provider.default_name())
             .or_else(|| input_devices.first().cloned());
 
         let selected_output = config.output_device
             .filter(|name| output_devices.contains(name))
-            .or_else(|| AudioDevice::OUTPUT.default_name())
+            .or_else(|| output_provider.default_name())
             .or_else(|| output_devices.first().cloned());
 
+        let backend = CpalBackend::new(Arc::clone(&input_provider), Arc::clone(&output_provider));
+
         AudioEngine {
+            input_provider,
+            output_provider,
+            config_path,
             input_devices,
             output_devices,
             selected_input,
             selected_output,
+            latency: config.latency,
+            backend: Arc::new(backend),
+            input_file: None,
+            input_capabilities: HashMap::new(),
+            output_capabilities: HashMap::new(),
         }
     }
 
     pub fn save_config(&self) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
         let config = AppConfig {
             input_device: self.selected_input.clone(),
             output_device: self.selected_output.clone(),
+            latency: self.latency,
         };
         if let Ok(json) = serde_json::to_string_pretty(&config) {
-            let _ = std::fs::write(config_path(), json);
+            let _ = std::fs::write(path, json);
         }
     }
 
@@ -91,6 +152,10 @@ impl AudioEngine {
     /// Select an input device by name
     pub fn set_input_device(&mut self, name: String) {
         if self.input_devices.contains(&name) {
+            if self.selected_input.as_ref() != Some(&name) {
+                // A calibration only holds for the devices it was measured on
+                self.latency.calibrated_ms = None;
+            }
             self.selected_input = Some(name);
         }
     }
@@ -98,51 +163,158 @@ impl AudioEngine {
     /// Select an output device by name
     pub fn set_output_device(&mut self, name: String) {
         if self.output_devices.contains(&name) {
+            if self.selected_output.as_ref() != Some(&name) {
+                self.latency.calibrated_ms = None;
+            }
             self.selected_output = Some(name);
         }
     }
 
+    /// Recording latency compensation settings
+    pub fn latency(&self) -> LatencySettings {
+        self.latency
+    }
+
+    /// Update and save the recording latency compensation settings
+    pub fn set_latency(&mut self, latency: LatencySettings) {
+        self.latency = latency;
+        self.save_config();
+    }
+
     /// Refresh the list of available devices
     pub fn refresh_devices(&mut self) {
-        self.input_devices = AudioDevice::INPUT.list().unwrap_or_default();
-        self.output_devices = AudioDevice::OUTPUT.list().unwrap_or_default();
+        self.input_devices = self.input_provider.list().unwrap_or_default();
+        self.output_devices = self.output_provider.list().unwrap_or_default();
+        self.input_capabilities.clear();
+        self.output_capabilities.clear();
 
         // Revalidate selections — fall back to OS default if current is gone
         if let Some(input) = &self.selected_input {
             if !self.input_devices.contains(input) {
-                self.selected_input = AudioDevice::INPUT.default_name()
+                self.selected_input = self.input_provider.default_name()
                     .or_else(|| self.input_devices.first().cloned());
             }
         }
         if let Some(output) = &self.selected_output {
             if !self.output_devices.contains(output) {
-                self.selected_output = AudioDevice::OUTPUT.default_name()
+                self.selected_output = self.output_provider.default_name()
                     .or_else(|| self.output_devices.first().cloned());
             }
         }
     }
 
-    /// Get an AudioDevice for the selected input (or default)
-    pub fn get_input_device() -> Result<AudioDevice, Box<dyn std::error::Error>> {
+    /// What an input device can run at
+    pub fn input_capabilities(&mut self, name: &str) -> Result<DeviceCapabilities, String> {
+        let provider = &self.input_provider;
+        self.input_capabilities
+            .entry(name.to_string())
+            .or_insert_with(|| provider.capabilities(name).map_err(|e| e.to_string()))
+            .clone()
+    }
+
+    /// What an output device can run at
+    pub fn output_capabilities(&mut self, name: &str) -> Result<DeviceCapabilities, String> {
+        let provider = &self.output_provider;
+        self.output_capabilities
+            .entry(name.to_string())
+            .or_insert_with(|| provider.capabilities(name).map_err(|e| e.to_string()))
+            .clone()
+    }
+
+    /// Switch where streams are opened, e.g. to a `NullBackend` to run without devices.
+    /// Streams already open keep running on the old backend.
+    pub fn set_backend(&mut self, backend: Arc<dyn AudioBackend>) {
+        self.backend = backend;
+    }
+
+    /// Record from a WAV file instead of the input device, or go back to the device
+    /// with `None`. The file plays from the start each time an input opens.
+    pub fn set_input_file(
+        &mut self,
+        path: Option<&Path>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.input_file = path.map(FileInput::open).transpose()?;
+        Ok(())
+    }
+
+    /// The WAV file standing in for the input device, if any
+    pub fn input_file(&self) -> Option<&Path> {
+        self.input_file.as_ref().map(|f| f.path())
+    }
+
+    /// Format of the selected input (or default), or of the input file
+    pub fn input_format() -> Result<StreamSpec, Box<dyn std::error::Error>> {
         let engine = Self::global();
         let engine = engine.lock().unwrap();
 
-        if let Some(name) = &engine.selected_input {
-            AudioDevice::INPUT.by_name(name)
-        } else {
-            AudioDevice::INPUT.default()
+        match &engine.input_file {
+            Some(file) => Ok(file.format()),
+            None => engine
+                .backend
+                .input_format(engine.selected_input.as_deref()),
+        }
+    }
+
+    /// Format to open the input with for a session at `sample_rate`. Fails if the
+    /// device runs at another rate; an input file is resampled instead.
+    pub fn input_format_for(sample_rate: u32) -> Result<StreamSpec, Box<dyn std::error::Error>> {
+        let format = Self::input_format()?;
+        let is_file = Self::global().lock().unwrap().input_file.is_some();
+        if !is_file && format.sample_rate != sample_rate {
+            return Err(format!(
+                "Input device sample rate ({}Hz) does not match session sample rate ({}Hz). Change your input device or start a new session.",
+                format.sample_rate, sample_rate
+            ).into());
         }
+        Ok(StreamSpec {
+            sample_rate,
+            ..format
+        })
     }
 
-    /// Get an AudioDevice for the selected output (or default)
-    pub fn get_output_device() -> Result<AudioDevice, Box<dyn std::error::Error>> {
+    /// Format of the selected output (or default)
+    pub fn output_format() -> Result<StreamSpec, Box<dyn std::error::Error>> {
         let engine = Self::global();
         let engine = engine.lock().unwrap();
 
-        if let Some(name) = &engine.selected_output {
-            AudioDevice::OUTPUT.by_name(name)
-        } else {
-            AudioDevice::OUTPUT.default()
+        engine
+            .backend
+            .output_format(engine.selected_output.as_deref())
+    }
+
+    /// Open a stream on the selected input (or default), or on the input file
+    pub fn open_input(
+        spec: StreamSpec,
+        callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        // Opening can take a while, so it happens without the engine locked
+        let (backend, device, file) = {
+            let engine = Self::global();
+            let engine = engine.lock().unwrap();
+            (
+                Arc::clone(&engine.backend),
+                engine.selected_input.clone(),
+                engine.input_file.clone(),
+            )
+        };
+
+        match file {
+            Some(file) => backend.open_source(spec, file.source(spec), callback),
+            None => backend.open_input(device.as_deref(), spec, callback),
         }
     }
+
+    /// Open a stream on the selected output (or default)
+    pub fn open_output(
+        spec: StreamSpec,
+        callback: OutputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        let (backend, device) = {
+            let engine = Self::global();
+            let engine = engine.lock().unwrap();
+            (Arc::clone(&engine.backend), engine.selected_output.clone())
+        };
+
+        backend.open_output(device.as_deref(), spec, callback)
+    }
 }
@@ -0,0 +1,68 @@
+use super::AudioStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// A stream without a device behind it: a thread calls `tick` with a block size every
+/// time that many frames would have played.
+pub(super) struct ClockedStream {
+    playing: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ClockedStream {
+    pub(super) fn start(
+        sample_rate: u32,
+        frames: u32,
+        mut tick: impl FnMut(usize) + Send + 'static,
+    ) -> Self {
+        let playing = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let period = Duration::from_secs_f64(frames.max(1) as f64 / sample_rate.max(1) as f64);
+
+        let thread = {
+            let playing = Arc::clone(&playing);
+            let stopped = Arc::clone(&stopped);
+            std::thread::spawn(move || {
+                // Deadlines are kept on a fixed grid so sleeping late does not drift
+                let mut next = Instant::now();
+                while !stopped.load(Ordering::Relaxed) {
+                    if playing.load(Ordering::Relaxed) {
+                        tick(frames as usize);
+                    }
+                    next += period;
+                    let now = Instant::now();
+                    if next > now {
+                        std::thread::sleep(next - now);
+                    } else {
+                        next = now;
+                    }
+                }
+            })
+        };
+
+        ClockedStream {
+            playing,
+            stopped,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl AudioStream for ClockedStream {
+    fn play(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.playing.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Drop for ClockedStream {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
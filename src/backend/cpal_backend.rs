@@ -0,0 +1,133 @@
+use super::clock::ClockedStream;
+use super::{AudioBackend, AudioStream, InputCallback, OutputCallback, SampleSource, StreamSpec};
+use crate::device::{AudioDevice, DeviceProvider};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{BufferSize, SampleRate, Stream, StreamConfig};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Callback size for sources when the stream does not ask for one
+const DEFAULT_SOURCE_FRAMES: u32 = 256;
+
+/// Audio devices opened through cpal, found by name through the providers.
+pub struct CpalBackend {
+    inputs: Arc<dyn DeviceProvider>,
+    outputs: Arc<dyn DeviceProvider>,
+}
+
+struct CpalStream(Stream);
+
+impl AudioStream for CpalStream {
+    fn play(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(self.0.play()?)
+    }
+}
+
+impl CpalBackend {
+    pub fn new(inputs: Arc<dyn DeviceProvider>, outputs: Arc<dyn DeviceProvider>) -> Self {
+        CpalBackend { inputs, outputs }
+    }
+
+    fn input_device(&self, name: Option<&str>) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        find_device(&*self.inputs, name)
+    }
+
+    fn output_device(&self, name: Option<&str>) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        find_device(&*self.outputs, name)
+    }
+}
+
+fn find_device(
+    provider: &dyn DeviceProvider,
+    name: Option<&str>,
+) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+    match name {
+        Some(name) => provider.by_name(name),
+        None => provider.default(),
+    }
+}
+
+fn format(device: &AudioDevice) -> StreamSpec {
+    StreamSpec {
+        channels: device.channels,
+        sample_rate: device.sample_rate,
+        buffer_frames: None,
+    }
+}
+
+fn stream_config(spec: StreamSpec) -> StreamConfig {
+    StreamConfig {
+        channels: spec.channels,
+        sample_rate: SampleRate(spec.sample_rate),
+        buffer_size: match spec.buffer_frames {
+            Some(frames) => BufferSize::Fixed(frames),
+            None => BufferSize::Default,
+        },
+    }
+}
+
+impl AudioBackend for CpalBackend {
+    fn output_format(
+        &self,
+        device: Option<&str>,
+    ) -> Result<StreamSpec, Box<dyn std::error::Error>> {
+        Ok(format(&self.output_device(device)?))
+    }
+
+    fn input_format(&self, device: Option<&str>) -> Result<StreamSpec, Box<dyn std::error::Error>> {
+        Ok(format(&self.input_device(device)?))
+    }
+
+    fn open_output(
+        &self,
+        device: Option<&str>,
+        spec: StreamSpec,
+        mut callback: OutputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        let stream = self.output_device(device)?.device.build_output_stream(
+            &stream_config(spec),
+            move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                let timestamp = info.timestamp();
+                callback(data, timestamp.playback.duration_since(&timestamp.callback));
+            },
+            |err| eprintln!("Output stream error: {err}"),
+            None,
+        )?;
+        Ok(Box::new(CpalStream(stream)))
+    }
+
+    fn open_input(
+        &self,
+        device: Option<&str>,
+        spec: StreamSpec,
+        mut callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        let stream = self.input_device(device)?.device.build_input_stream(
+            &stream_config(spec),
+            move |data: &[f32], info: &cpal::InputCallbackInfo| {
+                let timestamp = info.timestamp();
+                callback(data, timestamp.callback.duration_since(&timestamp.capture));
+            },
+            |err| eprintln!("Input stream error: {err}"),
+            None,
+        )?;
+        Ok(Box::new(CpalStream(stream)))
+    }
+
+    fn open_source(
+        &self,
+        spec: StreamSpec,
+        mut source: SampleSource,
+        mut callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        // Runs on the system clock, which is close enough to the output's for a source
+        // that is generated rather than captured
+        let frames = spec.buffer_frames.unwrap_or(DEFAULT_SOURCE_FRAMES);
+        let mut buffer = vec![0.0f32; frames as usize * spec.channels as usize];
+        let stream = ClockedStream::start(spec.sample_rate, frames, move |_| {
+            source(&mut buffer);
+            callback(&buffer, Some(Duration::ZERO));
+        });
+        Ok(Box::new(stream))
+    }
+}
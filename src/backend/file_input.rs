@@ -0,0 +1,51 @@
+use super::{SampleSource, StreamSpec};
+use crate::wav::WavFile;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A WAV file standing in for the input device. Every stream opened on it plays the
+/// file once from the start, then silence.
+#[derive(Clone)]
+pub struct FileInput {
+    path: PathBuf,
+    wav: Arc<WavFile>,
+}
+
+impl FileInput {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let wav = WavFile::load_from_file(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Ok(FileInput {
+            path: path.to_path_buf(),
+            wav: Arc::new(wav),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The file's own format.
+    pub fn format(&self) -> StreamSpec {
+        StreamSpec {
+            channels: self.wav.header.num_channels,
+            sample_rate: self.wav.header.sample_rate,
+            buffer_frames: None,
+        }
+    }
+
+    /// The file converted to `spec`, as a source for `AudioBackend::open_source`.
+    pub fn source(&self, spec: StreamSpec) -> SampleSource {
+        let samples = self
+            .wav
+            .converted(spec.sample_rate, spec.channels)
+            .to_f32_samples();
+        let mut position = 0;
+        Box::new(move |buffer: &mut [f32]| {
+            let n = buffer.len().min(samples.len() - position);
+            buffer[..n].copy_from_slice(&samples[position..position + n]);
+            buffer[n..].fill(0.0);
+            position += n;
+        })
+    }
+}
@@ -0,0 +1,63 @@
+mod clock;
+mod cpal_backend;
+mod file_input;
+mod null;
+
+pub use cpal_backend::CpalBackend;
+pub use file_input::FileInput;
+pub use null::{Clock, NullBackend};
+
+use std::time::Duration;
+
+/// Fills interleaved output. Also given the delay until the audio is heard, when known.
+pub type OutputCallback = Box<dyn FnMut(&mut [f32], Option<Duration>) + Send>;
+/// Receives interleaved input. Also given how long ago it was captured, when known.
+pub type InputCallback = Box<dyn FnMut(&[f32], Option<Duration>) + Send>;
+/// Produces interleaved input in place of a device.
+pub type SampleSource = Box<dyn FnMut(&mut [f32]) + Send>;
+
+/// Channels, rate and callback size of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamSpec {
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Frames per callback; `None` leaves it to the backend.
+    pub buffer_frames: Option<u32>,
+}
+
+/// An open stream. Callbacks run from `play` until it is dropped.
+pub trait AudioStream {
+    fn play(&self) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Where streams come from. Devices are picked by name, `None` meaning the default.
+pub trait AudioBackend: Send + Sync {
+    /// The format the output device runs at, with the buffer size left open.
+    fn output_format(&self, device: Option<&str>)
+        -> Result<StreamSpec, Box<dyn std::error::Error>>;
+
+    /// The format the input device runs at, with the buffer size left open.
+    fn input_format(&self, device: Option<&str>) -> Result<StreamSpec, Box<dyn std::error::Error>>;
+
+    fn open_output(
+        &self,
+        device: Option<&str>,
+        spec: StreamSpec,
+        callback: OutputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>>;
+
+    fn open_input(
+        &self,
+        device: Option<&str>,
+        spec: StreamSpec,
+        callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>>;
+
+    /// An input fed by `source` rather than a device, kept in step with the outputs.
+    fn open_source(
+        &self,
+        spec: StreamSpec,
+        source: SampleSource,
+        callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>>;
+}
@@ -0,0 +1,204 @@
+use super::clock::ClockedStream;
+use super::{AudioBackend, AudioStream, InputCallback, OutputCallback, SampleSource, StreamSpec};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+const DEFAULT_BUFFER_FRAMES: u32 = 256;
+
+/// How a `NullBackend` moves its streams along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    /// Each stream runs on its own thread in real time, like a device would.
+    RealTime,
+    /// Streams only run inside `NullBackend::advance`, on the caller's thread.
+    Manual,
+}
+
+/// A backend without devices, for running headless and for tests. Inputs hear silence
+/// (or whatever source they are given) and outputs go nowhere unless captured. Streams
+/// report no latency: what an input receives lines up with what the outputs play in
+/// the same block.
+pub struct NullBackend {
+    sample_rate: u32,
+    output_channels: u16,
+    input_channels: u16,
+    buffer_frames: u32,
+    clock: Clock,
+    captured: Option<Arc<Mutex<Vec<f32>>>>,
+    // Streams waiting for `advance`, only used with the manual clock
+    inputs: Mutex<Vec<Weak<ManualStream>>>,
+    outputs: Mutex<Vec<Weak<ManualStream>>>,
+}
+
+type Tick = Box<dyn FnMut(usize) + Send>;
+
+/// A stream run by `NullBackend::advance`. The backend only holds on to it weakly, so
+/// dropping it closes it.
+struct ManualStream {
+    playing: AtomicBool,
+    tick: Mutex<Tick>,
+}
+
+impl AudioStream for Arc<ManualStream> {
+    fn play(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.playing.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl NullBackend {
+    /// A real-time backend whose devices run at `sample_rate` with `channels` channels.
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        NullBackend {
+            sample_rate,
+            output_channels: channels,
+            input_channels: channels,
+            buffer_frames: DEFAULT_BUFFER_FRAMES,
+            clock: Clock::RealTime,
+            captured: None,
+            inputs: Mutex::new(Vec::new()),
+            outputs: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_input_channels(mut self, channels: u16) -> Self {
+        self.input_channels = channels;
+        self
+    }
+
+    /// Frames per callback for streams that leave it to the backend, and the block size
+    /// `advance` steps in.
+    pub fn with_buffer_frames(mut self, frames: u32) -> Self {
+        self.buffer_frames = frames.max(1);
+        self
+    }
+
+    /// Keep everything written to outputs, for `take_output`.
+    pub fn capturing(mut self) -> Self {
+        self.captured = Some(Arc::new(Mutex::new(Vec::new())));
+        self
+    }
+
+    /// With the manual clock, run every open stream for `frames` frames, one block at a
+    /// time: inputs first, then outputs. Does nothing with the real-time clock.
+    pub fn advance(&self, frames: usize) {
+        let live = |streams: &Mutex<Vec<Weak<ManualStream>>>| -> Vec<Arc<ManualStream>> {
+            let mut streams = streams.lock().unwrap();
+            streams.retain(|s| s.strong_count() > 0);
+            streams.iter().filter_map(Weak::upgrade).collect()
+        };
+        let inputs = live(&self.inputs);
+        let outputs = live(&self.outputs);
+
+        let mut done = 0;
+        while done < frames {
+            let n = (frames - done).min(self.buffer_frames as usize);
+            for stream in inputs.iter().chain(&outputs) {
+                if stream.playing.load(Ordering::Relaxed) {
+                    (stream.tick.lock().unwrap())(n);
+                }
+            }
+            done += n;
+        }
+    }
+
+    /// Everything written to outputs since the last call, interleaved. Empty unless
+    /// the backend is `capturing`.
+    pub fn take_output(&self) -> Vec<f32> {
+        self.captured
+            .as_ref()
+            .map(|c| std::mem::take(&mut *c.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    fn start(
+        &self,
+        spec: StreamSpec,
+        tick: Tick,
+        registry: &Mutex<Vec<Weak<ManualStream>>>,
+    ) -> Box<dyn AudioStream> {
+        match self.clock {
+            Clock::RealTime => {
+                let frames = spec.buffer_frames.unwrap_or(self.buffer_frames);
+                Box::new(ClockedStream::start(spec.sample_rate, frames, tick))
+            }
+            Clock::Manual => {
+                let stream = Arc::new(ManualStream {
+                    playing: AtomicBool::new(false),
+                    tick: Mutex::new(tick),
+                });
+                registry.lock().unwrap().push(Arc::downgrade(&stream));
+                Box::new(stream)
+            }
+        }
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn output_format(&self, _: Option<&str>) -> Result<StreamSpec, Box<dyn std::error::Error>> {
+        Ok(StreamSpec {
+            channels: self.output_channels,
+            sample_rate: self.sample_rate,
+            buffer_frames: None,
+        })
+    }
+
+    fn input_format(&self, _: Option<&str>) -> Result<StreamSpec, Box<dyn std::error::Error>> {
+        Ok(StreamSpec {
+            channels: self.input_channels,
+            sample_rate: self.sample_rate,
+            buffer_frames: None,
+        })
+    }
+
+    fn open_output(
+        &self,
+        _: Option<&str>,
+        spec: StreamSpec,
+        mut callback: OutputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        let channels = spec.channels.max(1) as usize;
+        let captured = self.captured.clone();
+        let mut buffer = Vec::new();
+        let tick = Box::new(move |frames: usize| {
+            buffer.clear();
+            buffer.resize(frames * channels, 0.0);
+            callback(&mut buffer, Some(Duration::ZERO));
+            if let Some(captured) = &captured {
+                captured.lock().unwrap().extend_from_slice(&buffer);
+            }
+        });
+        Ok(self.start(spec, tick, &self.outputs))
+    }
+
+    fn open_input(
+        &self,
+        _: Option<&str>,
+        spec: StreamSpec,
+        callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        self.open_source(spec, Box::new(|buffer| buffer.fill(0.0)), callback)
+    }
+
+    fn open_source(
+        &self,
+        spec: StreamSpec,
+        mut source: SampleSource,
+        mut callback: InputCallback,
+    ) -> Result<Box<dyn AudioStream>, Box<dyn std::error::Error>> {
+        let channels = spec.channels.max(1) as usize;
+        let mut buffer = Vec::new();
+        let tick = Box::new(move |frames: usize| {
+            buffer.resize(frames * channels, 0.0);
+            source(&mut buffer);
+            callback(&buffer, Some(Duration::ZERO));
+        });
+        Ok(self.start(spec, tick, &self.inputs))
+    }
+}
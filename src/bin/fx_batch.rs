@@ -0,0 +1,14 @@
+use rust_audio::cli;
+use std::env;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match cli::batch::run(&args) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::effects::EffectInstance;
+use crate::meter::Meter;
+use std::sync::Arc;
+
+/// An auxiliary return bus. Tracks feed it through sends; its FX chain processes the
+/// summed signal once and the result is added to the master mix, so a single reverb
+/// can serve the whole session.
+#[derive(Debug, Clone)]
+pub struct AuxBus {
+    pub name: String,
+    pub fx_chain: Vec<EffectInstance>,
+    pub volume: f64,
+    pub muted: bool,
+}
+
+impl AuxBus {
+    pub fn new(name: String) -> Self {
+        AuxBus {
+            name,
+            fx_chain: Vec::new(),
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+/// A track's feed into an aux bus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AuxSend {
+    pub bus: usize,
+    pub level: f32,
+    // Pre-fader sends ignore the track's volume and pan
+    pub pre_fader: bool,
+}
+
+impl AuxSend {
+    pub fn new(bus: usize) -> Self {
+        AuxSend {
+            bus,
+            level: 1.0,
+            pre_fader: false,
+        }
+    }
+}
+
+/// The master channel: the summed mix passes through its FX chain and gain
+/// before reaching the output or an export.
+pub struct MasterChannel {
+    pub fx_chain: Vec<EffectInstance>,
+    pub gain: f64,
+    // Output level, fed by the mixer during playback
+    pub meter: Arc<Meter>,
+}
+
+impl Default for MasterChannel {
+    fn default() -> Self {
+        MasterChannel {
+            fx_chain: Vec::new(),
+            gain: 1.0,
+            meter: Arc::new(Meter::new()),
+        }
+    }
+}
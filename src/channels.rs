@@ -0,0 +1,117 @@
+/// Channel count used for new sessions and mixdowns (interleaved stereo).
+pub const DEFAULT_CHANNELS: u16 = 2;
+
+/// Add one source frame into a destination frame, mapping between channel counts.
+/// - Same count: channel-for-channel.
+/// - Mono source: copied to every destination channel (centred).
+/// - Mono destination: average of all source channels.
+/// - Otherwise: destination channel `i` takes source channel `i % src.len()`,
+///   and surplus source channels are folded onto `j % dst.len()` and averaged.
+pub fn mix_frame_into(src: &[f32], dst: &mut [f32], gain: f32) {
+    let src_ch = src.len();
+    let dst_ch = dst.len();
+    if src_ch == 0 || dst_ch == 0 {
+        return;
+    }
+
+    if src_ch == dst_ch {
+        for (d, &s) in dst.iter_mut().zip(src) {
+            *d += s * gain;
+        }
+    } else if src_ch == 1 {
+        for d in dst.iter_mut() {
+            *d += src[0] * gain;
+        }
+    } else if dst_ch == 1 {
+        dst[0] += src.iter().sum::<f32>() / src_ch as f32 * gain;
+    } else if src_ch < dst_ch {
+        for (i, d) in dst.iter_mut().enumerate() {
+            *d += src[i % src_ch] * gain;
+        }
+    } else {
+        for (i, d) in dst.iter_mut().enumerate() {
+            let (sum, count) = src
+                .iter()
+                .skip(i)
+                .step_by(dst_ch)
+                .fold((0.0f32, 0usize), |(sum, count), &s| (sum + s, count + 1));
+            *d += sum / count as f32 * gain;
+        }
+    }
+}
+
+/// Convert an interleaved buffer from one channel count to another.
+pub fn remix(samples: &[f32], from: u16, to: u16) -> Vec<f32> {
+    if from == to || from == 0 || to == 0 {
+        return samples.to_vec();
+    }
+    let (from, to) = (from as usize, to as usize);
+    let frames = samples.len() / from;
+    let mut out = vec![0.0f32; frames * to];
+    for (src, dst) in samples.chunks_exact(from).zip(out.chunks_exact_mut(to)) {
+        mix_frame_into(src, dst, 1.0);
+    }
+    out
+}
+
+/// Apply a balance-style pan to an interleaved stereo buffer.
+/// `pan` ranges from -1.0 (hard left) to 1.0 (hard right); the centre is unity gain,
+/// and moving away from it attenuates the opposite side linearly.
+/// Buffers with a channel count other than 2 are left untouched.
+pub fn apply_balance(buffer: &mut [f32], channels: u16, pan: f32) {
+    if channels != 2 || pan == 0.0 {
+        return;
+    }
+    let (left, right) = balance_gains(pan);
+    for frame in buffer.chunks_exact_mut(2) {
+        frame[0] *= left;
+        frame[1] *= right;
+    }
+}
+
+/// (left, right) gains for a balance pan position in -1.0..=1.0.
+pub fn balance_gains(pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    if pan < 0.0 {
+        (1.0, 1.0 + pan)
+    } else {
+        (1.0 - pan, 1.0)
+    }
+}
+
+/// Split an interleaved buffer into one Vec per channel.
+pub fn deinterleave(samples: &[f32], channels: u16) -> Vec<Vec<f32>> {
+    let ch = channels.max(1) as usize;
+    let mut planes = vec![Vec::with_capacity(samples.len() / ch); ch];
+    for frame in samples.chunks_exact(ch) {
+        for (plane, &s) in planes.iter_mut().zip(frame) {
+            plane.push(s);
+        }
+    }
+    planes
+}
+
+/// Interleave per-channel buffers. Shorter channels are padded with silence.
+pub fn interleave(planes: &[Vec<f32>]) -> Vec<f32> {
+    let ch = planes.len();
+    let frames = planes.iter().map(|p| p.len()).max().unwrap_or(0);
+    let mut out = vec![0.0f32; frames * ch];
+    for (c, plane) in planes.iter().enumerate() {
+        for (f, &s) in plane.iter().enumerate() {
+            out[f * ch + c] = s;
+        }
+    }
+    out
+}
+
+/// Short label for a pan position: "C", "L30", "R100".
+pub fn format_pan(pan: f32) -> String {
+    let amount = (pan.abs() * 100.0).round() as u32;
+    if amount == 0 {
+        "C".to_string()
+    } else if pan < 0.0 {
+        format!("L{}", amount)
+    } else {
+        format!("R{}", amount)
+    }
+}
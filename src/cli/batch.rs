@@ -0,0 +1,318 @@
+use super::flag_value;
+use crate::effects::{EffectInstance, EffectType};
+use crate::project::{self, FxManifest};
+use crate::wav::WavFile;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use strum::IntoEnumIterator;
+
+const USAGE: &str = "Usage: fx_batch [options] <input.wav | pattern>...
+
+Runs an FX chain over WAV files in parallel. Patterns may use * and ? in the file
+name (e.g. \"takes/*.wav\") for shells that do not expand them.
+
+Options:
+  --fx <effect[:param=value,...]>
+                       Add an effect to the chain, in order (e.g. --fx delay:ms=250)
+  --chain <file.json>  Read the chain from a JSON list of {\"effect_type\", \"parameters\"}
+                       entries, as stored in project files
+  -o <dir>             Folder for the processed files (default: next to each input)
+  --suffix <text>      Added to each output file name (default: _fx)
+  -j <threads>         Files processed at once (default: one per CPU)
+  --list               Show the available effects and their parameters";
+
+const VALUE_FLAGS: [&str; 5] = ["--fx", "--chain", "-o", "--suffix", "-j"];
+const DEFAULT_SUFFIX: &str = "_fx";
+
+/// `fx_batch`: process every input through the chain. Returns whether every file
+/// was processed.
+pub fn run(args: &[String]) -> Result<bool, Box<dyn std::error::Error>> {
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return Ok(true);
+    }
+    if args.iter().any(|a| a == "--list") {
+        list_effects();
+        return Ok(true);
+    }
+
+    let chain = parse_chain(args)?;
+    let inputs = expand_inputs(&positional(args)?)?;
+    let out_dir = flag_value(args, "-o")?.map(PathBuf::from);
+    let suffix = flag_value(args, "--suffix")?.unwrap_or(DEFAULT_SUFFIX);
+    let threads = match super::parse_flag::<usize>(args, "-j")? {
+        Some(0) => return Err("-j must be at least 1".into()),
+        Some(n) => n,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    if let Some(dir) = &out_dir {
+        fs::create_dir_all(dir)?;
+    }
+
+    let jobs: Vec<(PathBuf, PathBuf)> = inputs
+        .into_iter()
+        .map(|input| {
+            let output = output_path(&input, out_dir.as_deref(), suffix);
+            (input, output)
+        })
+        .collect();
+    check_outputs(&jobs)?;
+
+    let failures = process_all(&jobs, &chain, threads);
+    eprintln!(
+        "Processed {} of {} files, {} failed",
+        jobs.len() - failures,
+        jobs.len(),
+        failures
+    );
+    Ok(failures == 0)
+}
+
+/// Arguments that are neither flags nor a flag's value.
+fn positional(args: &[String]) -> Result<Vec<String>, String> {
+    let mut inputs = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            i += 2;
+            continue;
+        }
+        if arg.starts_with('-') {
+            return Err(format!("Unknown option: {}\n\n{}", arg, USAGE));
+        }
+        inputs.push(arg.clone());
+        i += 1;
+    }
+    if inputs.is_empty() {
+        return Err(format!("No input files\n\n{}", USAGE));
+    }
+    Ok(inputs)
+}
+
+/// The chain from every `--fx` in order, or from `--chain`.
+fn parse_chain(args: &[String]) -> Result<Vec<EffectInstance>, Box<dyn std::error::Error>> {
+    let fx_args: Vec<&str> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--fx")
+        .map(|pair| pair[1].as_str())
+        .collect();
+    let manifests = match (flag_value(args, "--chain")?, fx_args.is_empty()) {
+        (Some(_), false) => return Err("Use either --fx or --chain, not both".into()),
+        (Some(file), true) => {
+            let json = fs::read_to_string(file)
+                .map_err(|e| format!("Cannot read chain {}: {}", file, e))?;
+            serde_json::from_str::<Vec<FxManifest>>(&json)
+                .map_err(|e| format!("Invalid chain {}: {}", file, e))?
+        }
+        (None, false) => fx_args
+            .into_iter()
+            .map(parse_fx)
+            .collect::<Result<_, _>>()?,
+        (None, true) => return Err("No effects given; use --fx or --chain".into()),
+    };
+    project::load_fx_chain(manifests)
+}
+
+/// `name[:param=value,...]`, with the name matched loosely ("large-reverb",
+/// "LargeReverb" and "Large Reverb" are the same effect).
+fn parse_fx(spec: &str) -> Result<FxManifest, String> {
+    let (name, params) = spec.split_once(':').unwrap_or((spec, ""));
+    let effect_type = EffectType::iter()
+        .find(|et| loose_name(&et.name()) == loose_name(name))
+        .ok_or_else(|| format!("Unknown effect: {} (see --list)", name))?;
+    let parameters = params
+        .split(',')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            p.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .ok_or_else(|| format!("Expected param=value in {}, got {}", spec, p))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(FxManifest {
+        effect_type: effect_type.name(),
+        parameters,
+    })
+}
+
+fn loose_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn list_effects() {
+    for effect_type in EffectType::iter() {
+        let params: Vec<String> = effect_type
+            .create_default()
+            .parameters()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        if params.is_empty() {
+            println!("{}", effect_type.name());
+        } else {
+            println!("{} ({})", effect_type.name(), params.join(", "));
+        }
+    }
+}
+
+/// Inputs in order, with file-name patterns expanded and sorted.
+fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if !name.contains(['*', '?']) {
+            files.push(path.to_path_buf());
+            continue;
+        }
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        let mut matches: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| wildcard_match(name, n))
+            })
+            .collect();
+        if matches.is_empty() {
+            return Err(format!("No files match {}", input));
+        }
+        matches.sort();
+        files.extend(matches);
+    }
+    Ok(files)
+}
+
+/// Whether `text` matches `pattern`, where `*` is any run of characters and `?` is
+/// any one character.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last * was and the text position it is currently standing in for
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn output_path(input: &Path, out_dir: Option<&Path>, suffix: &str) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file = format!("{}{}.wav", stem, suffix);
+    match out_dir {
+        Some(dir) => dir.join(file),
+        None => input.with_file_name(file),
+    }
+}
+
+/// Refuse jobs that would write over an input, or over each other's output.
+fn check_outputs(jobs: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    let inputs: HashSet<PathBuf> = jobs.iter().map(|(input, _)| resolved(input)).collect();
+    let mut outputs = HashMap::new();
+    for (input, output) in jobs {
+        let key = resolved(output);
+        if inputs.contains(&key) {
+            return Err(format!("Output would overwrite input {}", output.display()));
+        }
+        if let Some(other) = outputs.insert(key, input) {
+            return Err(format!(
+                "{} and {} would both be written to {}",
+                other.display(),
+                input.display(),
+                output.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `path` with its folder resolved, so different spellings of one file compare equal.
+/// The file itself need not exist.
+fn resolved(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(dir), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Process the jobs on `threads` workers, reporting each file as it finishes.
+/// Returns how many failed.
+fn process_all(jobs: &[(PathBuf, PathBuf)], chain: &[EffectInstance], threads: usize) -> usize {
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let failures = AtomicUsize::new(0);
+    // Keeps each report on its own line
+    let report = Mutex::new(());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.min(jobs.len()) {
+            scope.spawn(|| {
+                while let Some((input, output)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = process_file(input, output, chain);
+                    let _guard = report.lock().unwrap();
+                    let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                    match result {
+                        Ok(()) => eprintln!(
+                            "[{}/{}] {} -> {}",
+                            done,
+                            jobs.len(),
+                            input.display(),
+                            output.display()
+                        ),
+                        Err(e) => {
+                            failures.fetch_add(1, Ordering::Relaxed);
+                            eprintln!(
+                                "[{}/{}] FAILED {}: {}",
+                                done,
+                                jobs.len(),
+                                input.display(),
+                                e
+                            );
+                        }
+                    }
+                }
+            });
+        }
+    });
+    failures.into_inner()
+}
+
+fn process_file(input: &Path, output: &Path, chain: &[EffectInstance]) -> Result<(), String> {
+    let mut wav = WavFile::load_from_file(input).map_err(|e| e.to_string())?;
+    wav.apply_effects(chain.to_vec())
+        .map_err(|e| e.to_string())?;
+    wav.save_to_file(output).map_err(|e| e.to_string())
+}
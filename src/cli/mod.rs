@@ -0,0 +1,23 @@
+pub mod batch;
+pub mod render;
+
+/// The value following `flag` in `args`, if the flag is present.
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => args
+            .get(i + 1)
+            .map(|v| Some(v.as_str()))
+            .ok_or_else(|| format!("{} needs a value", flag)),
+        None => Ok(None),
+    }
+}
+
+/// Parse the value following `flag`, naming the flag if it does not parse.
+fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+    flag_value(args, flag)?
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("Invalid value for {}: {}", flag, v))
+        })
+        .transpose()
+}
@@ -0,0 +1,181 @@
+use super::{flag_value, parse_flag};
+use crate::export::{ExportJob, ExportRange, ExportSettings, ExportSource, Normalize};
+use crate::project;
+use crate::wav::SampleFormat;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: rust-audio render <project_dir> [options]
+
+Renders a saved project offline, without opening an audio device.
+
+Options:
+  -o <path>              Output file for a mix, or folder for stems
+                         (default: <project_dir>/<name>_mix.wav or <name>_stems)
+  --stems <tracks|buses> Write one file per track or aux bus instead of the mix
+  --range <project|loop|START-END>
+                         Timeline span in seconds (default: project)
+  --rate <hz>            Sample rate (default: the project's)
+  --format <16|24|32f>   Bit depth, 32f for float (default: 16)
+  --channels <1|2>       Channel count (default: the project's)
+  --tail <auto|seconds>  Time for effects to ring out after the range (default: auto)
+  --peak <dbfs>          Normalise the peak to this level
+  --lufs <lufs>          Normalise the integrated loudness to this level
+  --ceiling <dbtp>       True-peak ceiling for --lufs (default: -1.0)";
+
+const VALUE_FLAGS: [&str; 10] = [
+    "-o",
+    "--stems",
+    "--range",
+    "--rate",
+    "--format",
+    "--channels",
+    "--tail",
+    "--peak",
+    "--lufs",
+    "--ceiling",
+];
+const DEFAULT_CEILING_DBTP: f32 = -1.0;
+const PROGRESS_POLL_MS: u64 = 200;
+// Progress is reported each time it passes another step
+const PROGRESS_STEP_PERCENT: u32 = 10;
+
+/// `rust-audio render`: load a project and export it like the export dialog would.
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let project_dir = project_dir(args)?;
+    let session = project::load_project(&project_dir)
+        .map_err(|e| format!("Cannot load {}: {}", project_dir.display(), e))?;
+    let settings = parse_settings(args, session.sample_rate, session.channels)?;
+    let output = match flag_value(args, "-o")? {
+        Some(path) => PathBuf::from(path),
+        None if settings.source == ExportSource::Mix => {
+            project_dir.join(format!("{}_mix.wav", session.name))
+        }
+        None => project_dir.join(format!("{}_stems", session.name)),
+    };
+
+    let renders = session.export_renders(&settings)?;
+    let job = ExportJob::start(renders, settings, output);
+    let mut reported = 0;
+    while !job.is_finished() {
+        std::thread::sleep(Duration::from_millis(PROGRESS_POLL_MS));
+        let percent = (job.progress().fraction() * 100.0) as u32;
+        if percent >= reported + PROGRESS_STEP_PERCENT {
+            reported = percent - percent % PROGRESS_STEP_PERCENT;
+            eprintln!("Rendering {}%", reported);
+        }
+    }
+    for path in job.finish()? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// The one argument that is neither a flag nor a flag's value.
+fn project_dir(args: &[String]) -> Result<PathBuf, String> {
+    let mut positional = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            i += 2;
+            continue;
+        }
+        if arg.starts_with('-') {
+            return Err(format!("Unknown option: {}\n\n{}", arg, USAGE));
+        }
+        positional.push(arg);
+        i += 1;
+    }
+    match positional.as_slice() {
+        [dir] => Ok(PathBuf::from(dir)),
+        [] => Err(format!("Missing project directory\n\n{}", USAGE)),
+        _ => Err(format!("Expected one project directory\n\n{}", USAGE)),
+    }
+}
+
+fn parse_settings(
+    args: &[String],
+    sample_rate: u32,
+    channels: u16,
+) -> Result<ExportSettings, String> {
+    let mut settings = ExportSettings::new(sample_rate, channels);
+
+    settings.source = match flag_value(args, "--stems")? {
+        None => ExportSource::Mix,
+        Some("tracks") => ExportSource::TrackStems,
+        Some("buses") => ExportSource::BusStems,
+        Some(other) => return Err(format!("--stems takes tracks or buses, not {}", other)),
+    };
+    if let Some(range) = flag_value(args, "--range")? {
+        settings.range = parse_range(range, sample_rate)?;
+    }
+    if let Some(rate) = parse_flag::<u32>(args, "--rate")? {
+        if rate == 0 {
+            return Err("--rate must be above zero".to_string());
+        }
+        settings.sample_rate = rate;
+    }
+    settings.format = match flag_value(args, "--format")? {
+        None | Some("16") => SampleFormat::Int16,
+        Some("24") => SampleFormat::Int24,
+        Some("32f") | Some("float") => SampleFormat::Float32,
+        Some(other) => return Err(format!("--format takes 16, 24 or 32f, not {}", other)),
+    };
+    if let Some(count) = parse_flag::<u16>(args, "--channels")? {
+        if !(1..=2).contains(&count) {
+            return Err("--channels takes 1 or 2".to_string());
+        }
+        settings.channels = count;
+    }
+    settings.tail_seconds = match flag_value(args, "--tail")? {
+        None | Some("auto") => None,
+        Some(seconds) => match seconds.parse::<f64>() {
+            Ok(s) if s >= 0.0 => Some(s),
+            _ => return Err(format!("--tail takes auto or seconds, not {}", seconds)),
+        },
+    };
+
+    let peak = parse_flag::<f32>(args, "--peak")?;
+    let lufs = parse_flag::<f32>(args, "--lufs")?;
+    let ceiling = parse_flag::<f32>(args, "--ceiling")?;
+    settings.normalize = match (peak, lufs) {
+        (Some(_), Some(_)) => return Err("Use either --peak or --lufs, not both".to_string()),
+        (Some(dbfs), None) => Normalize::Peak(dbfs),
+        (None, Some(lufs)) => Normalize::Loudness {
+            lufs,
+            ceiling: ceiling.unwrap_or(DEFAULT_CEILING_DBTP),
+        },
+        (None, None) if ceiling.is_some() => {
+            return Err("--ceiling only applies with --lufs".to_string())
+        }
+        (None, None) => Normalize::Off,
+    };
+    Ok(settings)
+}
+
+fn parse_range(range: &str, sample_rate: u32) -> Result<ExportRange, String> {
+    match range {
+        "project" => return Ok(ExportRange::Project),
+        "loop" => return Ok(ExportRange::Loop),
+        _ => {}
+    }
+    let invalid = || {
+        format!(
+            "--range takes project, loop or START-END in seconds, not {}",
+            range
+        )
+    };
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let start: f64 = start.parse().map_err(|_| invalid())?;
+    let end: f64 = end.parse().map_err(|_| invalid())?;
+    if start < 0.0 || end <= start {
+        return Err(format!("--range end must come after its start: {}", range));
+    }
+    let frame = |seconds: f64| (seconds * sample_rate as f64) as u64;
+    Ok(ExportRange::Custom(frame(start)..frame(end)))
+}
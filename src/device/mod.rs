@@ -1,5 +1,11 @@
 use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::{Device, SampleRate, StreamConfig};
+use cpal::{Device, SampleRate, StreamConfig, SupportedBufferSize, SupportedStreamConfigRange};
+use std::ops::RangeInclusive;
+
+// Rates listed for devices that accept a range of them
+const COMMON_SAMPLE_RATES: [u32; 11] = [
+    8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000,
+];
 
 pub struct Input;
 pub struct Output;
@@ -30,16 +36,95 @@ impl AudioDevice {
     }
 }
 
-pub trait DeviceProvider {
+/// Everything a device can be opened with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceCapabilities {
+    pub sample_rates: Vec<u32>,
+    pub channels: Vec<u16>,
+    /// As the host names them, e.g. "i16" or "f32".
+    pub sample_formats: Vec<String>,
+    /// Smallest and largest frames per callback, if the host reports them.
+    pub buffer_frames: Option<RangeInclusive<u32>>,
+}
+
+impl DeviceCapabilities {
+    fn from_configs(configs: impl Iterator<Item = SupportedStreamConfigRange>) -> Self {
+        let mut capabilities = DeviceCapabilities::default();
+        for config in configs {
+            let rates = config.min_sample_rate().0..=config.max_sample_rate().0;
+            capabilities.sample_rates.extend(
+                COMMON_SAMPLE_RATES
+                    .iter()
+                    .copied()
+                    .filter(|rate| rates.contains(rate)),
+            );
+            // Ends of the range, in case the device only runs at an unusual rate
+            capabilities
+                .sample_rates
+                .extend([*rates.start(), *rates.end()]);
+            capabilities.channels.push(config.channels());
+            capabilities
+                .sample_formats
+                .push(config.sample_format().to_string());
+            if let SupportedBufferSize::Range { min, max } = *config.buffer_size() {
+                capabilities.buffer_frames = Some(match capabilities.buffer_frames {
+                    Some(frames) => (*frames.start()).min(min)..=(*frames.end()).max(max),
+                    None => min..=max,
+                });
+            }
+        }
+        capabilities.sample_rates.sort_unstable();
+        capabilities.sample_rates.dedup();
+        capabilities.channels.sort_unstable();
+        capabilities.channels.dedup();
+        capabilities.sample_formats.sort();
+        capabilities.sample_formats.dedup();
+        capabilities
+    }
+}
+
+/// The devices in one direction. Implemented for the system's devices by `Input` and
+/// `Output`; other implementations can stand in for them, e.g. in tests.
+pub trait DeviceProvider: Send + Sync {
     fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error>>;
     fn default(&self) -> Result<AudioDevice, Box<dyn std::error::Error>>;
     fn default_name(&self) -> Option<String>;
     fn by_index(&self, index: usize) -> Result<AudioDevice, Box<dyn std::error::Error>>;
     fn by_name(&self, name: &str) -> Result<AudioDevice, Box<dyn std::error::Error>>;
+    fn capabilities(&self, name: &str) -> Result<DeviceCapabilities, Box<dyn std::error::Error>>;
+}
+
+/// A provider with no devices, for running headless or with a `NullBackend`.
+pub struct NoDevices;
+
+impl DeviceProvider for NoDevices {
+    fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(Vec::new())
+    }
+
+    fn default(&self) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        Err("No devices".into())
+    }
+
+    fn default_name(&self) -> Option<String> {
+        None
+    }
+
+    fn by_index(&self, _: usize) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        Err("No devices".into())
+    }
+
+    fn by_name(&self, _: &str) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        Err("No devices".into())
+    }
+
+    fn capabilities(&self, _: &str) -> Result<DeviceCapabilities, Box<dyn std::error::Error>> {
+        Err("No devices".into())
+    }
 }
 
 macro_rules! impl_device_provider {
-    ($type:ty, $devices_fn:ident, $default_fn:ident, $config_fn:ident, $no_device_msg:expr) => {
+    ($type:ty, $devices_fn:ident, $default_fn:ident, $config_fn:ident, $supported_fn:ident, $no_device_msg:expr) => {
         impl DeviceProvider for $type {
             fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
                 let host = cpal::default_host();
@@ -79,9 +164,18 @@ macro_rules! impl_device_provider {
                 let config: StreamConfig = device.$config_fn()?.into();
                 Ok(AudioDevice::from_device_with_config(device, config))
             }
+
+            fn capabilities(&self, name: &str) -> Result<DeviceCapabilities, Box<dyn std::error::Error>> {
+                let host = cpal::default_host();
+                let device = host
+                    .$devices_fn()?
+                    .find(|d| d.name().ok().as_deref() == Some(name))
+                    .ok_or("Device not found")?;
+                Ok(DeviceCapabilities::from_configs(device.$supported_fn()?))
+            }
         }
     };
 }
 
-impl_device_provider!(Input, input_devices, default_input_device, default_input_config, "No input device available");
-impl_device_provider!(Output, output_devices, default_output_device, default_output_config, "No output device available");
+impl_device_provider!(Input, input_devices, default_input_device, default_input_config, supported_input_configs, "No input device available");
+impl_device_provider!(Output, output_devices, default_output_device, default_output_config, supported_output_configs, "No output device available");
@@ -1,4 +1,4 @@
-use super::{EffectBox, EffectTrait};
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
 use std::any::TypeId;
 use std::fmt;
 
@@ -74,7 +74,27 @@ impl EffectTrait for AdjustVolume {
         Ok(())
     }
 
+    fn processor(&self, _sample_rate: u32, _channels: u16) -> Option<ProcessorBox> {
+        Some(Box::new(AdjustVolumeProcessor(self.0)))
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<AdjustVolume>()
     }
 }
+
+struct AdjustVolumeProcessor(f32);
+
+impl EffectProcessor for AdjustVolumeProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        for sample in block.iter_mut() {
+            *sample = (*sample * self.0).clamp(-1.0, 1.0);
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == "volume" {
+            self.0 = value.clamp(0.0, 2.0);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{EffectBox, EffectTrait};
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
 use std::any::TypeId;
 use std::fmt;
 
@@ -111,7 +111,78 @@ impl EffectTrait for Delay {
         Ok(())
     }
 
+    fn processor(&self, sample_rate: u32, channels: u16) -> Option<ProcessorBox> {
+        let delay_frames = (self.ms * sample_rate as usize) / 1000;
+        let channels = channels.max(1) as usize;
+        let history_frames = delay_frames * self.taps + 1;
+        Some(Box::new(DelayProcessor {
+            delay_frames,
+            sample_rate,
+            taps: self.taps,
+            channels,
+            history: vec![0.0; history_frames * channels],
+            write_frame: 0,
+        }))
+    }
+
+    fn tail_seconds(&self) -> f32 {
+        (self.ms * self.taps) as f32 / 1000.0
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<Delay>()
     }
 }
+
+/// Streaming multi-tap delay: keeps the last `delay * taps` input frames per channel
+/// and sums each tap with the same gains as the offline `apply`.
+struct DelayProcessor {
+    delay_frames: usize,
+    sample_rate: u32,
+    taps: usize,
+    channels: usize,
+    history: Vec<f32>, // interleaved ring of past input frames
+    write_frame: usize,
+}
+
+impl EffectProcessor for DelayProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        if self.delay_frames == 0 {
+            return;
+        }
+        let history_frames = self.history.len() / self.channels;
+
+        for frame in block.chunks_exact_mut(self.channels) {
+            let write = self.write_frame * self.channels;
+            self.history[write..write + self.channels].copy_from_slice(frame);
+
+            for tap in 1..=self.taps {
+                let feedback_gain = 0.5 / tap as f32;
+                let read_frame =
+                    (self.write_frame + history_frames - self.delay_frames * tap) % history_frames;
+                let read = read_frame * self.channels;
+                for (ch, sample) in frame.iter_mut().enumerate() {
+                    *sample += self.history[read + ch] * feedback_gain;
+                }
+            }
+
+            for sample in frame.iter_mut() {
+                // Prevent clipping
+                *sample = sample.clamp(-1.0, 1.0);
+            }
+
+            self.write_frame = (self.write_frame + 1) % history_frames;
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == "ms" {
+            // The history only holds the delay the processor was built with; automated
+            // chains build it for the longest delay their lane reaches
+            let history_frames = self.history.len() / self.channels;
+            let longest = (history_frames - 1) / self.taps;
+            let ms = value.round().clamp(1.0, 5000.0) as usize;
+            self.delay_frames = (ms * self.sample_rate as usize / 1000).min(longest);
+        }
+    }
+}
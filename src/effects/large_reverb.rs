@@ -1,4 +1,4 @@
-use super::{EffectBox, EffectTrait};
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
 use std::any::TypeId;
 use std::fmt;
 
@@ -41,17 +41,8 @@ impl EffectTrait for LargeReverb {
     }
 
     fn apply(&self, samples: &mut Vec<f32>, sample_rate: u32) -> Result<(), &'static str> {
-        let comb_delays = vec![
-            (sample_rate as f32 * 0.0897) as usize, // ~90ms
-            (sample_rate as f32 * 0.1171) as usize, // ~117ms
-            (sample_rate as f32 * 0.1411) as usize, // ~141ms
-            (sample_rate as f32 * 0.1637) as usize, // ~164ms
-            (sample_rate as f32 * 0.1893) as usize, // ~189ms
-            (sample_rate as f32 * 0.2159) as usize, // ~216ms
-        ];
-
-        let feedback: f32 = 0.75;
-        let wet_level: f32 = 0.6;
+        let comb_delays = comb_delays(sample_rate);
+
         let mut reverb_output: Vec<f32> = vec![0.0; samples.len()];
 
         for delay in comb_delays {
@@ -60,7 +51,7 @@ impl EffectTrait for LargeReverb {
 
             for (i, &input) in samples.iter().enumerate() {
                 let delayed_sample = delay_buffer[buffer_index];
-                let output = input + delayed_sample * feedback;
+                let output = input + delayed_sample * FEEDBACK;
 
                 delay_buffer[buffer_index] = output;
                 buffer_index = (buffer_index + 1) % delay;
@@ -70,14 +61,79 @@ impl EffectTrait for LargeReverb {
         }
 
         for (i, sample) in samples.iter_mut().enumerate() {
-            *sample = *sample * (1.0 - wet_level) + reverb_output[i] * wet_level / 6.0;
+            *sample = *sample * (1.0 - WET_LEVEL) + reverb_output[i] * WET_LEVEL / 6.0;
             *sample = sample.clamp(-1.0, 1.0);
         }
 
         Ok(())
     }
 
+    fn processor(&self, sample_rate: u32, channels: u16) -> Option<ProcessorBox> {
+        let channels = channels.max(1) as usize;
+        let combs = (0..channels)
+            .map(|_| {
+                comb_delays(sample_rate)
+                    .into_iter()
+                    .map(|delay| Comb {
+                        buffer: vec![0.0; delay.max(1)],
+                        index: 0,
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(Box::new(LargeReverbProcessor { channels, combs }))
+    }
+
+    fn tail_seconds(&self) -> f32 {
+        // Longest comb (~216ms) has decayed by ~35dB after 14 passes at 0.75 feedback
+        3.0
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<LargeReverb>()
     }
 }
+
+const FEEDBACK: f32 = 0.75;
+const WET_LEVEL: f32 = 0.6;
+
+fn comb_delays(sample_rate: u32) -> Vec<usize> {
+    vec![
+        (sample_rate as f32 * 0.0897) as usize, // ~90ms
+        (sample_rate as f32 * 0.1171) as usize, // ~117ms
+        (sample_rate as f32 * 0.1411) as usize, // ~141ms
+        (sample_rate as f32 * 0.1637) as usize, // ~164ms
+        (sample_rate as f32 * 0.1893) as usize, // ~189ms
+        (sample_rate as f32 * 0.2159) as usize, // ~216ms
+    ]
+}
+
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+/// Streaming version of the parallel comb bank: one set of combs per channel.
+struct LargeReverbProcessor {
+    channels: usize,
+    combs: Vec<Vec<Comb>>,
+}
+
+impl EffectProcessor for LargeReverbProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        for frame in block.chunks_exact_mut(self.channels) {
+            for (sample, combs) in frame.iter_mut().zip(self.combs.iter_mut()) {
+                let input = *sample;
+                let mut reverb = 0.0;
+                for comb in combs.iter_mut() {
+                    let output = input + comb.buffer[comb.index] * FEEDBACK;
+                    comb.buffer[comb.index] = output;
+                    comb.index = (comb.index + 1) % comb.buffer.len();
+                    reverb += output;
+                }
+                let wet = input * (1.0 - WET_LEVEL) + reverb * WET_LEVEL / combs.len() as f32;
+                *sample = wet.clamp(-1.0, 1.0);
+            }
+        }
+    }
+}
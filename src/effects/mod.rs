@@ -36,10 +36,54 @@ pub trait EffectTrait: std::fmt::Debug + Send + Sync {
     fn update_parameter_boxed(&self, param_name: &str, value: &str) -> Result<EffectBox, String>;
     fn apply(&self, samples: &mut Vec<f32>, sample_rate: u32) -> Result<(), &'static str>;
 
+    /// Apply to an interleaved buffer with `channels` channels.
+    /// By default each channel is processed independently through `apply`;
+    /// effects that work across channels (like panning) override this.
+    fn apply_interleaved(
+        &self,
+        samples: &mut Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<(), &'static str> {
+        if channels <= 1 {
+            return self.apply(samples, sample_rate);
+        }
+        let mut planes = crate::channels::deinterleave(samples, channels);
+        for plane in planes.iter_mut() {
+            self.apply(plane, sample_rate)?;
+        }
+        *samples = crate::channels::interleave(&planes);
+        Ok(())
+    }
+
+    /// Streaming processor used by the real-time mixer, or None when the effect
+    /// needs the whole buffer at once (e.g. Reverse) and must be rendered offline.
+    fn processor(&self, _sample_rate: u32, _channels: u16) -> Option<ProcessorBox> {
+        None
+    }
+
+    /// How long the effect keeps producing sound after its input goes silent.
+    fn tail_seconds(&self) -> f32 {
+        0.0
+    }
+
     // Type identification for trait objects
     fn type_id(&self) -> TypeId;
 }
 
+pub type ProcessorBox = Box<dyn EffectProcessor>;
+
+/// Stateful, block-based counterpart of an effect, owned by the audio thread.
+/// Blocks are interleaved with the channel count given when the processor was created.
+pub trait EffectProcessor: Send {
+    fn process(&mut self, block: &mut [f32]);
+
+    /// Change a numeric parameter between blocks, for automation. Values outside the
+    /// parameter's range are clamped; parameters that cannot change while streaming
+    /// are ignored.
+    fn set_parameter(&mut self, _name: &str, _value: f32) {}
+}
+
 // EffectType enum for registry iteration
 #[derive(Debug, Clone, EnumIter, PartialEq, Eq)]
 pub enum EffectType {
@@ -97,6 +141,24 @@ impl EffectInstance {
         self.effect.apply(samples, sample_rate)
     }
 
+    pub fn apply_interleaved(
+        &self,
+        samples: &mut Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<(), &'static str> {
+        self.effect
+            .apply_interleaved(samples, sample_rate, channels)
+    }
+
+    pub fn processor(&self, sample_rate: u32, channels: u16) -> Option<ProcessorBox> {
+        self.effect.processor(sample_rate, channels)
+    }
+
+    pub fn tail_seconds(&self) -> f32 {
+        self.effect.tail_seconds()
+    }
+
     pub fn effect_type(&self) -> EffectType {
         self.effect_type.clone()
     }
@@ -1,4 +1,4 @@
-use super::{EffectBox, EffectTrait};
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
 use std::any::TypeId;
 use std::fmt;
 
@@ -17,21 +17,11 @@ impl fmt::Display for PanDirection {
     }
 }
 
+#[derive(Default)]
 pub struct PanLeft(pub u8);
+#[derive(Default)]
 pub struct PanRight(pub u8);
 
-impl Default for PanLeft {
-    fn default() -> Self {
-        PanLeft(0)
-    }
-}
-
-impl Default for PanRight {
-    fn default() -> Self {
-        PanRight(0)
-    }
-}
-
 impl fmt::Debug for PanLeft {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "PanLeft({})", self.0)
@@ -44,7 +34,7 @@ impl fmt::Debug for PanRight {
     }
 }
 
-fn pan_apply(samples: &mut Vec<f32>, amount: u8, direction: PanDirection) -> Result<(), &'static str> {
+fn pan_apply(samples: &mut [f32], amount: u8, direction: PanDirection) -> Result<(), &'static str> {
     if amount > 100 {
         return Err("Pan amount must be 0-100");
     }
@@ -102,6 +92,18 @@ impl EffectTrait for PanLeft {
         pan_apply(samples, self.0, PanDirection::Left)
     }
 
+    fn apply_interleaved(&self, samples: &mut Vec<f32>, _sample_rate: u32, channels: u16) -> Result<(), &'static str> {
+        // Panning only makes sense on an interleaved stereo pair
+        if channels != 2 {
+            return Ok(());
+        }
+        pan_apply(samples, self.0, PanDirection::Left)
+    }
+
+    fn processor(&self, _sample_rate: u32, channels: u16) -> Option<ProcessorBox> {
+        Some(Box::new(PanProcessor { amount: self.0, direction: PanDirection::Left, stereo: channels == 2 }))
+    }
+
     fn type_id(&self) -> TypeId { TypeId::of::<PanLeft>() }
 }
 
@@ -124,5 +126,37 @@ impl EffectTrait for PanRight {
         pan_apply(samples, self.0, PanDirection::Right)
     }
 
+    fn apply_interleaved(&self, samples: &mut Vec<f32>, _sample_rate: u32, channels: u16) -> Result<(), &'static str> {
+        // Panning only makes sense on an interleaved stereo pair
+        if channels != 2 {
+            return Ok(());
+        }
+        pan_apply(samples, self.0, PanDirection::Right)
+    }
+
+    fn processor(&self, _sample_rate: u32, channels: u16) -> Option<ProcessorBox> {
+        Some(Box::new(PanProcessor { amount: self.0, direction: PanDirection::Right, stereo: channels == 2 }))
+    }
+
     fn type_id(&self) -> TypeId { TypeId::of::<PanRight>() }
 }
+
+struct PanProcessor {
+    amount: u8,
+    direction: PanDirection,
+    stereo: bool,
+}
+
+impl EffectProcessor for PanProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        if self.stereo {
+            let _ = pan_apply(block, self.amount, self.direction);
+        }
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f32) {
+        if name == "amount" {
+            self.amount = value.round().clamp(0.0, 100.0) as u8;
+        }
+    }
+}
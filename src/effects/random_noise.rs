@@ -1,5 +1,6 @@
-use super::{EffectBox, EffectTrait};
-use rand::Rng;
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::any::TypeId;
 use std::fmt;
 
@@ -42,18 +43,33 @@ impl EffectTrait for RandomNoise {
     }
 
     fn apply(&self, samples: &mut Vec<f32>, _sample_rate: u32) -> Result<(), &'static str> {
-        let mut rng = rand::thread_rng();
-
-        for sample in samples.iter_mut() {
-            // Add random noise with small amplitude
-            let noise: f32 = rng.gen_range(-0.1..0.1);
-            *sample += noise;
-            *sample = sample.clamp(-1.0, 1.0);
-        }
+        add_noise(samples, &mut rand::thread_rng());
         Ok(())
     }
 
+    fn processor(&self, _sample_rate: u32, _channels: u16) -> Option<ProcessorBox> {
+        // Seed here so the audio thread never touches the OS entropy source
+        Some(Box::new(RandomNoiseProcessor(StdRng::from_entropy())))
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<RandomNoise>()
     }
 }
+
+struct RandomNoiseProcessor(StdRng);
+
+impl EffectProcessor for RandomNoiseProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        add_noise(block, &mut self.0);
+    }
+}
+
+fn add_noise(samples: &mut [f32], rng: &mut impl Rng) {
+    for sample in samples.iter_mut() {
+        // Add random noise with small amplitude
+        let noise: f32 = rng.gen_range(-0.1..0.1);
+        *sample += noise;
+        *sample = sample.clamp(-1.0, 1.0);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{EffectBox, EffectTrait};
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
 use std::any::TypeId;
 use std::fmt;
 
@@ -41,23 +41,37 @@ impl EffectTrait for TapeSaturation {
     }
 
     fn apply(&self, samples: &mut Vec<f32>, _sample_rate: u32) -> Result<(), &'static str> {
-        let drive: f32 = 2.0; // How hard we push the "tape"
-        let mix: f32 = 0.3; // How much saturation to blend in (30%)
-        let output_gain: f32 = 0.8; // Compensate for volume increase
-
-        for sample in samples.iter_mut() {
-            let clean = *sample;
-            let driven = *sample * drive;
-            let saturated = driven.tanh();
-
-            // Mix clean and saturated signals
-            *sample = (clean * (1.0 - mix) + saturated * mix) * output_gain;
-        }
-
+        saturate(samples);
         Ok(())
     }
 
+    fn processor(&self, _sample_rate: u32, _channels: u16) -> Option<ProcessorBox> {
+        Some(Box::new(TapeSaturation))
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<TapeSaturation>()
     }
 }
+
+// Stateless, so the effect is its own streaming processor
+impl EffectProcessor for TapeSaturation {
+    fn process(&mut self, block: &mut [f32]) {
+        saturate(block);
+    }
+}
+
+fn saturate(samples: &mut [f32]) {
+    let drive: f32 = 2.0; // How hard we push the "tape"
+    let mix: f32 = 0.3; // How much saturation to blend in (30%)
+    let output_gain: f32 = 0.8; // Compensate for volume increase
+
+    for sample in samples.iter_mut() {
+        let clean = *sample;
+        let driven = *sample * drive;
+        let saturated = driven.tanh();
+
+        // Mix clean and saturated signals
+        *sample = (clean * (1.0 - mix) + saturated * mix) * output_gain;
+    }
+}
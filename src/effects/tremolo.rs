@@ -1,4 +1,4 @@
-use super::{EffectBox, EffectTrait};
+use super::{EffectBox, EffectProcessor, EffectTrait, ProcessorBox};
 use std::any::TypeId;
 use std::fmt;
 
@@ -41,19 +41,49 @@ impl EffectTrait for Tremolo {
     }
 
     fn apply(&self, samples: &mut Vec<f32>, sample_rate: u32) -> Result<(), &'static str> {
-        const FREQUENCY: f32 = 8.0; // Tremolo frequency in Hz
-        const DEPTH: f32 = 0.3; // Tremolo depth (0.0 to 1.0)
-
         for (i, sample) in samples.iter_mut().enumerate() {
-            let time = i as f32 / sample_rate as f32;
-            let angle = 2.0 * std::f32::consts::PI * FREQUENCY * time;
-            let mod_factor = 1.0 - (DEPTH * (0.5 + 0.5 * angle.sin()));
-            *sample *= mod_factor;
+            *sample *= mod_factor(i as u64, sample_rate);
         }
         Ok(())
     }
 
+    fn processor(&self, sample_rate: u32, channels: u16) -> Option<ProcessorBox> {
+        Some(Box::new(TremoloProcessor {
+            sample_rate,
+            channels: channels.max(1) as usize,
+            frame: 0,
+        }))
+    }
+
     fn type_id(&self) -> TypeId {
         TypeId::of::<Tremolo>()
     }
 }
+
+const FREQUENCY: f32 = 8.0; // Tremolo frequency in Hz
+const DEPTH: f32 = 0.3; // Tremolo depth (0.0 to 1.0)
+
+fn mod_factor(frame: u64, sample_rate: u32) -> f32 {
+    // Wrap to one LFO period so f32 time stays precise on long sessions
+    let time = ((frame as f64 / sample_rate as f64) % (1.0 / FREQUENCY as f64)) as f32;
+    let angle = 2.0 * std::f32::consts::PI * FREQUENCY * time;
+    1.0 - (DEPTH * (0.5 + 0.5 * angle.sin()))
+}
+
+struct TremoloProcessor {
+    sample_rate: u32,
+    channels: usize,
+    frame: u64,
+}
+
+impl EffectProcessor for TremoloProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        for frame in block.chunks_exact_mut(self.channels) {
+            let factor = mod_factor(self.frame, self.sample_rate);
+            for sample in frame.iter_mut() {
+                *sample *= factor;
+            }
+            self.frame += 1;
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use crate::channels;
+use crate::loudness;
+use crate::mixer::Mixer;
+use crate::wav::{self, SampleFormat};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use strum::{EnumIter, IntoEnumIterator};
+
+// Frames rendered between progress updates and cancel checks
+const RENDER_BLOCK_FRAMES: usize = 8192;
+pub const SAMPLE_RATES: [u32; 5] = [44100, 48000, 88200, 96000, 192000];
+// Tail lengths offered after "Auto" (which waits for the effects to go quiet)
+pub const TAIL_SECONDS: [f64; 5] = [0.0, 1.0, 2.0, 5.0, 10.0];
+pub const PEAK_TARGETS_DBFS: [f32; 3] = [-0.1, -1.0, -3.0];
+// Integrated loudness and true-peak ceiling pairs, streaming first then broadcast
+pub const LOUDNESS_TARGETS: [(f32, f32); 3] = [(-14.0, -1.0), (-16.0, -1.0), (-23.0, -1.0)];
+
+/// What an export writes: the master mix, or one file per track or aux bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter)]
+pub enum ExportSource {
+    #[default]
+    Mix,
+    /// Each track's dry output through its groups and the master, without its sends.
+    TrackStems,
+    /// Each aux bus return through the master, fed by every track's sends.
+    BusStems,
+}
+
+impl ExportSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportSource::Mix => "Full mix",
+            ExportSource::TrackStems => "Track stems",
+            ExportSource::BusStems => "Bus stems",
+        }
+    }
+
+    /// The source after this one, wrapping around.
+    pub fn next(&self) -> ExportSource {
+        let sources: Vec<ExportSource> = ExportSource::iter().collect();
+        let idx = sources.iter().position(|s| s == self).unwrap_or(0);
+        sources[(idx + 1) % sources.len()]
+    }
+}
+
+/// Part of the timeline an export covers, before the tail.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ExportRange {
+    /// From the start to the end of the last clip.
+    #[default]
+    Project,
+    /// The loop region.
+    Loop,
+    /// Any span of frames.
+    Custom(Range<u64>),
+}
+
+impl ExportRange {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportRange::Project => "Whole project",
+            ExportRange::Loop => "Loop region",
+            ExportRange::Custom(_) => "Custom",
+        }
+    }
+}
+
+/// Gain applied to every exported file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Normalize {
+    #[default]
+    Off,
+    /// Bring the loudest peak to this level in dBFS. Stems share one gain so they
+    /// still sum to the mix.
+    Peak(f32),
+    /// Bring the integrated loudness to `lufs`, short of it if the true peak would
+    /// otherwise pass `ceiling` dBTP. Stems are measured and moved together.
+    Loudness { lufs: f32, ceiling: f32 },
+}
+
+impl Normalize {
+    pub fn label(&self) -> String {
+        match self {
+            Normalize::Off => "Off".to_string(),
+            Normalize::Peak(dbfs) => format!("Peak {:.1} dBFS", dbfs),
+            Normalize::Loudness { lufs, ceiling } => {
+                format!("{:.1} LUFS, {:.1} dBTP ceiling", lufs, ceiling)
+            }
+        }
+    }
+
+    /// Off, then each of `PEAK_TARGETS_DBFS` and `LOUDNESS_TARGETS`, wrapping around.
+    pub fn next(&self) -> Normalize {
+        let options: Vec<Normalize> = std::iter::once(Normalize::Off)
+            .chain(PEAK_TARGETS_DBFS.iter().map(|&dbfs| Normalize::Peak(dbfs)))
+            .chain(
+                LOUDNESS_TARGETS
+                    .iter()
+                    .map(|&(lufs, ceiling)| Normalize::Loudness { lufs, ceiling }),
+            )
+            .collect();
+        let idx = options.iter().position(|n| n == self).unwrap_or(0);
+        options[(idx + 1) % options.len()]
+    }
+}
+
+/// Everything the export dialog lets the user choose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSettings {
+    pub source: ExportSource,
+    pub range: ExportRange,
+    pub sample_rate: u32,
+    pub format: SampleFormat,
+    pub channels: u16,
+    /// Seconds rendered past the range end for reverbs and delays to ring out;
+    /// `None` renders until every effect tail has finished.
+    pub tail_seconds: Option<f64>,
+    pub normalize: Normalize,
+}
+
+impl ExportSettings {
+    /// The full mix of the whole project in the session's format, 16-bit.
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        ExportSettings {
+            source: ExportSource::default(),
+            range: ExportRange::default(),
+            sample_rate,
+            format: SampleFormat::default(),
+            channels,
+            tail_seconds: None,
+            normalize: Normalize::default(),
+        }
+    }
+
+    pub fn tail_label(&self) -> String {
+        match self.tail_seconds {
+            None => "Auto".to_string(),
+            Some(seconds) => format!("{:.0} s", seconds),
+        }
+    }
+
+    /// Auto, then each of `TAIL_SECONDS`, wrapping around.
+    pub fn next_tail(&self) -> Option<f64> {
+        match self.tail_seconds {
+            None => Some(TAIL_SECONDS[0]),
+            Some(seconds) => TAIL_SECONDS.iter().copied().find(|&s| s > seconds),
+        }
+    }
+}
+
+/// One file's worth of offline mixing, set up by `Session::export_renders`.
+pub struct ExportRender {
+    pub name: String,
+    pub mixer: Mixer,
+    pub frames: u64,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Shared between an export and whoever is watching it.
+#[derive(Default)]
+pub struct ExportProgress {
+    done: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl ExportProgress {
+    /// Fraction of the frames rendered so far, 0.0 to 1.0.
+    pub fn fraction(&self) -> f64 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        (self.done.load(Ordering::Relaxed) as f64 / total as f64).min(1.0)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Render `renders` and write them out. A single mix is written to `destination`;
+/// stems go into `destination` as a folder, one numbered file each. Returns the files
+/// written. Nothing is left behind if the export is cancelled or fails.
+pub fn export(
+    renders: Vec<ExportRender>,
+    settings: &ExportSettings,
+    destination: &Path,
+    progress: &ExportProgress,
+) -> Result<Vec<PathBuf>, String> {
+    if renders.is_empty() {
+        return Err("Nothing to export".to_string());
+    }
+    let total = renders.iter().map(|r| r.frames).sum();
+    progress.total.store(total, Ordering::Relaxed);
+
+    let mut outputs = Vec::new();
+    for render in renders {
+        let name = render.name.clone();
+        let samples = render_samples(render, settings, progress)?;
+        outputs.push((name, samples));
+    }
+    normalize(&mut outputs, settings);
+
+    let paths: Vec<PathBuf> = if settings.source == ExportSource::Mix {
+        vec![destination.to_path_buf()]
+    } else {
+        fs::create_dir_all(destination).map_err(|e| e.to_string())?;
+        outputs
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| {
+                let file = format!("{:02}_{}.wav", i + 1, file_name(name));
+                destination.join(file)
+            })
+            .collect()
+    };
+    for (path, (_, samples)) in paths.iter().zip(&outputs) {
+        if let Err(e) = wav::write_samples(
+            path,
+            samples,
+            settings.sample_rate,
+            settings.channels,
+            settings.format,
+        ) {
+            remove_partial(&paths, destination, settings.source);
+            return Err(format!("{}: {}", path.display(), e));
+        }
+    }
+    Ok(paths)
+}
+
+/// Mix one render and convert it to the export's sample rate and channel count.
+fn render_samples(
+    mut render: ExportRender,
+    settings: &ExportSettings,
+    progress: &ExportProgress,
+) -> Result<Vec<f32>, String> {
+    let channels = render.channels.max(1) as usize;
+    let mut samples = vec![0.0f32; render.frames as usize * channels];
+    for block in samples.chunks_mut(RENDER_BLOCK_FRAMES * channels) {
+        if progress.is_cancelled() {
+            return Err("Export cancelled".to_string());
+        }
+        render.mixer.process(block);
+        progress
+            .done
+            .fetch_add((block.len() / channels) as u64, Ordering::Relaxed);
+    }
+    if settings.channels != render.channels {
+        samples = channels::remix(&samples, render.channels, settings.channels);
+    }
+    if settings.sample_rate != render.sample_rate {
+        samples = wav::resample(
+            &samples,
+            settings.channels,
+            render.sample_rate,
+            settings.sample_rate,
+        );
+    }
+    Ok(samples)
+}
+
+fn normalize(outputs: &mut [(String, Vec<f32>)], settings: &ExportSettings) {
+    let gain_db = match settings.normalize {
+        Normalize::Off => return,
+        Normalize::Peak(dbfs) => {
+            let peak = outputs
+                .iter()
+                .flat_map(|(_, samples)| samples.iter())
+                .fold(0.0f32, |peak, s| peak.max(s.abs()));
+            if peak <= 0.0 {
+                return;
+            }
+            dbfs as f64 - 20.0 * (peak as f64).log10()
+        }
+        Normalize::Loudness { lufs, ceiling } => {
+            let report = match outputs {
+                [(_, samples)] => {
+                    loudness::analyze(samples, settings.channels, settings.sample_rate)
+                }
+                _ => loudness::analyze(&summed(outputs), settings.channels, settings.sample_rate),
+            };
+            if !report.integrated.is_finite() {
+                return;
+            }
+            (lufs as f64 - report.integrated).min(ceiling as f64 - report.true_peak)
+        }
+    };
+    let gain = 10f64.powf(gain_db / 20.0) as f32;
+    for (_, samples) in outputs.iter_mut() {
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
+
+/// All the stems mixed back together.
+fn summed(outputs: &[(String, Vec<f32>)]) -> Vec<f32> {
+    let len = outputs.iter().map(|(_, s)| s.len()).max().unwrap_or(0);
+    let mut sum = vec![0.0f32; len];
+    for (_, samples) in outputs {
+        for (total, &s) in sum.iter_mut().zip(samples) {
+            *total += s;
+        }
+    }
+    sum
+}
+
+fn remove_partial(paths: &[PathBuf], destination: &Path, source: ExportSource) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+    if source != ExportSource::Mix {
+        let _ = fs::remove_dir(destination);
+    }
+}
+
+/// `name` with anything that does not belong in a file name replaced.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// An export running on a background thread.
+pub struct ExportJob {
+    progress: Arc<ExportProgress>,
+    thread: JoinHandle<Result<Vec<PathBuf>, String>>,
+}
+
+impl ExportJob {
+    pub fn start(
+        renders: Vec<ExportRender>,
+        settings: ExportSettings,
+        destination: PathBuf,
+    ) -> ExportJob {
+        let progress = Arc::new(ExportProgress::default());
+        let shared = Arc::clone(&progress);
+        let thread = std::thread::spawn(move || export(renders, &settings, &destination, &shared));
+        ExportJob { progress, thread }
+    }
+
+    pub fn progress(&self) -> &ExportProgress {
+        &self.progress
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the export and return the files it wrote.
+    pub fn finish(self) -> Result<Vec<PathBuf>, String> {
+        self.thread
+            .join()
+            .unwrap_or_else(|_| Err("Export thread panicked".to_string()))
+    }
+}
@@ -0,0 +1,391 @@
+use crate::bus::{AuxBus, AuxSend};
+use crate::effects::EffectInstance;
+use crate::session::FxTarget;
+use crate::track::{AutomationLane, AutomationMode, Clip, Crossfade, Fade, FadeShape, Track};
+use crate::wav::WavFile;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+// Oldest edits are forgotten beyond this, releasing the audio only they refer to
+pub const MAX_UNDO_STEPS: usize = 100;
+
+/// Where a clip sits and how it plays. The audio is not part of it: it is looked up
+/// by the clip's id in the history's audio table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipRef {
+    pub id: String,
+    pub starts_at: u64,
+    pub offset: u64,
+    pub length: u64,
+    pub muted: bool,
+    pub take: Option<String>,
+    pub gain_db: f32,
+    pub fade_in: Fade,
+    pub fade_out: Fade,
+}
+
+impl ClipRef {
+    fn of(clip: &Clip) -> Self {
+        ClipRef {
+            id: clip.id.clone(),
+            starts_at: clip.starts_at,
+            offset: clip.offset,
+            length: clip.length,
+            muted: clip.muted,
+            take: clip.take.clone(),
+            gain_db: clip.gain_db,
+            fade_in: clip.fade_in,
+            fade_out: clip.fade_out,
+        }
+    }
+}
+
+/// Everything about a track's clips that clip edits change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipLayout {
+    pub clips: Vec<ClipRef>,
+    pub takes: Vec<ClipRef>,
+    pub selected_take: usize,
+    pub crossfades: Vec<Crossfade>,
+    pub auto_crossfade: bool,
+    pub crossfade_shape: FadeShape,
+}
+
+/// A track's mixer channel settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixerState {
+    pub volume: f64,
+    pub pan: f32,
+    pub muted: bool,
+    pub solo: bool,
+    pub solo_safe: bool,
+    pub sends: Vec<AuxSend>,
+}
+
+impl MixerState {
+    pub fn of(track: &Track) -> Self {
+        MixerState {
+            volume: track.volume,
+            pan: track.pan,
+            muted: track.muted,
+            solo: track.solo,
+            solo_safe: track.solo_safe,
+            sends: track.sends.clone(),
+        }
+    }
+
+    pub fn apply(&self, track: &mut Track) {
+        track.volume = self.volume;
+        track.pan = self.pan;
+        track.muted = self.muted;
+        track.solo = self.solo;
+        track.solo_safe = self.solo_safe;
+        track.sends = self.sends.clone();
+    }
+}
+
+/// A whole track, kept to bring it back after it was removed.
+#[derive(Debug, Clone)]
+pub struct TrackSnapshot {
+    pub name: String,
+    pub is_group: bool,
+    pub collapsed: bool,
+    pub input_channel: Option<u16>,
+    pub mixer: MixerState,
+    pub fx_chain: Vec<EffectInstance>,
+    pub clips: ClipLayout,
+    pub automation: Vec<AutomationLane>,
+    pub automation_mode: AutomationMode,
+}
+
+/// One change to the session, with the state on either side of it.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Clips {
+        track: usize,
+        before: ClipLayout,
+        after: ClipLayout,
+    },
+    Mixer {
+        track: usize,
+        before: MixerState,
+        after: MixerState,
+    },
+    /// An aux bus's volume and mute
+    Bus {
+        bus: usize,
+        before: (f64, bool),
+        after: (f64, bool),
+    },
+    MasterGain {
+        before: f64,
+        after: f64,
+    },
+    /// Every automation lane of a track
+    Automation {
+        track: usize,
+        before: Vec<AutomationLane>,
+        after: Vec<AutomationLane>,
+    },
+    /// An effect added (`before` is None), removed (`after` is None) or changed
+    Effect {
+        target: FxTarget,
+        index: usize,
+        before: Option<EffectInstance>,
+        after: Option<EffectInstance>,
+    },
+    Parent {
+        track: usize,
+        before: Option<usize>,
+        after: Option<usize>,
+    },
+    AddTrack {
+        index: usize,
+        track: TrackSnapshot,
+    },
+    /// `parents` holds every track's parent from before the removal
+    RemoveTrack {
+        index: usize,
+        track: TrackSnapshot,
+        parents: Vec<Option<usize>>,
+    },
+    AddBus {
+        index: usize,
+        bus: AuxBus,
+    },
+    /// `sends` holds every send that fed the bus, as (track, position in the
+    /// track's sends, send)
+    RemoveBus {
+        index: usize,
+        bus: AuxBus,
+        sends: Vec<(usize, usize, AuxSend)>,
+    },
+}
+
+impl Edit {
+    /// True when `newer` changes the same thing, so the two can be undone as one.
+    fn same_target(&self, newer: &Edit) -> bool {
+        match (self, newer) {
+            (Edit::Clips { track: a, .. }, Edit::Clips { track: b, .. }) => a == b,
+            (Edit::Mixer { track: a, .. }, Edit::Mixer { track: b, .. }) => a == b,
+            (Edit::Bus { bus: a, .. }, Edit::Bus { bus: b, .. }) => a == b,
+            (Edit::MasterGain { .. }, Edit::MasterGain { .. }) => true,
+            (Edit::Automation { track: a, .. }, Edit::Automation { track: b, .. }) => a == b,
+            (
+                Edit::Effect {
+                    target: a,
+                    index: i,
+                    before: Some(_),
+                    after: Some(_),
+                },
+                Edit::Effect {
+                    target: b,
+                    index: j,
+                    before: Some(_),
+                    after: Some(_),
+                },
+            ) => a == b && i == j,
+            _ => false,
+        }
+    }
+
+    /// Take the end state of `newer`, keeping this edit's start state.
+    fn absorb(&mut self, newer: Edit) {
+        match (self, newer) {
+            (Edit::Clips { after, .. }, Edit::Clips { after: new, .. }) => *after = new,
+            (Edit::Mixer { after, .. }, Edit::Mixer { after: new, .. }) => *after = new,
+            (Edit::Bus { after, .. }, Edit::Bus { after: new, .. }) => *after = new,
+            (Edit::MasterGain { after, .. }, Edit::MasterGain { after: new, .. }) => *after = new,
+            (Edit::Automation { after, .. }, Edit::Automation { after: new, .. }) => *after = new,
+            (Edit::Effect { after, .. }, Edit::Effect { after: new, .. }) => *after = new,
+            _ => {}
+        }
+    }
+
+    fn clip_ids(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Edit::Clips { before, after, .. } => {
+                Box::new(layout_ids(before).chain(layout_ids(after)))
+            }
+            Edit::AddTrack { track, .. } | Edit::RemoveTrack { track, .. } => {
+                Box::new(layout_ids(&track.clips))
+            }
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+fn layout_ids(layout: &ClipLayout) -> impl Iterator<Item = &str> {
+    layout
+        .clips
+        .iter()
+        .chain(layout.takes.iter())
+        .map(|c| c.id.as_str())
+}
+
+/// A named group of edits undone and redone together.
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub name: String,
+    pub edits: Vec<Edit>,
+    // Repeats of this command (a volume step, a nudge) fold into it
+    coalesce: bool,
+}
+
+/// Undo and redo stacks of session edits.
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Command>,
+    redo: Vec<Command>,
+    // Audio of every clip an edit refers to, by clip id
+    audio: HashMap<String, Arc<WavFile>>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The track's clips as an edit records them, keeping hold of their audio.
+    pub fn capture(&mut self, track: &Track) -> ClipLayout {
+        for clip in track.clips.iter().chain(track.takes.iter()) {
+            self.audio
+                .entry(clip.id.clone())
+                .or_insert_with(|| Arc::clone(&clip.wav_data));
+        }
+        ClipLayout {
+            clips: track.clips.iter().map(ClipRef::of).collect(),
+            takes: track.takes.iter().map(ClipRef::of).collect(),
+            selected_take: track.selected_take,
+            crossfades: track.crossfades.clone(),
+            auto_crossfade: track.auto_crossfade,
+            crossfade_shape: track.crossfade_shape,
+        }
+    }
+
+    /// Put a recorded layout back on a track. Fails, leaving the track as it was,
+    /// when the audio of one of its clips is no longer held.
+    pub fn restore(&self, layout: &ClipLayout, track: &mut Track) -> Result<(), String> {
+        let rebuild = |refs: &[ClipRef]| -> Result<Vec<Clip>, String> {
+            refs.iter()
+                .map(|r| {
+                    Ok(Clip {
+                        id: r.id.clone(),
+                        wav_data: Arc::clone(self.audio_of(&r.id)?),
+                        starts_at: r.starts_at,
+                        offset: r.offset,
+                        length: r.length,
+                        muted: r.muted,
+                        take: r.take.clone(),
+                        gain_db: r.gain_db,
+                        fade_in: r.fade_in,
+                        fade_out: r.fade_out,
+                    })
+                })
+                .collect()
+        };
+        let clips = rebuild(&layout.clips)?;
+        let takes = rebuild(&layout.takes)?;
+        track.clips = clips;
+        track.takes = takes;
+        track.selected_take = layout.selected_take;
+        track.crossfades = layout.crossfades.clone();
+        track.auto_crossfade = layout.auto_crossfade;
+        track.crossfade_shape = layout.crossfade_shape;
+        Ok(())
+    }
+
+    /// Check that `restore` can put every clip of `layout` back.
+    pub fn check_audio(&self, layout: &ClipLayout) -> Result<(), String> {
+        layout_ids(layout).try_for_each(|id| self.audio_of(id).map(|_| ()))
+    }
+
+    fn audio_of(&self, id: &str) -> Result<&Arc<WavFile>, String> {
+        self.audio
+            .get(id)
+            .ok_or_else(|| format!("The audio of clip {} is no longer available", id))
+    }
+
+    /// Add a command to undo. Nothing is recorded when `edits` is empty; with
+    /// `coalesce` a repeat of the last command extends it instead of adding another.
+    pub fn record(&mut self, name: &str, edits: Vec<Edit>, coalesce: bool) {
+        if edits.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        if let Some(last) = self.undo.back_mut() {
+            let repeat = coalesce
+                && last.coalesce
+                && last.name == name
+                && last.edits.len() == edits.len()
+                && last.edits.iter().zip(&edits).all(|(a, b)| a.same_target(b));
+            if repeat {
+                for (edit, newer) in last.edits.iter_mut().zip(edits) {
+                    edit.absorb(newer);
+                }
+                self.release_audio();
+                return;
+            }
+        }
+        self.undo.push_back(Command {
+            name: name.to_string(),
+            edits,
+            coalesce,
+        });
+        while self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.pop_front();
+        }
+        self.release_audio();
+    }
+
+    /// The next command to undo, moved to the redo stack.
+    pub fn undo(&mut self) -> Option<Command> {
+        let command = self.undo.pop_back()?;
+        self.redo.push(command.clone());
+        Some(command)
+    }
+
+    /// The next command to redo, moved back to the undo stack.
+    pub fn redo(&mut self) -> Option<Command> {
+        let mut command = self.redo.pop()?;
+        // A redone edit starts a fresh run of repeats
+        command.coalesce = false;
+        self.undo.push_back(command.clone());
+        Some(command)
+    }
+
+    pub fn next_undo(&self) -> Option<&Command> {
+        self.undo.back()
+    }
+
+    pub fn next_redo(&self) -> Option<&Command> {
+        self.redo.last()
+    }
+
+    pub fn undo_name(&self) -> Option<&str> {
+        self.undo.back().map(|c| c.name.as_str())
+    }
+
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo.last().map(|c| c.name.as_str())
+    }
+
+    /// Forget every edit, e.g. after a change the history cannot follow.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.audio.clear();
+    }
+
+    // Let go of audio that no remaining edit refers to
+    fn release_audio(&mut self) {
+        let used: HashSet<&str> = self
+            .undo
+            .iter()
+            .chain(self.redo.iter())
+            .flat_map(|c| c.edits.iter())
+            .flat_map(Edit::clip_ids)
+            .collect();
+        self.audio.retain(|id, _| used.contains(id.as_str()));
+    }
+}
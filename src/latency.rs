@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Calibration clicks are this far apart; round trips longer than this cannot be measured
+pub const CALIBRATION_CLICK_INTERVAL_MS: u32 = 500;
+pub const CALIBRATION_CLICKS: usize = 4;
+const CLICK_MS: f32 = 2.0;
+// A recorded click must reach this level (and stand well above the noise) to count
+const CLICK_MIN_LEVEL: f32 = 0.01;
+const CLICK_NOISE_RATIO: f32 = 4.0;
+
+/// How new recordings are lined up with the backing mix. Overdubs arrive late by the
+/// round trip through the output and input; recorded clips are moved earlier by the
+/// calibrated round trip (or the latencies the streams report when uncalibrated)
+/// plus a user offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySettings {
+    #[serde(default)]
+    pub offset_ms: f32,
+    #[serde(default)]
+    pub calibrated_ms: Option<f32>,
+}
+
+impl LatencySettings {
+    /// Frames to move a new recording earlier by, given the round trip the streams
+    /// reported while recording.
+    pub fn compensation_frames(&self, reported_frames: u64, sample_rate: u32) -> u64 {
+        let round_trip = match self.calibrated_ms {
+            Some(ms) => ms_to_frames(ms, sample_rate),
+            None => reported_frames as f64,
+        };
+        (round_trip + ms_to_frames(self.offset_ms, sample_rate))
+            .round()
+            .max(0.0) as u64
+    }
+}
+
+pub fn ms_to_frames(ms: f32, sample_rate: u32) -> f64 {
+    ms as f64 * sample_rate as f64 / 1000.0
+}
+
+pub fn frames_to_ms(frames: u64, sample_rate: u32) -> f32 {
+    (frames as f64 * 1000.0 / sample_rate.max(1) as f64) as f32
+}
+
+// Stored while no latency has been reported; zero is a valid report
+const NOT_REPORTED: u64 = u64::MAX;
+
+/// Latency a stream reports from its callbacks, shared lock-free with the audio thread.
+pub struct StreamLatency(AtomicU64);
+
+impl Default for StreamLatency {
+    fn default() -> Self {
+        StreamLatency(AtomicU64::new(NOT_REPORTED))
+    }
+}
+
+impl StreamLatency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the gap between the callback and the device, if the host reported one.
+    pub fn report(&self, latency: Option<Duration>, sample_rate: u32) {
+        if let Some(latency) = latency {
+            let frames = (latency.as_secs_f64() * sample_rate as f64).round() as u64;
+            self.0.store(frames, Ordering::Relaxed);
+        }
+    }
+
+    pub fn frames(&self) -> Option<u64> {
+        match self.0.load(Ordering::Relaxed) {
+            NOT_REPORTED => None,
+            frames => Some(frames),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.0.store(NOT_REPORTED, Ordering::Relaxed);
+    }
+}
+
+/// A short full-scale click.
+pub fn click(sample_rate: u32) -> Vec<f32> {
+    let len = (ms_to_frames(CLICK_MS, sample_rate) as usize).max(1);
+    (0..len)
+        .map(|i| if i % 2 == 0 { 0.9 } else { -0.9 })
+        .collect()
+}
+
+/// Round trip in frames from a mono loopback recording of clicks played every
+/// `interval` frames from frame 0: the median delay of the clicks that were heard.
+pub fn measure_round_trip(recorded: &[f32], interval: usize, clicks: usize) -> Option<u64> {
+    let mut delays: Vec<usize> = (0..clicks)
+        .filter_map(|k| {
+            let start = k * interval;
+            let window = recorded.get(start..(start + interval).min(recorded.len()))?;
+            detect_onset(window)
+        })
+        .collect();
+    // Half the clicks must be heard, otherwise the loopback is probably not connected
+    if delays.len() * 2 < clicks.max(1) {
+        return None;
+    }
+    delays.sort_unstable();
+    Some(delays[delays.len() / 2] as u64)
+}
+
+/// First sample that rises clearly above the window's noise floor.
+fn detect_onset(window: &[f32]) -> Option<usize> {
+    let peak = window.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    if peak < CLICK_MIN_LEVEL {
+        return None;
+    }
+    let mut magnitudes: Vec<f32> = window.iter().map(|s| s.abs()).collect();
+    magnitudes.sort_unstable_by(f32::total_cmp);
+    let noise = magnitudes[magnitudes.len() / 2];
+    if peak < noise * CLICK_NOISE_RATIO {
+        return None;
+    }
+    let threshold = peak * 0.5;
+    window.iter().position(|s| s.abs() >= threshold)
+}
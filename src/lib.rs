@@ -1,9 +1,21 @@
 pub mod audio_engine;
+pub mod backend;
+pub mod bus;
+pub mod channels;
+pub mod cli;
 pub mod device;
 pub mod effects;
+pub mod export;
+pub mod history;
+pub mod latency;
+pub mod loudness;
 pub mod master_bus;
+pub mod meter;
+pub mod metronome;
+pub mod mixer;
 pub mod project;
 pub mod session;
+pub mod tempo;
 pub mod track;
 pub mod ui;
 pub mod wav;
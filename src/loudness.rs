@@ -0,0 +1,291 @@
+use crate::wav::WavFile;
+use std::f64::consts::PI;
+
+// ITU-R BS.1770-4 / EBU Tech 3341-3342 timings and gates
+const MOMENTARY_SECONDS: f64 = 0.4;
+const SHORT_TERM_SECONDS: f64 = 3.0;
+const STEP_SECONDS: f64 = 0.1;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+// Surround channels count for more; the LFE is left out (5.1 order: L R C LFE Ls Rs)
+const SURROUND_WEIGHTS: [f64; 6] = [1.0, 1.0, 1.0, 0.0, 1.41, 1.41];
+// True peak interpolation: input samples either side of each interpolated point
+const TRUE_PEAK_HALF_TAPS: usize = 12;
+
+/// Loudness of a piece of audio. Levels are in LUFS, the range in LU and the true peak
+/// in dBTP; silence measures as negative infinity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessReport {
+    /// Gated loudness of the whole programme.
+    pub integrated: f64,
+    /// 400 ms loudness every 100 ms.
+    pub momentary: Vec<f64>,
+    /// 3 s loudness every 100 ms.
+    pub short_term: Vec<f64>,
+    /// Spread between the quiet and loud parts of the short-term loudness.
+    pub loudness_range: f64,
+    /// Highest inter-sample peak across all channels.
+    pub true_peak: f64,
+}
+
+impl LoudnessReport {
+    pub fn max_momentary(&self) -> f64 {
+        self.momentary
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    pub fn max_short_term(&self) -> f64 {
+        self.short_term
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// One line for the status bar.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} LUFS integrated | {} LUFS max short-term | LRA {:.1} LU | {} dBTP",
+            format_level(self.integrated),
+            format_level(self.max_short_term()),
+            self.loudness_range,
+            format_level(self.true_peak)
+        )
+    }
+}
+
+fn format_level(level: f64) -> String {
+    if level.is_finite() {
+        format!("{:.1}", level)
+    } else {
+        "-inf".to_string()
+    }
+}
+
+impl WavFile {
+    pub fn loudness(&self) -> LoudnessReport {
+        analyze(
+            &self.to_f32_samples(),
+            self.header.num_channels,
+            self.header.sample_rate,
+        )
+    }
+}
+
+/// Measure interleaved samples.
+pub fn analyze(samples: &[f32], channels: u16, sample_rate: u32) -> LoudnessReport {
+    let channels = channels.max(1) as usize;
+    let weights: Vec<f64> = (0..channels)
+        .map(|ch| {
+            if channels == SURROUND_WEIGHTS.len() {
+                SURROUND_WEIGHTS[ch]
+            } else {
+                1.0
+            }
+        })
+        .collect();
+
+    // Weighted K-filtered energy of every 100 ms step
+    let step = ((sample_rate as f64 * STEP_SECONDS) as usize).max(1);
+    let mut filters: Vec<KWeighting> = (0..channels)
+        .map(|_| KWeighting::new(sample_rate))
+        .collect();
+    let mut steps = Vec::new();
+    for block in samples.chunks(step * channels) {
+        let mut energy = 0.0;
+        for frame in block.chunks_exact(channels) {
+            for (ch, &sample) in frame.iter().enumerate() {
+                let filtered = filters[ch].process(sample as f64);
+                energy += weights[ch] * filtered * filtered;
+            }
+        }
+        steps.push(energy);
+    }
+    // A partial last step counts as a full one, as though silence followed
+    let window_energy = |steps_per_window: usize| -> Vec<f64> {
+        if steps.len() < steps_per_window {
+            return Vec::new();
+        }
+        steps
+            .windows(steps_per_window)
+            .map(|w| w.iter().sum::<f64>() / (steps_per_window * step) as f64)
+            .collect()
+    };
+    let momentary_energy = window_energy((MOMENTARY_SECONDS / STEP_SECONDS).round() as usize);
+    let short_term_energy = window_energy((SHORT_TERM_SECONDS / STEP_SECONDS).round() as usize);
+
+    LoudnessReport {
+        integrated: gated_loudness(&momentary_energy, RELATIVE_GATE_LU),
+        momentary: momentary_energy.iter().map(|&e| loudness(e)).collect(),
+        loudness_range: loudness_range(&short_term_energy),
+        short_term: short_term_energy.iter().map(|&e| loudness(e)).collect(),
+        true_peak: true_peak(samples, channels, sample_rate),
+    }
+}
+
+fn loudness(energy: f64) -> f64 {
+    if energy <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Mean energy of the blocks louder than `gate`.
+fn mean_energy<'a>(blocks: impl Iterator<Item = &'a f64>, gate: f64) -> Option<f64> {
+    let (sum, count) = blocks
+        .filter(|&&e| loudness(e) > gate)
+        .fold((0.0, 0usize), |(sum, count), &e| (sum + e, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Loudness of the blocks above the absolute gate and `relative_gate` below their mean.
+fn gated_loudness(blocks: &[f64], relative_gate: f64) -> f64 {
+    let Some(ungated) = mean_energy(blocks.iter(), ABSOLUTE_GATE_LUFS) else {
+        return f64::NEG_INFINITY;
+    };
+    let gate = (loudness(ungated) + relative_gate).max(ABSOLUTE_GATE_LUFS);
+    mean_energy(blocks.iter(), gate).map_or(f64::NEG_INFINITY, loudness)
+}
+
+/// EBU Tech 3342 loudness range from the short-term block energies.
+fn loudness_range(blocks: &[f64]) -> f64 {
+    let Some(ungated) = mean_energy(blocks.iter(), ABSOLUTE_GATE_LUFS) else {
+        return 0.0;
+    };
+    let gate = loudness(ungated) + RANGE_RELATIVE_GATE_LU;
+    let mut levels: Vec<f64> = blocks
+        .iter()
+        .map(|&e| loudness(e))
+        .filter(|&l| l > ABSOLUTE_GATE_LUFS && l > gate)
+        .collect();
+    if levels.is_empty() {
+        return 0.0;
+    }
+    levels.sort_by(f64::total_cmp);
+    let at = |p: f64| levels[((levels.len() - 1) as f64 * p).round() as usize];
+    at(RANGE_HIGH_PERCENTILE) - at(RANGE_LOW_PERCENTILE)
+}
+
+/// Highest peak with the signal oversampled to at least 192 kHz, in dBTP.
+fn true_peak(samples: &[f32], channels: usize, sample_rate: u32) -> f64 {
+    let factor = (192_000 / sample_rate.max(1)).clamp(1, 4) as usize;
+    let kernel = interpolation_kernel(factor);
+    let frames = samples.len() / channels;
+    let mut peak = 0.0f64;
+    for ch in 0..channels {
+        let sample = |i: isize| -> f64 {
+            if i < 0 || i as usize >= frames {
+                0.0
+            } else {
+                samples[i as usize * channels + ch] as f64
+            }
+        };
+        for n in 0..frames as isize {
+            peak = peak.max(sample(n).abs());
+            // Phase 0 lands on the sample itself
+            for taps in kernel.iter().skip(1) {
+                let value: f64 = taps
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &h)| h * sample(n + k as isize - TRUE_PEAK_HALF_TAPS as isize + 1))
+                    .sum();
+                peak = peak.max(value.abs());
+            }
+        }
+    }
+    if peak <= 0.0 {
+        f64::NEG_INFINITY
+    } else {
+        20.0 * peak.log10()
+    }
+}
+
+/// Windowed-sinc taps for each fractional position between input samples. Phase `p`
+/// interpolates the point `p / factor` of the way from sample `n` to `n + 1`.
+fn interpolation_kernel(factor: usize) -> Vec<Vec<f64>> {
+    let width = 2 * TRUE_PEAK_HALF_TAPS;
+    (0..factor)
+        .map(|phase| {
+            let fraction = phase as f64 / factor as f64;
+            (0..width)
+                .map(|k| {
+                    // Distance from the interpolated point to tap k
+                    let t = k as f64 - (TRUE_PEAK_HALF_TAPS as f64 - 1.0) - fraction;
+                    let sinc = if t == 0.0 {
+                        1.0
+                    } else {
+                        (PI * t).sin() / (PI * t)
+                    };
+                    let x = (t / TRUE_PEAK_HALF_TAPS as f64 + 1.0) / 2.0;
+                    let window = 0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos();
+                    sinc * window
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The BS.1770 K-weighting: a high-shelf for the head followed by a high-pass,
+/// worked out for any sample rate.
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        KWeighting { shelf, high_pass }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(sample))
+    }
+}
+
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad { b, a, z: [0.0; 2] }
+    }
+
+    // Transposed direct form II
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
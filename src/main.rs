@@ -1,8 +1,16 @@
-use rust_audio::ui;
+use rust_audio::{cli, ui};
 use std::env;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
+    // Headless rendering for scripts and build servers
+    if args.get(1).is_some_and(|arg| arg == "render") {
+        if let Err(e) = cli::render::run(&args[2..]) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     let debug_mode = args.iter().any(|arg| arg == "-debug" || arg == "--debug");
     ui::run(debug_mode)
 }
@@ -1,25 +1,31 @@
 use crate::audio_engine::AudioEngine;
-use cpal::traits::{DeviceTrait, StreamTrait};
-use cpal::{SampleRate, Stream, StreamConfig};
+use crate::backend::{AudioStream, StreamSpec};
+use crate::channels;
+use crate::latency::StreamLatency;
+use crate::mixer::Mixer;
 use ringbuf::traits::Consumer;
 use ringbuf::HeapCons;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
-const OUTPUT_BUFFER_FRAMES: u32 = 32;
+pub(crate) const OUTPUT_BUFFER_FRAMES: u32 = 32;
+// Frames rendered by the mixer per pass inside the output callback.
+const MIX_BUFFER_FRAMES: usize = 1024;
 
 pub struct MasterBusConfig {
-    pub playback_samples: Option<Vec<f32>>,
+    /// Real-time mix to play; `None` for monitoring only.
+    pub mixer: Option<Mixer>,
     pub monitor_consumer: Option<HeapCons<f32>>,
     pub sample_rate: u32,
     pub low_latency: bool,
 }
 
 pub struct MasterBus {
-    stream: Option<Stream>,
+    stream: Option<Box<dyn AudioStream>>,
     is_playing: Arc<AtomicBool>,
     frames_consumed: Arc<AtomicU64>,
-    total_frames: usize,
+    finished: Arc<AtomicBool>,
+    output_latency: Arc<StreamLatency>,
 }
 
 impl Default for MasterBus {
@@ -28,7 +34,8 @@ impl Default for MasterBus {
             stream: None,
             is_playing: Arc::new(AtomicBool::new(false)),
             frames_consumed: Arc::new(AtomicU64::new(0)),
-            total_frames: 0,
+            finished: Arc::new(AtomicBool::new(false)),
+            output_latency: Arc::new(StreamLatency::new()),
         }
     }
 }
@@ -37,70 +44,79 @@ impl MasterBus {
     pub fn start(&mut self, config: MasterBusConfig) -> Result<(), Box<dyn std::error::Error>> {
         self.stop();
 
-        let output_device = AudioEngine::get_output_device()?;
-        let channels = output_device.channels as usize;
+        let output_format = AudioEngine::output_format()?;
+        let channels = output_format.channels as usize;
 
-        let buffer_size = if config.low_latency {
-            cpal::BufferSize::Fixed(OUTPUT_BUFFER_FRAMES)
-        } else {
-            cpal::BufferSize::Default
+        let spec = StreamSpec {
+            channels: output_format.channels,
+            sample_rate: config.sample_rate,
+            buffer_frames: config.low_latency.then_some(OUTPUT_BUFFER_FRAMES),
         };
 
-        let stream_config = StreamConfig {
-            channels: output_device.channels,
-            sample_rate: SampleRate(config.sample_rate),
-            buffer_size,
-        };
-
-        let playback_buf: Arc<Vec<f32>> = Arc::new(config.playback_samples.unwrap_or_default());
-        let playback_len = playback_buf.len();
-        self.total_frames = playback_len;
+        let mut mixer = config.mixer;
+        let mix_channels = mixer.as_ref().map(|m| m.channels() as usize).unwrap_or(1);
+        let mut mix_buf = vec![0.0f32; MIX_BUFFER_FRAMES * mix_channels];
 
         let is_playing = Arc::clone(&self.is_playing);
         let frames_consumed = Arc::clone(&self.frames_consumed);
+        let finished = Arc::clone(&self.finished);
+        let output_latency = Arc::clone(&self.output_latency);
+        let sample_rate = config.sample_rate;
 
         is_playing.store(true, Ordering::Relaxed);
         frames_consumed.store(0, Ordering::Relaxed);
+        finished.store(false, Ordering::Relaxed);
+        output_latency.reset();
 
-        let mut playback_pos: usize = 0;
         let mut monitor_cons = config.monitor_consumer;
 
-        let stream = output_device.device.build_output_stream(
-            &stream_config,
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+        let stream = AudioEngine::open_output(
+            spec,
+            Box::new(move |data: &mut [f32], latency| {
+                output_latency.report(latency, sample_rate);
+                data.fill(0.0);
                 if !is_playing.load(Ordering::Relaxed) {
-                    for sample in data.iter_mut() {
-                        *sample = 0.0;
-                    }
                     return;
                 }
 
                 let frames = data.len() / channels;
+                let mut done = 0;
+
+                while done < frames {
+                    let n = (frames - done).min(MIX_BUFFER_FRAMES);
+                    let out = &mut data[done * channels..(done + n) * channels];
+
+                    // Pull the next block from the mixer and map it onto the device channels
+                    if let Some(mixer) = mixer.as_mut() {
+                        let mix = &mut mix_buf[..n * mix_channels];
+                        mixer.process(mix);
+                        for (src, dst) in mix
+                            .chunks_exact(mix_channels)
+                            .zip(out.chunks_exact_mut(channels))
+                        {
+                            channels::mix_frame_into(src, dst, 1.0);
+                        }
+                        if mixer.is_finished() {
+                            finished.store(true, Ordering::Relaxed);
+                        }
+                    }
 
-                for frame in 0..frames {
-                    let playback_sample = if playback_pos < playback_len {
-                        let s = playback_buf[playback_pos];
-                        playback_pos += 1;
-                        s
-                    } else {
-                        0.0
-                    };
-
-                    let monitor_sample = monitor_cons
-                        .as_mut()
-                        .and_then(|c| c.try_pop())
-                        .unwrap_or(0.0);
-
-                    let mixed = playback_sample + monitor_sample;
-                    for ch in 0..channels {
-                        data[frame * channels + ch] = mixed;
+                    // Live input monitoring is mono and goes to every output channel
+                    for frame in out.chunks_exact_mut(channels) {
+                        let monitor_sample = monitor_cons
+                            .as_mut()
+                            .and_then(|c| c.try_pop())
+                            .unwrap_or(0.0);
+                        for sample in frame.iter_mut() {
+                            *sample += monitor_sample;
+                        }
                     }
+
+                    done += n;
                 }
 
                 frames_consumed.fetch_add(frames as u64, Ordering::Relaxed);
-            },
-            |err| eprintln!("MasterBus stream error: {err}"),
-            None,
+            }),
         )?;
 
         stream.play()?;
@@ -113,19 +129,21 @@ impl MasterBus {
         self.is_playing.store(false, Ordering::Relaxed);
         self.stream = None;
         self.frames_consumed.store(0, Ordering::Relaxed);
-        self.total_frames = 0;
+        self.finished.store(false, Ordering::Relaxed);
     }
 
     pub fn frames_consumed(&self) -> u64 {
         self.frames_consumed.load(Ordering::Relaxed)
     }
 
+    /// True once the mixer has played past its last voice (including FX tails).
     pub fn is_finished(&self) -> bool {
-        if self.total_frames == 0 {
-            return false;
-        }
-        let consumed = self.frames_consumed.load(Ordering::Relaxed) as usize;
-        consumed >= self.total_frames
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Delay from the output callback to the speakers, as last reported by the host.
+    pub fn output_latency_frames(&self) -> Option<u64> {
+        self.output_latency.frames()
     }
 
     pub fn is_active(&self) -> bool {
@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Displayed levels fall at this rate once the signal drops
+const DECAY_DB_PER_SECOND: f32 = 24.0;
+// How long the peak-hold marker stays put before it starts to fall
+const PEAK_HOLD: Duration = Duration::from_millis(1500);
+// Anything at or above full scale counts as a clip
+const CLIP_LEVEL: f32 = 1.0;
+pub const METER_FLOOR_DB: f32 = -60.0;
+
+/// Peak and RMS level meter. The audio thread publishes each block it processes with
+/// plain atomic operations; the UI reads it back through `display`, which applies
+/// decay, peak hold and a latched clip indicator.
+pub struct Meter {
+    peak: AtomicU32, // f32 bits, highest |sample| since the UI last read
+    rms: AtomicU32,  // f32 bits, loudest block RMS since the UI last read
+    clipped: AtomicBool,
+    // Only touched by the UI thread
+    ballistics: Mutex<Ballistics>,
+}
+
+/// What a meter shows right now, all levels linear (1.0 = full scale).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeterDisplay {
+    pub peak: f32,
+    pub rms: f32,
+    pub hold: f32,
+    pub clipped: bool,
+}
+
+#[derive(Default)]
+struct Ballistics {
+    shown: MeterDisplay,
+    held_at: Option<Instant>,
+    last_read: Option<Instant>,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Meter {
+            peak: AtomicU32::new(0),
+            rms: AtomicU32::new(0),
+            clipped: AtomicBool::new(false),
+            ballistics: Mutex::new(Ballistics::default()),
+        }
+    }
+}
+
+impl Meter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure a block of samples (any channel layout) and publish its levels.
+    /// Real-time safe: no allocation and no locking.
+    pub fn measure(&self, samples: &[f32]) {
+        let mut peak = 0.0f32;
+        let mut sum_squares = 0.0f32;
+        for &sample in samples {
+            peak = peak.max(sample.abs());
+            sum_squares += sample * sample;
+        }
+        self.publish(peak, sum_squares, samples.len());
+    }
+
+    /// Publish levels measured elsewhere: the block's peak and sum of squares over
+    /// `count` samples.
+    pub fn publish(&self, peak: f32, sum_squares: f32, count: usize) {
+        if count == 0 || !peak.is_finite() {
+            return;
+        }
+        let rms = (sum_squares / count as f32).sqrt();
+        // Non-negative floats order the same as their bit patterns
+        self.peak.fetch_max(peak.to_bits(), Ordering::Relaxed);
+        self.rms.fetch_max(rms.to_bits(), Ordering::Relaxed);
+        if peak >= CLIP_LEVEL {
+            self.clipped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Current levels for display. Call from the UI thread only: each call consumes
+    /// what the audio thread published since the previous one.
+    pub fn display(&self) -> MeterDisplay {
+        let peak = f32::from_bits(self.peak.swap(0, Ordering::Relaxed));
+        let rms = f32::from_bits(self.rms.swap(0, Ordering::Relaxed));
+        let clipped = self.clipped.load(Ordering::Relaxed);
+
+        let Ok(mut ballistics) = self.ballistics.lock() else {
+            return MeterDisplay {
+                peak,
+                rms,
+                hold: peak,
+                clipped,
+            };
+        };
+        let now = Instant::now();
+        let elapsed = ballistics
+            .last_read
+            .map_or(0.0, |t| now.duration_since(t).as_secs_f32());
+        let fall = 10f32.powf(-DECAY_DB_PER_SECOND * elapsed / 20.0);
+
+        let shown = ballistics.shown;
+        let mut next = MeterDisplay {
+            peak: peak.max(shown.peak * fall),
+            rms: rms.max(shown.rms * fall),
+            hold: shown.hold,
+            clipped,
+        };
+        if next.peak >= shown.hold {
+            next.hold = next.peak;
+            ballistics.held_at = Some(now);
+        } else if ballistics
+            .held_at
+            .is_none_or(|t| now.duration_since(t) >= PEAK_HOLD)
+        {
+            next.hold = (shown.hold * fall).max(next.peak);
+        }
+
+        ballistics.shown = next;
+        ballistics.last_read = Some(now);
+        next
+    }
+
+    /// Clear the latched clip indicator.
+    pub fn reset_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Linear level in dBFS, floored at `METER_FLOOR_DB`.
+pub fn level_db(level: f32) -> f32 {
+    if level <= 0.0 {
+        return METER_FLOOR_DB;
+    }
+    (20.0 * level.log10()).max(METER_FLOOR_DB)
+}
@@ -0,0 +1,280 @@
+use crate::tempo::TempoMap;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use strum::{EnumIter, IntoEnumIterator};
+
+pub const MAX_COUNT_IN_BARS: u32 = 4;
+const CLICK_SECONDS: f32 = 0.05;
+// Accented clicks are this much louder than the other beats
+const ACCENT_GAIN: f32 = 1.0;
+const BEAT_GAIN: f32 = 0.6;
+
+/// Click sound, each with a higher-pitched variant for downbeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, EnumIter)]
+pub enum ClickSound {
+    #[default]
+    Beep,
+    Woodblock,
+    Stick,
+}
+
+impl ClickSound {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClickSound::Beep => "Beep",
+            ClickSound::Woodblock => "Woodblock",
+            ClickSound::Stick => "Stick",
+        }
+    }
+
+    /// The sound after this one, wrapping around.
+    pub fn next(&self) -> ClickSound {
+        let sounds: Vec<ClickSound> = ClickSound::iter().collect();
+        let idx = sounds.iter().position(|s| s == self).unwrap_or(0);
+        sounds[(idx + 1) % sounds.len()]
+    }
+
+    /// One mono click at full scale.
+    fn render(&self, accent: bool, sample_rate: u32) -> Vec<f32> {
+        let len = (CLICK_SECONDS * sample_rate as f32) as usize;
+        let sr = sample_rate as f32;
+        let (pitch, decay) = match (self, accent) {
+            (ClickSound::Beep, false) => (1000.0, 60.0),
+            (ClickSound::Beep, true) => (1500.0, 60.0),
+            (ClickSound::Woodblock, false) => (800.0, 150.0),
+            (ClickSound::Woodblock, true) => (1200.0, 150.0),
+            (ClickSound::Stick, false) => (2500.0, 250.0),
+            (ClickSound::Stick, true) => (3500.0, 250.0),
+        };
+        // A fixed seed keeps every click identical
+        let mut noise_state: u32 = 0x1234_5678;
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / sr;
+                let envelope = (-decay * t).exp();
+                let tone = (TAU * pitch * t).sin();
+                let sample = match self {
+                    ClickSound::Beep => tone,
+                    ClickSound::Woodblock => 0.7 * tone + 0.3 * (TAU * pitch * 2.7 * t).sin(),
+                    ClickSound::Stick => {
+                        noise_state ^= noise_state << 13;
+                        noise_state ^= noise_state >> 17;
+                        noise_state ^= noise_state << 5;
+                        let noise = noise_state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                        0.5 * tone + 0.5 * noise
+                    }
+                };
+                sample * envelope
+            })
+            .collect()
+    }
+}
+
+/// Metronome preferences, saved with the project.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MetronomeSettings {
+    /// Click along with playback and recording; the count-in clicks either way
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub sound: ClickSound,
+    #[serde(default = "default_level")]
+    pub level: f32,
+    /// Bars clicked before recording starts capturing
+    #[serde(default)]
+    pub count_in_bars: u32,
+    /// Send the click straight to the output, after the master channel, so it never
+    /// shows up on the master meter or in a render of the mix. Otherwise it is mixed
+    /// in before the master channel like any other track.
+    #[serde(default = "default_output_only")]
+    pub output_only: bool,
+}
+
+fn default_level() -> f32 {
+    0.5
+}
+
+fn default_output_only() -> bool {
+    true
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        MetronomeSettings {
+            enabled: false,
+            sound: ClickSound::default(),
+            level: default_level(),
+            count_in_bars: 0,
+            output_only: default_output_only(),
+        }
+    }
+}
+
+// Clicks played before the timeline starts moving
+struct CountIn {
+    remaining: u64,
+    elapsed: u64,
+    beat_frames: f64,
+    beats_per_bar: u32,
+}
+
+/// Real-time click generator run by the mixer. Clicks follow the tempo map, with the
+/// first beat of every bar accented.
+pub struct Metronome {
+    tempo: TempoMap,
+    sample_rate: u32,
+    enabled: bool,
+    output_only: bool,
+    level: f32,
+    accent_click: Vec<f32>,
+    beat_click: Vec<f32>,
+    // Click being played and how far into it we are
+    sounding: Option<(bool, usize)>,
+    // Next beat ahead of `expected_position`, recomputed when playback jumps
+    next_beat: Option<(u64, bool)>,
+    expected_position: u64,
+    count_in: Option<CountIn>,
+}
+
+impl Metronome {
+    pub fn new(settings: &MetronomeSettings, tempo: &TempoMap, sample_rate: u32) -> Self {
+        Metronome {
+            tempo: tempo.clone(),
+            sample_rate,
+            enabled: settings.enabled,
+            output_only: settings.output_only,
+            level: settings.level.clamp(0.0, 1.0),
+            accent_click: settings.sound.render(true, sample_rate),
+            beat_click: settings.sound.render(false, sample_rate),
+            sounding: None,
+            next_beat: None,
+            expected_position: 0,
+            count_in: None,
+        }
+    }
+
+    /// Click `bars` bars at the tempo of the bar containing `from` before the timeline
+    /// starts moving.
+    pub fn with_count_in(mut self, bars: u32, from: u64) -> Self {
+        if bars == 0 {
+            return self;
+        }
+        let bar = self.tempo.frames_to_musical(from, self.sample_rate).bar;
+        let (beat_frames, beats_per_bar) = self.tempo.beat_at_bar(bar, self.sample_rate);
+        self.count_in = Some(CountIn {
+            remaining: (beat_frames * (beats_per_bar * bars) as f64).round() as u64,
+            elapsed: 0,
+            beat_frames,
+            beats_per_bar,
+        });
+        self
+    }
+
+    /// Frames of count-in left before playback starts.
+    pub fn count_in_remaining(&self) -> u64 {
+        self.count_in.as_ref().map_or(0, |c| c.remaining)
+    }
+
+    /// True while there are clicks to come: the click is on or still counting in.
+    pub fn is_clicking(&self) -> bool {
+        self.enabled || self.count_in.is_some()
+    }
+
+    pub fn output_only(&self) -> bool {
+        self.output_only
+    }
+
+    /// Add the count-in clicks for the next block; consumes the block's frames from the
+    /// count-in.
+    pub fn count_in_block(&mut self, block: &mut [f32], channels: usize) {
+        let Some(count_in) = self.count_in.as_mut() else {
+            return;
+        };
+        let frames = block.len() / channels;
+        for frame in block.chunks_exact_mut(channels) {
+            let beat = (count_in.elapsed as f64 / count_in.beat_frames).round();
+            if (beat * count_in.beat_frames).round() as u64 == count_in.elapsed {
+                let accent = (beat as u64).is_multiple_of(count_in.beats_per_bar as u64);
+                self.sounding = Some((accent, 0));
+            }
+            count_in.elapsed += 1;
+            Self::play(
+                &mut self.sounding,
+                frame,
+                self.level,
+                &self.accent_click,
+                &self.beat_click,
+            );
+        }
+        count_in.remaining = count_in.remaining.saturating_sub(frames as u64);
+        if count_in.remaining == 0 {
+            self.count_in = None;
+        }
+    }
+
+    /// Add clicks to a block of the timeline starting at `position`.
+    pub fn process(&mut self, position: u64, block: &mut [f32], channels: usize) {
+        if !self.enabled {
+            // Let a count-in click ring out
+            for frame in block.chunks_exact_mut(channels) {
+                Self::play(
+                    &mut self.sounding,
+                    frame,
+                    self.level,
+                    &self.accent_click,
+                    &self.beat_click,
+                );
+            }
+            return;
+        }
+        if self.next_beat.is_none() || position != self.expected_position {
+            let beat = self.tempo.next_beat(position, self.sample_rate);
+            self.next_beat = Some((beat.frame, beat.is_bar));
+        }
+        for (i, frame) in block.chunks_exact_mut(channels).enumerate() {
+            let at = position + i as u64;
+            if let Some((beat_frame, accent)) = self.next_beat {
+                if beat_frame == at {
+                    self.sounding = Some((accent, 0));
+                    let next = self.tempo.next_beat(at + 1, self.sample_rate);
+                    self.next_beat = Some((next.frame, next.is_bar));
+                }
+            }
+            Self::play(
+                &mut self.sounding,
+                frame,
+                self.level,
+                &self.accent_click,
+                &self.beat_click,
+            );
+        }
+        self.expected_position = position + (block.len() / channels) as u64;
+    }
+
+    // Mix the next sample of the sounding click into every channel of `frame`
+    fn play(
+        sounding: &mut Option<(bool, usize)>,
+        frame: &mut [f32],
+        level: f32,
+        accent_click: &[f32],
+        beat_click: &[f32],
+    ) {
+        let Some((accent, pos)) = sounding.as_mut() else {
+            return;
+        };
+        let (click, gain) = if *accent {
+            (accent_click, ACCENT_GAIN)
+        } else {
+            (beat_click, BEAT_GAIN)
+        };
+        match click.get(*pos) {
+            Some(&sample) => {
+                for out in frame.iter_mut() {
+                    *out += sample * gain * level;
+                }
+                *pos += 1;
+            }
+            None => *sounding = None,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::voice::{add_scaled, scaled_levels};
+use super::{chain_processors, VoiceParams, MAX_BLOCK_FRAMES};
+use crate::bus::AuxBus;
+use crate::effects::{EffectInstance, ProcessorBox};
+use crate::meter::Meter;
+use crate::track::Track;
+use std::sync::Arc;
+
+/// A summing bus inside the real-time mixer, used for aux returns and group submixes:
+/// inputs are summed into `input`, run through the bus FX processors, and added to
+/// the destination at the bus volume and pan.
+pub struct BusVoice {
+    fx: Vec<ProcessorBox>,
+    params: Arc<VoiceParams>,
+    input: Vec<f32>,
+    block_len: usize,
+    tail_frames: u64,
+    meter: Option<Arc<Meter>>,
+    last_gains: Option<(f32, f32)>,
+}
+
+impl BusVoice {
+    /// Build the voice for an aux bus. Effects that cannot stream are bypassed.
+    pub fn from_bus(bus: &AuxBus, sample_rate: u32, channels: u16) -> Self {
+        let params = VoiceParams::new(bus.volume as f32, 0.0, bus.muted);
+        Self::new(&bus.fx_chain, params, sample_rate, channels)
+    }
+
+    /// Build the submix voice for a group track: its FX chain, volume, pan and mute
+    /// apply to the sum of its children.
+    pub fn from_group(track: &Track, muted: bool, sample_rate: u32, channels: u16) -> Self {
+        let params = VoiceParams::new(track.volume as f32, track.pan, muted);
+        Self::new(&track.fx_chain, params, sample_rate, channels)
+    }
+
+    fn new(
+        fx_chain: &[EffectInstance],
+        params: VoiceParams,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Self {
+        let (fx, tail_frames) = chain_processors(fx_chain, sample_rate, channels);
+        let params = Arc::new(params);
+        BusVoice {
+            fx,
+            params,
+            input: vec![0.0; MAX_BLOCK_FRAMES * channels.max(1) as usize],
+            block_len: 0,
+            tail_frames,
+            meter: None,
+            last_gains: None,
+        }
+    }
+
+    /// Publish the bus output level to `meter`.
+    pub fn with_meter(mut self, meter: Arc<Meter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    pub fn params(&self) -> Arc<VoiceParams> {
+        Arc::clone(&self.params)
+    }
+
+    /// How long the bus keeps ringing after its last send goes quiet.
+    pub fn tail_frames(&self) -> u64 {
+        self.tail_frames
+    }
+
+    /// Swap in new FX processors, returning the old ones so they can be freed off the
+    /// audio thread.
+    pub(crate) fn replace_fx(
+        &mut self,
+        fx: Vec<ProcessorBox>,
+        tail_frames: u64,
+    ) -> Vec<ProcessorBox> {
+        self.tail_frames = tail_frames;
+        std::mem::replace(&mut self.fx, fx)
+    }
+
+    /// Clear the input for a new block of `len` interleaved samples.
+    pub(crate) fn begin_block(&mut self, len: usize) {
+        self.block_len = len;
+        self.input[..len].fill(0.0);
+    }
+
+    pub(crate) fn input_mut(&mut self) -> &mut [f32] {
+        &mut self.input[..self.block_len]
+    }
+
+    /// Process the summed sends and add the bus return into `out`.
+    pub(crate) fn process(&mut self, out: &mut [f32], channels: usize) {
+        let input = &mut self.input[..self.block_len];
+        for fx in self.fx.iter_mut() {
+            fx.process(input);
+        }
+
+        let (target_left, target_right) = self.params.gains(channels == 2);
+        let (start_left, start_right) = self.last_gains.unwrap_or((target_left, target_right));
+        let step = 1.0 / (input.len() / channels).max(1) as f32;
+        let (mut peak, mut sum_squares) = (0.0f32, 0.0f32);
+
+        for (f, (src, dst)) in input
+            .chunks_exact(channels)
+            .zip(out.chunks_exact_mut(channels))
+            .enumerate()
+        {
+            let t = (f + 1) as f32 * step;
+            let left = start_left + (target_left - start_left) * t;
+            let right = start_right + (target_right - start_right) * t;
+            add_scaled(src, dst, left, right);
+            if self.meter.is_some() {
+                let (frame_peak, frame_squares) = scaled_levels(src, left, right);
+                peak = peak.max(frame_peak);
+                sum_squares += frame_squares;
+            }
+        }
+
+        if let Some(meter) = &self.meter {
+            meter.publish(peak, sum_squares, input.len());
+        }
+        self.last_gains = Some((target_left, target_right));
+    }
+}
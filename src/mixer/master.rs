@@ -0,0 +1,73 @@
+use super::{chain_processors, VoiceParams};
+use crate::bus::MasterChannel;
+use crate::effects::ProcessorBox;
+use crate::meter::Meter;
+use std::sync::Arc;
+
+/// The master channel inside the real-time mixer: runs the summed block through the
+/// master FX processors, then applies the master gain.
+pub struct MasterStage {
+    fx: Vec<ProcessorBox>,
+    params: Arc<VoiceParams>,
+    tail_frames: u64,
+    meter: Option<Arc<Meter>>,
+    last_gain: Option<f32>,
+}
+
+impl MasterStage {
+    pub fn from_channel(master: &MasterChannel, sample_rate: u32, channels: u16) -> Self {
+        let (fx, tail_frames) = chain_processors(&master.fx_chain, sample_rate, channels);
+        MasterStage {
+            fx,
+            params: Arc::new(VoiceParams::new(master.gain as f32, 0.0, false)),
+            tail_frames,
+            meter: None,
+            last_gain: None,
+        }
+    }
+
+    /// Publish the master output level to `meter`.
+    pub fn with_meter(mut self, meter: Arc<Meter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    pub fn params(&self) -> Arc<VoiceParams> {
+        Arc::clone(&self.params)
+    }
+
+    pub fn tail_frames(&self) -> u64 {
+        self.tail_frames
+    }
+
+    /// Swap in new FX processors, returning the old ones so they can be freed off the
+    /// audio thread.
+    pub(crate) fn replace_fx(
+        &mut self,
+        fx: Vec<ProcessorBox>,
+        tail_frames: u64,
+    ) -> Vec<ProcessorBox> {
+        self.tail_frames = tail_frames;
+        std::mem::replace(&mut self.fx, fx)
+    }
+
+    pub(crate) fn process(&mut self, block: &mut [f32], channels: usize) {
+        for fx in self.fx.iter_mut() {
+            fx.process(block);
+        }
+
+        let (target, _) = self.params.gains(false);
+        let start = self.last_gain.unwrap_or(target);
+        let step = 1.0 / (block.len() / channels).max(1) as f32;
+        for (f, frame) in block.chunks_exact_mut(channels).enumerate() {
+            let gain = start + (target - start) * (f + 1) as f32 * step;
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
+        if let Some(meter) = &self.meter {
+            meter.measure(block);
+        }
+        self.last_gain = Some(target);
+    }
+}
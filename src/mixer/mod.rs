@@ -0,0 +1,544 @@
+mod bus;
+mod master;
+mod routing;
+mod voice;
+
+pub use bus::BusVoice;
+pub use master::MasterStage;
+pub use routing::Routing;
+pub use voice::{TrackVoice, VoiceChain, VoiceParams};
+
+use crate::bus::AuxSend;
+use crate::effects::{EffectInstance, ProcessorBox};
+use crate::metronome::Metronome;
+
+use ringbuf::{
+    traits::{Consumer, Observer, Producer, Split},
+    HeapCons, HeapProd, HeapRb,
+};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Largest block processed in one pass; bigger requests are split so scratch never reallocates.
+const MAX_BLOCK_FRAMES: usize = 1024;
+const COMMAND_QUEUE_SIZE: usize = 64;
+// Voices and buses that can be added during playback before the UI sends bigger storage
+const SPARE_SLOTS: usize = 16;
+
+/// Streaming processors for an FX chain on a bus, group or the master, with the chain's
+/// combined tail in frames. Effects that cannot stream are bypassed.
+pub fn chain_processors(
+    chain: &[EffectInstance],
+    sample_rate: u32,
+    channels: u16,
+) -> (Vec<ProcessorBox>, u64) {
+    let fx = chain
+        .iter()
+        .filter_map(|fx| fx.processor(sample_rate, channels))
+        .collect();
+    let tail_seconds: f32 = chain.iter().map(|fx| fx.tail_seconds()).sum();
+    (fx, (tail_seconds * sample_rate as f32) as u64)
+}
+
+/// Structural changes sent from the UI thread to the audio thread.
+pub enum MixerCommand {
+    AddVoice(TrackVoice),
+    RemoveVoice(usize),
+    /// Empty storage with room for more voices, taken over before the current
+    /// storage runs out so adding a voice never allocates on the audio thread
+    GrowVoices {
+        voices: Vec<TrackVoice>,
+        track_outputs: Vec<Option<usize>>,
+    },
+    ReplaceChain {
+        track: usize,
+        chain: VoiceChain,
+    },
+    SetSends {
+        track: usize,
+        sends: Vec<AuxSend>,
+    },
+    AddBus(BusVoice),
+    RemoveBus(usize),
+    GrowBuses(Vec<BusVoice>),
+    ReplaceBusFx {
+        bus: usize,
+        fx: Vec<ProcessorBox>,
+        tail_frames: u64,
+    },
+    ReplaceMasterFx {
+        fx: Vec<ProcessorBox>,
+        tail_frames: u64,
+    },
+    ReplaceRouting(Routing),
+    SetLoop(Option<Range<u64>>),
+    SetMetronome(Option<Box<Metronome>>),
+}
+
+/// Objects the audio thread is done with, handed back so they are freed off the audio thread.
+/// Each command hands back at most one.
+pub enum Retired {
+    Voice(TrackVoice),
+    VoiceStorage(Vec<TrackVoice>, Vec<Option<usize>>),
+    BusStorage(Vec<BusVoice>),
+    Chain(VoiceChain),
+    Sends(Vec<AuxSend>),
+    Bus(BusVoice),
+    Processors(Vec<ProcessorBox>),
+    Routing(Routing),
+    Metronome(Box<Metronome>),
+}
+
+/// Real-time mixing graph, owned by the audio callback.
+/// Each call to `process` pulls one block from every track voice, sums them through
+/// their group submixes, feeds their sends through the aux buses and runs the sum
+/// through the master channel. With a loop set, playback jumps from the loop end back
+/// to its start without a gap. An optional metronome clicks along and can hold the
+/// timeline for a count-in.
+pub struct Mixer {
+    voices: Vec<TrackVoice>,
+    routing: Routing,
+    buses: Vec<BusVoice>,
+    master: MasterStage,
+    channels: usize,
+    position: u64,
+    // Published after every block so the UI can follow the playhead across loops
+    shared_position: Arc<AtomicU64>,
+    loop_range: Option<Range<u64>>,
+    metronome: Option<Box<Metronome>>,
+    scratch: Vec<f32>,
+    commands: HeapCons<MixerCommand>,
+    retired: HeapProd<Retired>,
+}
+
+/// UI-side handle for a running `Mixer`: parameter updates are plain atomic stores,
+/// structural changes go through a lock-free command queue. Commands that find the
+/// queue full wait in a backlog and are sent, in order, as it drains.
+pub struct MixerHandle {
+    params: Vec<Arc<VoiceParams>>,
+    bus_params: Vec<Arc<VoiceParams>>,
+    group_params: Vec<Arc<VoiceParams>>,
+    master_params: Arc<VoiceParams>,
+    position: Arc<AtomicU64>,
+    commands: HeapProd<MixerCommand>,
+    backlog: VecDeque<MixerCommand>,
+    retired: HeapCons<Retired>,
+    // Voices and buses the mixer's storage holds without reallocating
+    voice_capacity: usize,
+    bus_capacity: usize,
+}
+
+impl Mixer {
+    pub fn new(
+        voices: Vec<TrackVoice>,
+        routing: Routing,
+        buses: Vec<BusVoice>,
+        master: MasterStage,
+        start_frame: u64,
+        channels: u16,
+    ) -> (Mixer, MixerHandle) {
+        let (command_prod, command_cons) = HeapRb::<MixerCommand>::new(COMMAND_QUEUE_SIZE).split();
+        let (retired_prod, retired_cons) = HeapRb::<Retired>::new(COMMAND_QUEUE_SIZE).split();
+
+        let channels = channels.max(1) as usize;
+        let params = voices.iter().map(|v| v.params()).collect();
+        let bus_params = buses.iter().map(|b| b.params()).collect();
+        let group_params = routing.groups.iter().map(|g| g.params()).collect();
+        let master_params = master.params();
+        // Leave room for voices and buses added during playback without reallocating
+        // on the audio thread
+        let mut voices = voices;
+        voices.reserve_exact(SPARE_SLOTS);
+        let mut buses = buses;
+        buses.reserve_exact(SPARE_SLOTS);
+        let mut routing = routing;
+        routing.track_outputs.resize(voices.len(), None);
+        routing
+            .track_outputs
+            .reserve_exact(voices.capacity() - voices.len());
+        let voice_capacity = voices.capacity().min(routing.track_outputs.capacity());
+        let bus_capacity = buses.capacity();
+        let position = Arc::new(AtomicU64::new(start_frame));
+
+        let mixer = Mixer {
+            voices,
+            routing,
+            buses,
+            master,
+            channels,
+            position: start_frame,
+            shared_position: Arc::clone(&position),
+            loop_range: None,
+            metronome: None,
+            scratch: vec![0.0; MAX_BLOCK_FRAMES * channels],
+            commands: command_cons,
+            retired: retired_prod,
+        };
+        let handle = MixerHandle {
+            params,
+            bus_params,
+            group_params,
+            master_params,
+            position,
+            commands: command_prod,
+            backlog: VecDeque::new(),
+            retired: retired_cons,
+            voice_capacity,
+            bus_capacity,
+        };
+        (mixer, handle)
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    /// Timeline frame of the next block to be rendered.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Frame at which the last voice (including FX tails, the tails of the groups
+    /// and buses it feeds and the master tail) goes silent.
+    pub fn end_frame(&self) -> u64 {
+        let mut end = 0;
+        for (i, voice) in self.voices.iter().enumerate() {
+            if voice.end_frame() == 0 {
+                continue;
+            }
+            end = end.max(voice.end_frame() + self.routing.tail_frames(i));
+            for send in voice.sends() {
+                if let Some(bus) = self.buses.get(send.bus) {
+                    end = end.max(voice.end_frame() + bus.tail_frames());
+                }
+            }
+        }
+        if end == 0 {
+            return 0;
+        }
+        end + self.master.tail_frames()
+    }
+
+    /// Cycle between `region.start` and `region.end` once playback reaches the region,
+    /// or play straight through with `None`. The region must not be empty.
+    pub fn set_loop(&mut self, region: Option<Range<u64>>) {
+        self.loop_range = region.filter(|r| r.start < r.end);
+    }
+
+    pub fn set_metronome(&mut self, metronome: Option<Metronome>) {
+        self.metronome = metronome.map(Box::new);
+    }
+
+    /// True once playback has passed the last voice. A loop that has not been left
+    /// behind or a clicking metronome keeps the mixer running.
+    pub fn is_finished(&self) -> bool {
+        if self.metronome.as_ref().is_some_and(|m| m.is_clicking()) {
+            return false;
+        }
+        if self
+            .loop_range
+            .as_ref()
+            .is_some_and(|r| self.position < r.end)
+        {
+            return false;
+        }
+        self.position >= self.end_frame()
+    }
+
+    /// Fill `out` (interleaved, `channels()` wide) with the next block of the mix.
+    pub fn process(&mut self, out: &mut [f32]) {
+        self.apply_commands();
+
+        out.fill(0.0);
+        let mut offset = 0;
+        while offset < out.len() {
+            let mut frames = ((out.len() - offset) / self.channels).min(MAX_BLOCK_FRAMES);
+            // Blocks stop at the loop end so the jump back lands exactly on a block edge
+            let loop_end = self
+                .loop_range
+                .as_ref()
+                .filter(|r| self.position < r.end)
+                .map(|r| r.end);
+            if let Some(end) = loop_end {
+                frames = frames.min((end - self.position) as usize);
+            }
+            if frames == 0 {
+                break;
+            }
+
+            // The timeline waits while the count-in clicks
+            if let Some(metronome) = self
+                .metronome
+                .as_mut()
+                .filter(|m| m.count_in_remaining() > 0)
+            {
+                let frames = frames.min(metronome.count_in_remaining() as usize);
+                let block = &mut out[offset..offset + frames * self.channels];
+                offset += block.len();
+                metronome.count_in_block(block, self.channels);
+                continue;
+            }
+
+            let block = &mut out[offset..offset + frames * self.channels];
+            offset += block.len();
+
+            for bus in self.buses.iter_mut() {
+                bus.begin_block(block.len());
+            }
+            self.routing.begin_block(block.len());
+            let scratch = &mut self.scratch[..block.len()];
+            for (i, voice) in self.voices.iter_mut().enumerate() {
+                let dst = match self.routing.track_output(i) {
+                    Some(slot) => self.routing.groups[slot].input_mut(),
+                    None => &mut *block,
+                };
+                voice.process(self.position, scratch, dst, &mut self.buses, self.channels);
+            }
+            self.routing.process(block, self.channels);
+            for bus in self.buses.iter_mut() {
+                bus.process(block, self.channels);
+            }
+            if let Some(metronome) = self.metronome.as_mut() {
+                if !metronome.output_only() {
+                    metronome.process(self.position, block, self.channels);
+                }
+            }
+            self.master.process(block, self.channels);
+            if let Some(metronome) = self.metronome.as_mut() {
+                if metronome.output_only() {
+                    metronome.process(self.position, block, self.channels);
+                }
+            }
+            self.position += frames as u64;
+            if loop_end == Some(self.position) {
+                if let Some(region) = &self.loop_range {
+                    self.position = region.start;
+                }
+            }
+        }
+        self.shared_position.store(self.position, Ordering::Relaxed);
+    }
+
+    fn apply_commands(&mut self) {
+        // A command is only taken while there is room to hand back what it replaces,
+        // so nothing is ever freed here; the rest wait for the next block
+        while self.retired.vacant_len() > 0 {
+            let Some(command) = self.commands.try_pop() else {
+                break;
+            };
+            match command {
+                MixerCommand::AddVoice(voice) => {
+                    self.voices.push(voice);
+                    self.routing.track_outputs.push(None);
+                }
+                MixerCommand::RemoveVoice(index) => {
+                    if index < self.voices.len() {
+                        let voice = self.voices.remove(index);
+                        if index < self.routing.track_outputs.len() {
+                            self.routing.track_outputs.remove(index);
+                        }
+                        self.retire(Retired::Voice(voice));
+                    }
+                }
+                MixerCommand::GrowVoices {
+                    mut voices,
+                    mut track_outputs,
+                } => {
+                    voices.append(&mut self.voices);
+                    track_outputs.append(&mut self.routing.track_outputs);
+                    let old_voices = std::mem::replace(&mut self.voices, voices);
+                    let old_outputs =
+                        std::mem::replace(&mut self.routing.track_outputs, track_outputs);
+                    self.retire(Retired::VoiceStorage(old_voices, old_outputs));
+                }
+                MixerCommand::ReplaceChain { track, chain } => {
+                    if let Some(voice) = self.voices.get_mut(track) {
+                        let old = voice.replace_chain(chain);
+                        self.retire(Retired::Chain(old));
+                    }
+                }
+                MixerCommand::SetSends { track, sends } => {
+                    if let Some(voice) = self.voices.get_mut(track) {
+                        let old = voice.replace_sends(sends);
+                        self.retire(Retired::Sends(old));
+                    }
+                }
+                MixerCommand::AddBus(bus) => self.buses.push(bus),
+                MixerCommand::RemoveBus(index) => {
+                    if index < self.buses.len() {
+                        let bus = self.buses.remove(index);
+                        self.retire(Retired::Bus(bus));
+                    }
+                }
+                MixerCommand::GrowBuses(mut buses) => {
+                    buses.append(&mut self.buses);
+                    let old = std::mem::replace(&mut self.buses, buses);
+                    self.retire(Retired::BusStorage(old));
+                }
+                MixerCommand::ReplaceBusFx {
+                    bus,
+                    fx,
+                    tail_frames,
+                } => {
+                    if let Some(bus) = self.buses.get_mut(bus) {
+                        let old = bus.replace_fx(fx, tail_frames);
+                        self.retire(Retired::Processors(old));
+                    }
+                }
+                MixerCommand::ReplaceMasterFx { fx, tail_frames } => {
+                    let old = self.master.replace_fx(fx, tail_frames);
+                    self.retire(Retired::Processors(old));
+                }
+                MixerCommand::ReplaceRouting(routing) => {
+                    let old = std::mem::replace(&mut self.routing, routing);
+                    self.retire(Retired::Routing(old));
+                }
+                MixerCommand::SetLoop(region) => self.set_loop(region),
+                MixerCommand::SetMetronome(metronome) => {
+                    if let Some(old) = std::mem::replace(&mut self.metronome, metronome) {
+                        self.retire(Retired::Metronome(old));
+                    }
+                }
+            }
+        }
+    }
+
+    // Hand an object back to the UI thread. `apply_commands` makes sure there is room;
+    // were there not, leaking it is still better than freeing it here.
+    fn retire(&mut self, object: Retired) {
+        if let Err(object) = self.retired.try_push(object) {
+            std::mem::forget(object);
+        }
+    }
+}
+
+impl MixerHandle {
+    /// Timeline frame the mixer has rendered up to, following loop jumps.
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    pub fn set_loop(&mut self, region: Option<Range<u64>>) {
+        self.send(MixerCommand::SetLoop(region));
+    }
+
+    pub fn set_metronome(&mut self, metronome: Option<Metronome>) {
+        self.send(MixerCommand::SetMetronome(metronome.map(Box::new)));
+    }
+
+    /// Publish a track's fader state to the audio thread.
+    pub fn set_params(&self, track: usize, volume: f32, pan: f32, muted: bool) {
+        if let Some(params) = self.params.get(track) {
+            params.set(volume, pan, muted);
+        }
+    }
+
+    /// Publish an aux bus's fader state to the audio thread.
+    pub fn set_bus_params(&self, bus: usize, volume: f32, muted: bool) {
+        if let Some(params) = self.bus_params.get(bus) {
+            params.set(volume, 0.0, muted);
+        }
+    }
+
+    /// Publish a group submix's fader state to the audio thread.
+    pub fn set_group_params(&self, slot: usize, volume: f32, pan: f32, muted: bool) {
+        if let Some(params) = self.group_params.get(slot) {
+            params.set(volume, pan, muted);
+        }
+    }
+
+    /// Publish the master gain to the audio thread.
+    pub fn set_master_gain(&self, gain: f32) {
+        self.master_params.set(gain, 0.0, false);
+    }
+
+    pub fn add_voice(&mut self, voice: TrackVoice) {
+        if self.params.len() == self.voice_capacity {
+            self.voice_capacity = (self.voice_capacity * 2).max(SPARE_SLOTS);
+            self.send(MixerCommand::GrowVoices {
+                voices: Vec::with_capacity(self.voice_capacity),
+                track_outputs: Vec::with_capacity(self.voice_capacity),
+            });
+        }
+        self.params.push(voice.params());
+        self.send(MixerCommand::AddVoice(voice));
+    }
+
+    pub fn remove_voice(&mut self, index: usize) {
+        if index < self.params.len() {
+            self.params.remove(index);
+            self.send(MixerCommand::RemoveVoice(index));
+        }
+    }
+
+    pub fn replace_chain(&mut self, track: usize, chain: VoiceChain) {
+        self.send(MixerCommand::ReplaceChain { track, chain });
+    }
+
+    pub fn set_sends(&mut self, track: usize, sends: Vec<AuxSend>) {
+        self.send(MixerCommand::SetSends { track, sends });
+    }
+
+    pub fn add_bus(&mut self, bus: BusVoice) {
+        if self.bus_params.len() == self.bus_capacity {
+            self.bus_capacity = (self.bus_capacity * 2).max(SPARE_SLOTS);
+            let buses = Vec::with_capacity(self.bus_capacity);
+            self.send(MixerCommand::GrowBuses(buses));
+        }
+        self.bus_params.push(bus.params());
+        self.send(MixerCommand::AddBus(bus));
+    }
+
+    pub fn remove_bus(&mut self, index: usize) {
+        if index < self.bus_params.len() {
+            self.bus_params.remove(index);
+            self.send(MixerCommand::RemoveBus(index));
+        }
+    }
+
+    pub fn replace_bus_fx(&mut self, bus: usize, fx: Vec<ProcessorBox>, tail_frames: u64) {
+        self.send(MixerCommand::ReplaceBusFx {
+            bus,
+            fx,
+            tail_frames,
+        });
+    }
+
+    pub fn replace_master_fx(&mut self, fx: Vec<ProcessorBox>, tail_frames: u64) {
+        self.send(MixerCommand::ReplaceMasterFx { fx, tail_frames });
+    }
+
+    /// Swap in new group routing. `routing.track_outputs` must cover every voice,
+    /// including ones added since the mixer was built.
+    pub fn replace_routing(&mut self, mut routing: Routing) {
+        // Voices added later push onto these outputs on the audio thread
+        let outputs = &mut routing.track_outputs;
+        outputs.reserve_exact(self.voice_capacity.saturating_sub(outputs.len()));
+        self.group_params = routing.groups.iter().map(|g| g.params()).collect();
+        self.send(MixerCommand::ReplaceRouting(routing));
+    }
+
+    /// Drop everything the audio thread has handed back, and pass on commands that
+    /// were waiting for room in the queue.
+    pub fn collect_retired(&mut self) {
+        while self.retired.try_pop().is_some() {}
+        self.flush();
+    }
+
+    // Queue a command behind any that are still waiting
+    fn send(&mut self, command: MixerCommand) {
+        self.backlog.push_back(command);
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while let Some(command) = self.backlog.pop_front() {
+            if let Err(command) = self.commands.try_push(command) {
+                self.backlog.push_front(command);
+                break;
+            }
+        }
+    }
+}
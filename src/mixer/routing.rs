@@ -0,0 +1,65 @@
+use super::BusVoice;
+
+/// How track voices are summed through group submixes before reaching the master.
+#[derive(Default)]
+pub struct Routing {
+    /// Group submix buses, ordered children first so each group is processed
+    /// before the group it feeds.
+    pub groups: Vec<BusVoice>,
+    /// Slot of the group each group bus feeds, `None` for the master.
+    /// A group only ever feeds a later slot.
+    pub group_outputs: Vec<Option<usize>>,
+    /// Slot of the group each track voice feeds, `None` for the master.
+    pub track_outputs: Vec<Option<usize>>,
+}
+
+impl Routing {
+    /// Group slot a track voice feeds, if that slot exists.
+    pub(crate) fn track_output(&self, track: usize) -> Option<usize> {
+        self.track_outputs
+            .get(track)
+            .copied()
+            .flatten()
+            .filter(|&slot| slot < self.groups.len())
+    }
+
+    /// Group slot a group bus feeds, if it is a valid later slot.
+    pub(crate) fn group_output(&self, slot: usize) -> Option<usize> {
+        self.group_outputs
+            .get(slot)
+            .copied()
+            .flatten()
+            .filter(|&parent| parent > slot && parent < self.groups.len())
+    }
+
+    /// Combined FX tail of every group between a track and the master.
+    pub(crate) fn tail_frames(&self, track: usize) -> u64 {
+        let mut tail = 0;
+        let mut slot = self.track_output(track);
+        while let Some(s) = slot {
+            tail += self.groups[s].tail_frames();
+            slot = self.group_output(s);
+        }
+        tail
+    }
+
+    /// Clear every group input for a new block of `len` interleaved samples.
+    pub(crate) fn begin_block(&mut self, len: usize) {
+        for group in self.groups.iter_mut() {
+            group.begin_block(len);
+        }
+    }
+
+    /// Process the groups children first, each one feeding its parent or `out`.
+    pub(crate) fn process(&mut self, out: &mut [f32], channels: usize) {
+        for slot in 0..self.groups.len() {
+            let parent = self.group_output(slot);
+            let (head, tail) = self.groups.split_at_mut(slot + 1);
+            let group = &mut head[slot];
+            match parent {
+                Some(p) => group.process(tail[p - slot - 1].input_mut(), channels),
+                None => group.process(out, channels),
+            }
+        }
+    }
+}
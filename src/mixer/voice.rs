@@ -0,0 +1,520 @@
+use super::BusVoice;
+use crate::bus::AuxSend;
+use crate::channels;
+use crate::effects::{EffectInstance, ProcessorBox};
+use crate::meter::Meter;
+use crate::track::{AutomationLane, AutomationTarget, ClipEnvelope, Track};
+use crate::wav::WavFile;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+// Clips with more channels than this are truncated when streamed.
+const MAX_CLIP_CHANNELS: usize = 32;
+
+/// Track parameters shared lock-free between the UI thread (writer) and the audio thread (reader).
+pub struct VoiceParams {
+    volume: AtomicU32, // f32 bits
+    pan: AtomicU32,    // f32 bits
+    muted: AtomicBool,
+}
+
+impl VoiceParams {
+    pub fn new(volume: f32, pan: f32, muted: bool) -> Self {
+        VoiceParams {
+            volume: AtomicU32::new(volume.to_bits()),
+            pan: AtomicU32::new(pan.to_bits()),
+            muted: AtomicBool::new(muted),
+        }
+    }
+
+    pub fn set(&self, volume: f32, pan: f32, muted: bool) {
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+        self.pan.store(pan.to_bits(), Ordering::Relaxed);
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    fn pan(&self) -> f32 {
+        f32::from_bits(self.pan.load(Ordering::Relaxed))
+    }
+
+    /// (left, right) gains: volume times balance for stereo, plain volume otherwise,
+    /// silence when muted.
+    pub(crate) fn gains(&self, stereo: bool) -> (f32, f32) {
+        mix_gains(self.volume(), self.pan(), self.is_muted(), stereo)
+    }
+}
+
+fn mix_gains(volume: f32, pan: f32, muted: bool, stereo: bool) -> (f32, f32) {
+    if muted {
+        return (0.0, 0.0);
+    }
+    if !stereo {
+        return (volume, volume);
+    }
+    let (left, right) = channels::balance_gains(pan);
+    (volume * left, volume * right)
+}
+
+/// The automation lanes a voice plays, evaluated for every frame.
+#[derive(Default)]
+struct VoiceAutomation {
+    volume: Option<AutomationLane>,
+    pan: Option<AutomationLane>,
+    mute: Option<AutomationLane>,
+    effects: Vec<AutomationLane>,
+}
+
+impl VoiceAutomation {
+    fn from_track(track: &Track) -> Self {
+        let mut automation = VoiceAutomation::default();
+        for lane in track.playing_lanes() {
+            let slot = match lane.target {
+                AutomationTarget::Volume => &mut automation.volume,
+                AutomationTarget::Pan => &mut automation.pan,
+                AutomationTarget::Mute => &mut automation.mute,
+                AutomationTarget::Effect { .. } => {
+                    automation.effects.push(lane.clone());
+                    continue;
+                }
+            };
+            *slot = Some(lane.clone());
+        }
+        automation
+    }
+
+    fn drives_gains(&self) -> bool {
+        self.volume.is_some() || self.pan.is_some() || self.mute.is_some()
+    }
+
+    fn drives_effect(&self, index: usize) -> bool {
+        self.effects
+            .iter()
+            .any(|l| matches!(l.target, AutomationTarget::Effect { index: i, .. } if i == index))
+    }
+
+    /// (left, right, muted) at timeline frame `frame`: automated where there is a lane,
+    /// from `params` otherwise. A track muted by the mixer stays muted.
+    fn gains(&self, params: &VoiceParams, frame: u64, stereo: bool) -> (f32, f32, bool) {
+        let value = |lane: &Option<AutomationLane>| lane.as_ref().and_then(|l| l.value_at(frame));
+        let volume = value(&self.volume).unwrap_or_else(|| params.volume());
+        let pan = value(&self.pan).unwrap_or_else(|| params.pan());
+        let muted = params.is_muted() || value(&self.mute).is_some_and(|v| v >= 0.5);
+        let (left, right) = mix_gains(volume, pan, muted, stereo);
+        (left, right, muted)
+    }
+}
+
+/// A streaming processor for `fx`. An automated effect is built with its parameters at
+/// the highest values its lanes reach, so buffers sized from them (a delay's history)
+/// are big enough for the whole lane.
+fn automated_processor(
+    fx: &EffectInstance,
+    index: usize,
+    lanes: &[AutomationLane],
+    sample_rate: u32,
+    channels: u16,
+) -> Option<ProcessorBox> {
+    let mut sized = fx.clone();
+    for lane in lanes {
+        let AutomationTarget::Effect { index: i, param } = &lane.target else {
+            continue;
+        };
+        if *i != index {
+            continue;
+        }
+        let current = fx
+            .parameters()
+            .into_iter()
+            .find(|(name, _)| name == param)
+            .map(|(_, value)| value);
+        let (Some(current), Some(highest)) = (
+            current,
+            lane.points.iter().map(|p| p.value).reduce(f32::max),
+        ) else {
+            continue;
+        };
+        let Ok(current) = current.parse::<f32>() else {
+            continue;
+        };
+        if highest <= current {
+            continue;
+        }
+        let value = if current.fract() == 0.0 {
+            format!("{}", highest.ceil())
+        } else {
+            highest.to_string()
+        };
+        if let Ok(updated) = sized.update_parameter(param, &value) {
+            sized = updated;
+        }
+    }
+    sized.processor(sample_rate, channels)
+}
+
+/// Streams one clip straight from its WAV data, decoding only the frames it needs.
+struct ClipReader {
+    audio: Arc<WavFile>,
+    starts_at: u64,
+    offset: u64, // first source frame played
+    frames: u64,
+    channels: usize,
+    envelope: ClipEnvelope,
+}
+
+impl ClipReader {
+    fn read_into(&self, position: u64, block: &mut [f32], out_channels: usize) {
+        let block_end = position + (block.len() / out_channels) as u64;
+        let clip_end = self.starts_at + self.frames;
+        if clip_end <= position || self.starts_at >= block_end || self.channels == 0 {
+            return;
+        }
+
+        let read_channels = self.channels.min(MAX_CLIP_CHANNELS);
+        let mut frame = [0.0f32; MAX_CLIP_CHANNELS];
+
+        for absolute in self.starts_at.max(position)..clip_end.min(block_end) {
+            let into_clip = absolute - self.starts_at;
+            let clip_frame = (into_clip + self.offset) as usize;
+            for (ch, sample) in frame[..read_channels].iter_mut().enumerate() {
+                *sample = self.audio.sample_at(clip_frame * self.channels + ch);
+            }
+            let out = (absolute - position) as usize * out_channels;
+            channels::mix_frame_into(
+                &frame[..read_channels],
+                &mut block[out..out + out_channels],
+                self.envelope.at(absolute),
+            );
+        }
+    }
+}
+
+enum VoiceSource {
+    Clips(Vec<ClipReader>),
+    /// Pre-fader offline render, used when the FX chain cannot stream.
+    /// Interleaved, beginning at frame `start`.
+    Rendered {
+        start: u64,
+        samples: Vec<f32>,
+    },
+}
+
+/// Everything a voice plays: its audio source, FX processors and where it ends.
+/// Swapped as a unit when clips or the FX chain change during playback.
+pub struct VoiceChain {
+    source: VoiceSource,
+    fx: Vec<ProcessorBox>,
+    automation: VoiceAutomation,
+    end_frame: u64,
+    // Clip audio stops here; effects keep ringing out
+    source_end: u64,
+    tail_frames: u64,
+}
+
+impl VoiceChain {
+    /// Build the chain for `track`. If every effect can stream, clips are read live and
+    /// processed block by block; otherwise the track is rendered offline from `from_frame`,
+    /// with the effects at their static settings.
+    pub fn from_track(track: &Track, from_frame: u64, sample_rate: u32, channels: u16) -> Self {
+        let automation = VoiceAutomation::from_track(track);
+        let processors: Option<Vec<ProcessorBox>> = track
+            .fx_chain
+            .iter()
+            .enumerate()
+            .map(|(i, fx)| {
+                if automation.drives_effect(i) {
+                    automated_processor(fx, i, &automation.effects, sample_rate, channels)
+                } else {
+                    fx.processor(sample_rate, channels)
+                }
+            })
+            .collect();
+
+        match processors {
+            Some(fx) => {
+                let readers = track
+                    .clip_playback(sample_rate)
+                    .into_iter()
+                    .map(|playback| {
+                        let clip = &track.clips[playback.clip];
+                        ClipReader {
+                            audio: Arc::clone(&clip.wav_data),
+                            starts_at: playback.starts_at,
+                            offset: playback.offset,
+                            frames: playback.frames,
+                            channels: clip.wav_data.header.num_channels as usize,
+                            envelope: playback.envelope,
+                        }
+                    })
+                    .collect();
+                let tail_frames = track.fx_tail_frames(sample_rate);
+                let end_frame = if track.clips.is_empty() {
+                    0
+                } else {
+                    track.clips_end() + tail_frames
+                };
+                VoiceChain {
+                    source: VoiceSource::Clips(readers),
+                    fx,
+                    automation,
+                    end_frame,
+                    source_end: u64::MAX,
+                    tail_frames,
+                }
+            }
+            None => {
+                let samples = track.render_pre_fader(from_frame, sample_rate, channels);
+                let end_frame = from_frame + (samples.len() / channels.max(1) as usize) as u64;
+                VoiceChain {
+                    source: VoiceSource::Rendered {
+                        start: from_frame,
+                        samples,
+                    },
+                    fx: Vec::new(),
+                    automation,
+                    end_frame,
+                    source_end: u64::MAX,
+                    tail_frames: 0,
+                }
+            }
+        }
+    }
+
+    /// Stop reading clips at `frame`, leaving the FX tail to ring out after it.
+    /// An offline-rendered chain already has its effects baked in, so it simply stops.
+    pub fn until(mut self, frame: u64) -> Self {
+        self.source_end = frame;
+        self.end_frame = self.end_frame.min(frame + self.tail_frames);
+        self
+    }
+
+    fn read_into(&self, position: u64, block: &mut [f32], channels: usize) {
+        if position >= self.source_end {
+            return;
+        }
+        let frames = (self.source_end - position).min((block.len() / channels) as u64) as usize;
+        let block = &mut block[..frames * channels];
+        match &self.source {
+            VoiceSource::Clips(readers) => {
+                for reader in readers {
+                    reader.read_into(position, block, channels);
+                }
+            }
+            VoiceSource::Rendered { start, samples } => {
+                if position < *start {
+                    return;
+                }
+                let offset = (position - start) as usize * channels;
+                if offset >= samples.len() {
+                    return;
+                }
+                let n = block.len().min(samples.len() - offset);
+                block[..n].copy_from_slice(&samples[offset..offset + n]);
+            }
+        }
+    }
+
+    /// Run the block through the FX processors. Automated effects are processed a
+    /// frame at a time with their parameters set for that frame.
+    fn process_fx(&mut self, position: u64, block: &mut [f32], channels: usize) {
+        for (i, fx) in self.fx.iter_mut().enumerate() {
+            if !self.automation.drives_effect(i) {
+                fx.process(block);
+                continue;
+            }
+            for (f, frame) in block.chunks_exact_mut(channels).enumerate() {
+                for lane in &self.automation.effects {
+                    let AutomationTarget::Effect { index, param } = &lane.target else {
+                        continue;
+                    };
+                    if *index != i {
+                        continue;
+                    }
+                    if let Some(value) = lane.value_at(position + f as u64) {
+                        fx.set_parameter(param, value);
+                    }
+                }
+                fx.process(frame);
+            }
+        }
+    }
+}
+
+/// One track inside the real-time mixer: clip readers -> FX processors -> gain/pan,
+/// plus pre/post-fader sends into aux buses.
+pub struct TrackVoice {
+    chain: VoiceChain,
+    params: Arc<VoiceParams>,
+    sends: Vec<AuxSend>,
+    // Whether the voice feeds its output as well as its sends
+    dry: bool,
+    // Post-fader level tap, read by the UI
+    meter: Option<Arc<Meter>>,
+    // Gains applied at the end of the previous block, ramped from to avoid zipper noise
+    last_gains: Option<(f32, f32)>,
+}
+
+impl TrackVoice {
+    pub fn new(chain: VoiceChain, params: Arc<VoiceParams>, sends: Vec<AuxSend>) -> Self {
+        TrackVoice {
+            chain,
+            params,
+            sends,
+            dry: true,
+            meter: None,
+            last_gains: None,
+        }
+    }
+
+    /// Feed only the sends, dropping the voice's own output.
+    pub fn sends_only(mut self) -> Self {
+        self.dry = false;
+        self
+    }
+
+    /// Publish this voice's post-fader level to `meter`.
+    pub fn with_meter(mut self, meter: Arc<Meter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    pub fn params(&self) -> Arc<VoiceParams> {
+        Arc::clone(&self.params)
+    }
+
+    pub fn end_frame(&self) -> u64 {
+        self.chain.end_frame
+    }
+
+    pub fn sends(&self) -> &[AuxSend] {
+        &self.sends
+    }
+
+    /// Swap in a new chain, returning the old one so it can be freed off the audio thread.
+    pub(crate) fn replace_chain(&mut self, chain: VoiceChain) -> VoiceChain {
+        std::mem::replace(&mut self.chain, chain)
+    }
+
+    /// Swap in new sends, returning the old ones so they can be freed off the audio thread.
+    pub(crate) fn replace_sends(&mut self, sends: Vec<AuxSend>) -> Vec<AuxSend> {
+        std::mem::replace(&mut self.sends, sends)
+    }
+
+    /// Render this voice for the block starting at `position`, add it into `out` and feed
+    /// its sends into the matching bus inputs. `scratch` must be the same length as `out`.
+    pub(crate) fn process(
+        &mut self,
+        position: u64,
+        scratch: &mut [f32],
+        out: &mut [f32],
+        buses: &mut [BusVoice],
+        channels: usize,
+    ) {
+        if position >= self.chain.end_frame {
+            return;
+        }
+
+        scratch.fill(0.0);
+        self.chain.read_into(position, scratch, channels);
+        self.chain.process_fx(position, scratch, channels);
+
+        let stereo = channels == 2;
+        let automation = &self.chain.automation;
+        let automated = automation.drives_gains();
+        let (target_left, target_right) = self.params.gains(stereo);
+        let (start_left, start_right) = self.last_gains.unwrap_or((target_left, target_right));
+        let static_muted = self.params.is_muted();
+        let frames = scratch.len() / channels;
+        let step = 1.0 / frames.max(1) as f32;
+        let (mut peak, mut sum_squares) = (0.0f32, 0.0f32);
+        let mut last_gains = (target_left, target_right);
+
+        for (f, (src, dst)) in scratch
+            .chunks_exact(channels)
+            .zip(out.chunks_exact_mut(channels))
+            .enumerate()
+        {
+            // Automated gains follow their lanes exactly; the rest ramp across the block
+            let (left, right, muted) = if automated {
+                automation.gains(&self.params, position + f as u64, stereo)
+            } else {
+                let t = (f + 1) as f32 * step;
+                (
+                    start_left + (target_left - start_left) * t,
+                    start_right + (target_right - start_right) * t,
+                    static_muted,
+                )
+            };
+            last_gains = (left, right);
+            if self.dry {
+                add_scaled(src, dst, left, right);
+            }
+            if self.meter.is_some() {
+                let (frame_peak, frame_squares) = scaled_levels(src, left, right);
+                peak = peak.max(frame_peak);
+                sum_squares += frame_squares;
+            }
+
+            for send in &self.sends {
+                let Some(bus) = buses.get_mut(send.bus) else {
+                    continue;
+                };
+                let (send_left, send_right) = if send.pre_fader {
+                    let level = if muted { 0.0 } else { send.level };
+                    (level, level)
+                } else {
+                    (left * send.level, right * send.level)
+                };
+                let offset = f * channels;
+                add_scaled(
+                    src,
+                    &mut bus.input_mut()[offset..offset + channels],
+                    send_left,
+                    send_right,
+                );
+            }
+        }
+
+        if let Some(meter) = &self.meter {
+            meter.publish(peak, sum_squares, scratch.len());
+        }
+        self.last_gains = Some(last_gains);
+    }
+}
+
+/// Add one frame into another with separate left/right gains for stereo;
+/// other channel counts use the left gain throughout.
+pub(crate) fn add_scaled(src: &[f32], dst: &mut [f32], left: f32, right: f32) {
+    if src.len() == 2 {
+        dst[0] += src[0] * left;
+        dst[1] += src[1] * right;
+    } else {
+        for (d, &s) in dst.iter_mut().zip(src) {
+            *d += s * left;
+        }
+    }
+}
+
+/// Peak and sum of squares of one frame as `add_scaled` would add it.
+pub(crate) fn scaled_levels(src: &[f32], left: f32, right: f32) -> (f32, f32) {
+    let mut peak = 0.0f32;
+    let mut sum_squares = 0.0f32;
+    for (ch, &s) in src.iter().enumerate() {
+        let gain = if src.len() == 2 && ch == 1 {
+            right
+        } else {
+            left
+        };
+        let v = s * gain;
+        peak = peak.max(v.abs());
+        sum_squares += v * v;
+    }
+    (peak, sum_squares)
+}
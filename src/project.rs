@@ -1,26 +1,123 @@
-use crate::effects::EffectType;
-use crate::session::Session;
-use crate::track::Track;
+use crate::bus::{AuxBus, AuxSend};
+use crate::channels::DEFAULT_CHANNELS;
+use crate::effects::{EffectInstance, EffectType};
+use crate::metronome::MetronomeSettings;
+use crate::session::{Session, DEFAULT_PRE_ROLL_SECONDS};
+use crate::tempo::{TempoChange, TempoMap};
+use crate::track::{AutomationLane, AutomationMode, Clip, Crossfade, Fade, FadeShape, Track};
 use crate::wav::WavFile;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 use strum::IntoEnumIterator;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectManifest {
     pub name: String,
     pub sample_rate: u32,
+    #[serde(default = "default_channels")]
+    pub channels: u16,
     pub tracks: Vec<TrackManifest>,
+    #[serde(default)]
+    pub aux_buses: Vec<BusManifest>,
+    #[serde(default = "default_master_gain")]
+    pub master_gain: f64,
+    #[serde(default)]
+    pub master_fx_chain: Vec<FxManifest>,
+    #[serde(default)]
+    pub loop_start: u64,
+    #[serde(default)]
+    pub loop_end: u64,
+    #[serde(default)]
+    pub loop_enabled: bool,
+    #[serde(default)]
+    pub punch_in: u64,
+    #[serde(default)]
+    pub punch_out: u64,
+    #[serde(default)]
+    pub punch_enabled: bool,
+    #[serde(default = "default_pre_roll")]
+    pub pre_roll_seconds: f32,
+    // Tempo and time signature changes; 120 BPM in 4/4 when absent
+    #[serde(default)]
+    pub tempo_changes: Vec<TempoChange>,
+    #[serde(default)]
+    pub metronome: MetronomeSettings,
+}
+
+fn default_channels() -> u16 {
+    DEFAULT_CHANNELS
+}
+
+fn default_pre_roll() -> f32 {
+    DEFAULT_PRE_ROLL_SECONDS
+}
+
+fn default_master_gain() -> f64 {
+    1.0
+}
+
+fn default_auto_crossfade() -> bool {
+    true
+}
+
+fn default_crossfade_shape() -> FadeShape {
+    FadeShape::EqualPower
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TrackManifest {
     pub name: String,
     pub volume: f64,
+    #[serde(default)]
+    pub pan: f32,
     pub muted: bool,
+    #[serde(default)]
+    pub solo: bool,
+    #[serde(default)]
+    pub solo_safe: bool,
     pub clips: Vec<ClipManifest>,
+    // Take lanes; comped clips name the take they play in `take`
+    #[serde(default)]
+    pub takes: Vec<ClipManifest>,
+    // Manual crossfades, naming the clips they join by id
+    #[serde(default)]
+    pub crossfades: Vec<Crossfade>,
+    #[serde(default = "default_auto_crossfade")]
+    pub auto_crossfade: bool,
+    #[serde(default = "default_crossfade_shape")]
+    pub crossfade_shape: FadeShape,
     pub fx_chain: Vec<FxManifest>,
+    // Effect lanes name their effect by its index in `fx_chain`
+    #[serde(default)]
+    pub automation: Vec<AutomationLane>,
+    #[serde(default)]
+    pub automation_mode: AutomationMode,
+    #[serde(default)]
+    pub sends: Vec<SendManifest>,
+    #[serde(default)]
+    pub is_group: bool,
+    #[serde(default)]
+    pub parent: Option<usize>, // index of the enclosing group track
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BusManifest {
+    pub name: String,
+    pub volume: f64,
+    pub muted: bool,
+    pub fx_chain: Vec<FxManifest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendManifest {
+    pub bus: usize,
+    pub level: f32,
+    pub pre_fader: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +125,21 @@ pub struct ClipManifest {
     pub id: String,
     pub file: String, // "clips/{id}.wav"
     pub starts_at: u64,
+    // Section of the file that plays; the whole file when absent
+    #[serde(default)]
+    pub offset: u64,
+    #[serde(default)]
+    pub length: Option<u64>,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub take: Option<String>,
+    #[serde(default)]
+    pub gain_db: f32,
+    #[serde(default)]
+    pub fade_in: Fade,
+    #[serde(default)]
+    pub fade_out: Fade,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,50 +157,78 @@ pub fn save_project(
     let clips_dir = project_dir.join("clips");
     fs::create_dir_all(&clips_dir)?;
 
+    // Clips cut from the same recording share one file
+    let mut sources: HashMap<*const WavFile, String> = HashMap::new();
+
     let mut track_manifests = Vec::new();
     for track in session.tracks.iter() {
-        let mut clip_manifests = Vec::new();
-
-        // Save each clip as a WAV file using its unique ID
-        for clip in track.clips.iter() {
-            let clip_filename = format!("{}.wav", clip.id);
-            let clip_path = clips_dir.join(&clip_filename);
-
-            // Only write the file if it doesn't already exist (incremental save)
-            if !clip_path.exists() {
-                let mut wav = clip.wav_data.clone();
-                wav.save_to_file(&clip_path)?;
-            }
-
-            clip_manifests.push(ClipManifest {
-                id: clip.id.clone(),
-                file: format!("clips/{}", clip_filename),
-                starts_at: clip.starts_at,
-            });
-        }
-
-        let fx_manifests: Vec<FxManifest> = track
-            .fx_chain
+        let clips = track
+            .clips
             .iter()
-            .map(|fx| FxManifest {
-                effect_type: fx.effect_type().name(),
-                parameters: fx.parameters(),
-            })
-            .collect();
+            .map(|clip| save_clip(clip, &clips_dir, &mut sources))
+            .collect::<Result<Vec<_>, _>>()?;
+        let takes = track
+            .takes
+            .iter()
+            .map(|clip| save_clip(clip, &clips_dir, &mut sources))
+            .collect::<Result<Vec<_>, _>>()?;
 
         track_manifests.push(TrackManifest {
             name: track.name.clone(),
             volume: track.volume,
+            pan: track.pan,
             muted: track.muted,
-            clips: clip_manifests,
-            fx_chain: fx_manifests,
+            solo: track.solo,
+            solo_safe: track.solo_safe,
+            clips,
+            takes,
+            crossfades: track.live_crossfades(session.sample_rate),
+            auto_crossfade: track.auto_crossfade,
+            crossfade_shape: track.crossfade_shape,
+            fx_chain: fx_manifests(&track.fx_chain),
+            automation: track.automation.clone(),
+            automation_mode: track.automation_mode,
+            sends: track
+                .sends
+                .iter()
+                .map(|send| SendManifest {
+                    bus: send.bus,
+                    level: send.level,
+                    pre_fader: send.pre_fader,
+                })
+                .collect(),
+            is_group: track.is_group,
+            parent: track.parent,
+            collapsed: track.collapsed,
         });
     }
 
     let manifest = ProjectManifest {
         name: session.name.clone(),
         sample_rate: session.sample_rate,
+        channels: session.channels,
         tracks: track_manifests,
+        aux_buses: session
+            .aux_buses
+            .iter()
+            .map(|bus| BusManifest {
+                name: bus.name.clone(),
+                volume: bus.volume,
+                muted: bus.muted,
+                fx_chain: fx_manifests(&bus.fx_chain),
+            })
+            .collect(),
+        master_gain: session.master.gain,
+        master_fx_chain: fx_manifests(&session.master.fx_chain),
+        loop_start: session.transport.loop_start,
+        loop_end: session.transport.loop_end,
+        loop_enabled: session.transport.loop_enabled,
+        punch_in: session.transport.punch_in,
+        punch_out: session.transport.punch_out,
+        punch_enabled: session.transport.punch_enabled,
+        pre_roll_seconds: session.transport.pre_roll_seconds,
+        tempo_changes: session.tempo.changes().to_vec(),
+        metronome: session.metronome,
     };
 
     let manifest_path = project_dir.join("project.json");
@@ -98,54 +238,215 @@ pub fn save_project(
     Ok(())
 }
 
+fn fx_manifests(chain: &[EffectInstance]) -> Vec<FxManifest> {
+    chain
+        .iter()
+        .map(|fx| FxManifest {
+            effect_type: fx.effect_type().name(),
+            parameters: fx.parameters(),
+        })
+        .collect()
+}
+
+/// Build an FX chain from manifest entries, as saved in projects and FX chain files.
+pub fn load_fx_chain(
+    manifests: Vec<FxManifest>,
+) -> Result<Vec<EffectInstance>, Box<dyn std::error::Error>> {
+    let mut chain = Vec::new();
+    for fx_manifest in manifests {
+        // Find the EffectType by name
+        let effect_type = EffectType::iter()
+            .find(|et| et.name() == fx_manifest.effect_type)
+            .ok_or_else(|| format!("Unknown effect type: {}", fx_manifest.effect_type))?;
+
+        let mut effect = effect_type.create_default();
+
+        for (param_name, param_value) in fx_manifest.parameters {
+            effect = effect
+                .update_parameter(&param_name, &param_value)
+                .map_err(|e| format!("Failed to set parameter {}: {}", param_name, e))?;
+        }
+
+        chain.push(effect);
+    }
+    Ok(chain)
+}
+
+/// Describe a clip for the manifest, writing its audio to `clips_dir` unless another
+/// clip cut from the same recording already did.
+fn save_clip(
+    clip: &Clip,
+    clips_dir: &Path,
+    sources: &mut HashMap<*const WavFile, String>,
+) -> Result<ClipManifest, Box<dyn std::error::Error>> {
+    let source = Arc::as_ptr(&clip.wav_data);
+    let file = match sources.get(&source) {
+        Some(file) => file.clone(),
+        None => {
+            let file = write_clip_file(clip, clips_dir)?;
+            sources.insert(source, file.clone());
+            file
+        }
+    };
+
+    Ok(ClipManifest {
+        id: clip.id.clone(),
+        file,
+        starts_at: clip.starts_at,
+        offset: clip.offset,
+        length: Some(clip.length),
+        muted: clip.muted,
+        take: clip.take.clone(),
+        gain_db: clip.gain_db,
+        fade_in: clip.fade_in,
+        fade_out: clip.fade_out,
+    })
+}
+
+/// Write a clip's audio into `clips_dir`, named after the clip, unless it is already
+/// there; returns its path relative to the project.
+fn write_clip_file(clip: &Clip, clips_dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let clip_filename = format!("{}.wav", clip.id);
+    let clip_path = clips_dir.join(&clip_filename);
+
+    // Only write the file if it doesn't already exist (incremental save)
+    if !clip_path.exists() {
+        let mut wav = (*clip.wav_data).clone();
+        wav.save_to_file(&clip_path)?;
+    }
+    Ok(format!("clips/{}", clip_filename))
+}
+
+/// Copy an imported clip's audio into the project's `clips/` directory straight away,
+/// so the project holds its own copy even before it is next saved.
+pub fn copy_clip_into_project(
+    clip: &Clip,
+    project_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clips_dir = project_dir.join("clips");
+    fs::create_dir_all(&clips_dir)?;
+    write_clip_file(clip, &clips_dir)?;
+    Ok(())
+}
+
+/// Rebuild a clip from the manifest, loading each audio file once.
+fn load_clip(
+    manifest: ClipManifest,
+    project_dir: &Path,
+    sources: &mut HashMap<String, Arc<WavFile>>,
+) -> Result<Clip, Box<dyn std::error::Error>> {
+    let wav = match sources.get(&manifest.file) {
+        Some(wav) => Arc::clone(wav),
+        None => {
+            let clip_path = project_dir.join(&manifest.file);
+            let wav = Arc::new(WavFile::load_from_file(clip_path)?);
+            sources.insert(manifest.file.clone(), Arc::clone(&wav));
+            wav
+        }
+    };
+
+    let mut clip = Clip::new(manifest.id, wav, manifest.starts_at);
+    clip.offset = manifest.offset.min(clip.length);
+    clip.length = manifest
+        .length
+        .unwrap_or(clip.length)
+        .min(clip.length - clip.offset);
+    clip.muted = manifest.muted;
+    clip.take = manifest.take;
+    clip.gain_db = manifest.gain_db;
+    clip.fade_in = manifest.fade_in;
+    clip.fade_out = manifest.fade_out;
+    Ok(clip)
+}
+
 pub fn load_project(project_dir: &Path) -> Result<Session, Box<dyn std::error::Error>> {
     let manifest_path = project_dir.join("project.json");
     let json = fs::read_to_string(manifest_path)?;
     let manifest: ProjectManifest = serde_json::from_str(&json)?;
 
+    let mut sources: HashMap<String, Arc<WavFile>> = HashMap::new();
     let mut tracks = Vec::new();
     for track_manifest in manifest.tracks {
         let mut track = Track::new(track_manifest.name);
         track.volume = track_manifest.volume;
+        track.pan = track_manifest.pan;
         track.muted = track_manifest.muted;
+        track.solo = track_manifest.solo;
+        track.solo_safe = track_manifest.solo_safe;
+        track.is_group = track_manifest.is_group;
+        track.parent = track_manifest.parent;
+        track.collapsed = track_manifest.collapsed;
+        track.crossfades = track_manifest.crossfades;
+        track.auto_crossfade = track_manifest.auto_crossfade;
+        track.crossfade_shape = track_manifest.crossfade_shape;
 
         for clip_manifest in track_manifest.clips {
-            let clip_path = project_dir.join(&clip_manifest.file);
-            let wav = WavFile::load_from_file(clip_path)?;
-
-            track.clips.push(crate::track::Clip {
-                id: clip_manifest.id,
-                wav_data: wav,
-                starts_at: clip_manifest.starts_at,
-            });
+            track
+                .clips
+                .push(load_clip(clip_manifest, project_dir, &mut sources)?);
         }
-
-        for fx_manifest in track_manifest.fx_chain {
-            // Find the EffectType by name
-            let effect_type = EffectType::iter()
-                .find(|et| et.name() == fx_manifest.effect_type)
-                .ok_or_else(|| format!("Unknown effect type: {}", fx_manifest.effect_type))?;
-
-            let mut effect = effect_type.create_default();
-
-            for (param_name, param_value) in fx_manifest.parameters {
-                effect = effect
-                    .update_parameter(&param_name, &param_value)
-                    .map_err(|e| format!("Failed to set parameter {}: {}", param_name, e))?;
-            }
-
-            track.fx_chain.push(effect);
+        for clip_manifest in track_manifest.takes {
+            track
+                .takes
+                .push(load_clip(clip_manifest, project_dir, &mut sources)?);
         }
 
+        track.fx_chain = load_fx_chain(track_manifest.fx_chain)?;
+        track.automation = track_manifest.automation;
+        track.automation_mode = track_manifest.automation_mode;
+        track.sends = track_manifest
+            .sends
+            .into_iter()
+            .filter(|send| send.bus < manifest.aux_buses.len())
+            .map(|send| AuxSend {
+                bus: send.bus,
+                level: send.level,
+                pre_fader: send.pre_fader,
+            })
+            .collect();
+
         // Recompute waveform
-        track.cache_waveform();
+        track.cache_waveform(manifest.sample_rate);
 
         tracks.push(track);
     }
 
+    // Drop parent references that do not point at a group track
+    let is_group: Vec<bool> = tracks.iter().map(|t| t.is_group).collect();
+    for track in tracks.iter_mut() {
+        if track
+            .parent
+            .is_some_and(|p| !is_group.get(p).copied().unwrap_or(false))
+        {
+            track.parent = None;
+        }
+    }
+
     let mut session = Session::new(manifest.name, manifest.sample_rate);
+    session.channels = manifest.channels;
     session.tracks = tracks;
 
+    for bus_manifest in manifest.aux_buses {
+        let mut bus = AuxBus::new(bus_manifest.name);
+        bus.volume = bus_manifest.volume;
+        bus.muted = bus_manifest.muted;
+        bus.fx_chain = load_fx_chain(bus_manifest.fx_chain)?;
+        session.aux_buses.push(bus);
+    }
+
+    session.master.gain = manifest.master_gain;
+    session.master.fx_chain = load_fx_chain(manifest.master_fx_chain)?;
+
+    session.transport.loop_start = manifest.loop_start;
+    session.transport.loop_end = manifest.loop_end;
+    session.transport.loop_enabled = manifest.loop_enabled;
+    session.transport.punch_in = manifest.punch_in;
+    session.transport.punch_out = manifest.punch_out;
+    session.transport.punch_enabled = manifest.punch_enabled;
+    session.transport.pre_roll_seconds = manifest.pre_roll_seconds;
+    session.tempo = TempoMap::from_changes(manifest.tempo_changes);
+    session.metronome = manifest.metronome;
+
     Ok(session)
 }
 
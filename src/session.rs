@@ -1,16 +1,44 @@
 use crate::audio_engine::AudioEngine;
-use crate::master_bus::{MasterBus, MasterBusConfig};
-use crate::track::{Track, TrackState};
-use cpal::traits::{DeviceTrait, StreamTrait};
-use cpal::{BufferSize, Stream};
+use crate::backend::{AudioStream, StreamSpec};
+use crate::bus::{AuxBus, AuxSend, MasterChannel};
+use crate::channels::DEFAULT_CHANNELS;
+use crate::effects::EffectInstance;
+use crate::export::{ExportRange, ExportRender, ExportSettings, ExportSource};
+use crate::history::{ClipLayout, Edit, History, MixerState, TrackSnapshot};
+use crate::latency::{self, StreamLatency};
+use crate::loudness::{self, LoudnessReport};
+use crate::master_bus::{MasterBus, MasterBusConfig, OUTPUT_BUFFER_FRAMES};
+use crate::meter::Meter;
+use crate::metronome::{ClickSound, Metronome, MetronomeSettings, MAX_COUNT_IN_BARS};
+use crate::mixer::{
+    chain_processors, BusVoice, MasterStage, Mixer, MixerHandle, Routing, TrackVoice, VoiceChain,
+    VoiceParams,
+};
+use crate::project;
+use crate::tempo::{MusicalTime, TempoChange, TempoMap, TIME_SIGNATURES};
+use crate::track::{
+    generate_clip_id, AutomationMode, AutomationTarget, Clip, Curve, FadeShape, RecordingPlacement,
+    Track, TrackState, TOUCH_GLIDE_SECONDS, TOUCH_RELEASE_SECONDS,
+};
+use crate::wav::WavFile;
 use ringbuf::{
-    traits::{Producer, Split},
-    HeapProd, HeapRb,
+    traits::{Consumer, Producer, Split},
+    HeapCons, HeapProd, HeapRb,
 };
 use std::borrow::Cow;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const INPUT_BUFFER_FRAMES: u32 = 32;
 const MONITOR_RING_BUFFER_SIZE: usize = 128;
+// Extra recording time after the last calibration click
+const CALIBRATION_MARGIN_SECONDS: f64 = 0.25;
+const MIN_CLIP_GAIN_DB: f32 = -48.0;
+const MAX_CLIP_GAIN_DB: f32 = 12.0;
+pub const DEFAULT_PRE_ROLL_SECONDS: f32 = 2.0;
+const MAX_PRE_ROLL_SECONDS: f32 = 10.0;
 
 /// Extract a single channel's audio data from the full interleaved audio data.
 /// Returns the original data when no channel is selected (all channels),
@@ -47,6 +75,23 @@ fn monitor_frame_sample(
     }
 }
 
+/// Publish the level of the input a track listens to (one channel, or all of them)
+/// to its meter, without allocating.
+fn meter_input(meter: &Meter, data: &[f32], channels: usize, input_channel: Option<u16>) {
+    match input_channel {
+        None => meter.measure(data),
+        Some(sel) => {
+            let (mut peak, mut sum_squares, mut count) = (0.0f32, 0.0f32, 0);
+            for &sample in data.iter().skip(sel as usize).step_by(channels.max(1)) {
+                peak = peak.max(sample.abs());
+                sum_squares += sample * sample;
+                count += 1;
+            }
+            meter.publish(peak, sum_squares, count);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransportState {
     Stopped,
@@ -57,7 +102,16 @@ pub enum TransportState {
 pub struct Transport {
     pub state: TransportState,
     pub playhead_position: u64,
-    playback_origin: u64,
+    // Loop (cycle) region in frames; only used while enabled and non-empty
+    pub loop_start: u64,
+    pub loop_end: u64,
+    pub loop_enabled: bool,
+    // Punch region in frames: recording only replaces audio inside it
+    pub punch_in: u64,
+    pub punch_out: u64,
+    pub punch_enabled: bool,
+    // Seconds of playback before the punch-in when recording starts
+    pub pre_roll_seconds: f32,
 }
 
 impl Default for Transport {
@@ -65,7 +119,13 @@ impl Default for Transport {
         Transport {
             state: TransportState::Stopped,
             playhead_position: 0,
-            playback_origin: 0,
+            loop_start: 0,
+            loop_end: 0,
+            loop_enabled: false,
+            punch_in: 0,
+            punch_out: 0,
+            punch_enabled: false,
+            pre_roll_seconds: DEFAULT_PRE_ROLL_SECONDS,
         }
     }
 }
@@ -73,12 +133,10 @@ impl Default for Transport {
 impl Transport {
     pub fn play(&mut self) {
         self.state = TransportState::Playing;
-        self.playback_origin = self.playhead_position;
     }
 
     pub fn record(&mut self) {
         self.state = TransportState::Recording;
-        self.playback_origin = self.playhead_position;
     }
 
     pub fn stop(&mut self) {
@@ -110,18 +168,58 @@ impl Transport {
         self.playhead_position as f64 / sample_rate as f64
     }
 
-    pub fn advance_playhead_from_master(&mut self, samples_consumed: u64) {
-        self.playhead_position = self.playback_origin + samples_consumed;
+    /// The region playback cycles through, if looping is on and the region is valid.
+    pub fn loop_range(&self) -> Option<Range<u64>> {
+        (self.loop_enabled && self.loop_start < self.loop_end)
+            .then_some(self.loop_start..self.loop_end)
+    }
+
+    /// The region recording is confined to, if punching is on and the region is valid.
+    pub fn punch_range(&self) -> Option<Range<u64>> {
+        (self.punch_enabled && self.punch_in < self.punch_out)
+            .then_some(self.punch_in..self.punch_out)
     }
 }
 
+/// Owner of an FX chain that can be edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FxTarget {
+    Track(usize),
+    Aux(usize),
+    Master,
+}
+
+// A latency calibration playing its clicks, finished by `Session::check_calibration`
+struct Calibration {
+    input_stream: Box<dyn AudioStream>,
+    recorded: HeapCons<f32>,
+    interval: usize,
+    ends_at: Instant,
+}
+
 pub struct Session {
     pub name: String,
     pub tracks: Vec<Track>,
+    pub aux_buses: Vec<AuxBus>,
+    pub master: MasterChannel,
     pub sample_rate: u32,
+    /// Channel count of the mix (interleaved), independent of the output device.
+    pub channels: u16,
     pub transport: Transport,
+    pub tempo: TempoMap,
+    pub metronome: MetronomeSettings,
     master_bus: MasterBus,
-    shared_input_stream: Option<Stream>,
+    // UI-side handle to the mixer running inside the master bus, while playing
+    mixer: Option<MixerHandle>,
+    shared_input_stream: Option<Box<dyn AudioStream>>,
+    // Capture-to-callback delay reported by the input stream while recording
+    input_latency: Arc<StreamLatency>,
+    // Frames of count-in the running recording started with
+    count_in_frames: u64,
+    history: History,
+    // Clips of the tracks being recorded, from before the recording
+    recording_undo: Vec<(usize, ClipLayout)>,
+    calibration: Option<Calibration>,
 }
 
 impl Session {
@@ -129,19 +227,36 @@ impl Session {
         Session {
             name,
             tracks: Vec::new(),
+            aux_buses: Vec::new(),
+            master: MasterChannel::default(),
             sample_rate,
+            channels: DEFAULT_CHANNELS,
             transport: Transport::default(),
+            tempo: TempoMap::default(),
+            metronome: MetronomeSettings::default(),
             master_bus: MasterBus::default(),
+            mixer: None,
             shared_input_stream: None,
+            input_latency: Arc::new(StreamLatency::new()),
+            count_in_frames: 0,
+            history: History::new(),
+            recording_undo: Vec::new(),
+            calibration: None,
         }
     }
 
     pub fn add_track(&mut self, name: String) -> Result<(), Box<dyn std::error::Error>> {
-        const MAX_TRACKS: usize = 3;
-        if self.tracks.len() >= MAX_TRACKS {
-            return Err(format!("Maximum of {} tracks allowed", MAX_TRACKS).into());
+        let track = Track::new(name);
+        // A new top-level track is only silenced by another track's solo
+        let muted = self.is_soloing();
+        let from = self.render_from();
+        if let Some(handle) = self.mixer.as_mut() {
+            let voice = build_voice(&track, from, self.sample_rate, self.channels, muted)
+                .with_meter(Arc::clone(&track.output_meter));
+            handle.add_voice(voice);
         }
-        self.tracks.push(Track::new(name));
+        self.tracks.push(track);
+        self.record_added_track("Add track");
         Ok(())
     }
 
@@ -168,30 +283,38 @@ impl Session {
     }
 
     pub fn start_playback(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.is_calibrating() {
+            return Err("Wait for the latency calibration to finish".into());
+        }
         let playhead_pos = self.transport.playhead_position;
 
-        // Pre-render all tracks from playhead position and mix them into one mono buffer
-        // This happens BEFORE playback starts (not real-time)
-        let master_buffer = self.render_master_buffer(playhead_pos);
-        if master_buffer.is_empty() {
+        // Tracks are streamed and mixed block by block inside the output callback,
+        // so playback starts immediately regardless of project length
+        let (mut mixer, handle) = self.build_mixer(playhead_pos, true);
+        mixer.set_loop(self.transport.loop_range());
+        mixer.set_metronome(self.live_metronome());
+        if mixer.is_finished() {
             return Ok(());
         }
 
         let monitor_consumer = self.build_monitor_consumer();
 
         self.master_bus.start(MasterBusConfig {
-            playback_samples: Some(master_buffer),
+            mixer: Some(mixer),
             monitor_consumer,
             sample_rate: self.sample_rate,
             low_latency: false,
         })?;
+        self.mixer = Some(handle);
 
         self.transport.play();
         Ok(())
     }
 
     pub fn stop_playback(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.end_automation_writes();
         self.master_bus.stop();
+        self.mixer = None;
         self.transport.stop();
 
         self.refresh_monitoring();
@@ -201,11 +324,22 @@ impl Session {
 
     pub fn check_playback_status(&mut self) {
         if self.transport.is_playing() {
-            let frames_consumed = self.master_bus.frames_consumed();
-            self.transport.advance_playhead_from_master(frames_consumed);
+            self.sync_mixer();
+
+            if let Some(handle) = self.mixer.as_ref() {
+                self.transport.playhead_position = handle.position();
+            }
+            let position = self.transport.playhead_position;
+            for index in 0..self.tracks.len() {
+                self.update_automation_writes(index, Some(position));
+            }
 
-            if self.master_bus.is_finished() {
+            // Recording keeps running past the end of the backing mix
+            let playing_only = self.transport.state == TransportState::Playing;
+            if playing_only && self.master_bus.is_finished() {
+                self.end_automation_writes();
                 self.master_bus.stop();
+                self.mixer = None;
                 self.transport.stop();
                 self.refresh_monitoring();
             }
@@ -214,26 +348,43 @@ impl Session {
 
     // --- Recording ---
 
+    /// The selected input's format with a low-latency buffer. Fails if the device runs
+    /// at a different rate than the session.
+    fn input_spec(&self) -> Result<StreamSpec, Box<dyn std::error::Error>> {
+        let format = AudioEngine::input_format_for(self.sample_rate)?;
+        Ok(StreamSpec {
+            buffer_frames: Some(INPUT_BUFFER_FRAMES),
+            ..format
+        })
+    }
+
     pub fn start_recording(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
         let armed_count = self.tracks.iter().filter(|t| t.is_armed()).count();
         if armed_count == 0 {
             return Ok(0);
         }
+        if self.is_calibrating() {
+            return Err("Wait for the latency calibration to finish".into());
+        }
+
+        let punch = self.transport.punch_range();
+        if punch.is_some() && self.transport.loop_range().is_some() {
+            return Err("Punch recording and loop recording cannot be used together".into());
+        }
 
         self.master_bus.stop();
         self.shared_input_stream = None;
 
-        let input_device = AudioEngine::get_input_device()?;
-        let mut config = input_device.config.clone();
-        if config.sample_rate.0 != self.sample_rate {
-            return Err(format!(
-                "Input device sample rate ({}Hz) does not match session sample rate ({}Hz). Change your input device or start a new session.",
-                config.sample_rate.0, self.sample_rate
-            ).into());
-        }
-        let channels = config.channels;
-        config.buffer_size = BufferSize::Fixed(INPUT_BUFFER_FRAMES);
+        let spec = self.input_spec()?;
+        let channels = spec.channels;
 
+        // Punching in starts the pre-roll before the punch-in so the performer can play along
+        if let Some(punch) = punch {
+            let pre_roll =
+                latency::ms_to_frames(self.transport.pre_roll_seconds * 1000.0, self.sample_rate)
+                    as u64;
+            self.transport.playhead_position = punch.start.saturating_sub(pre_roll);
+        }
         let playhead_pos = self.transport.playhead_position;
 
         for track in &mut self.tracks {
@@ -249,24 +400,33 @@ impl Session {
 
         let mut rec_producers: Vec<HeapProd<f32>> = Vec::new();
         let mut input_channels: Vec<Option<u16>> = Vec::new();
+        let mut input_meters: Vec<Arc<Meter>> = Vec::new();
 
         for track in &mut self.tracks {
             if let Some(prod) = track.take_recording_producer() {
                 rec_producers.push(prod);
                 input_channels.push(track.input_channel);
+                input_meters.push(Arc::clone(&track.input_meter));
             }
         }
 
         let monitor_ring = HeapRb::<f32>::new(MONITOR_RING_BUFFER_SIZE);
         let (mut monitor_producer, monitor_consumer) = monitor_ring.split();
 
-        let input_data_fn = move |data: &[f32], _: &cpal::InputCallbackInfo| {
+        let input_latency = Arc::clone(&self.input_latency);
+        input_latency.reset();
+        let sample_rate = self.sample_rate;
+
+        let input_data_fn = move |data: &[f32], latency| {
             let ch = channels as usize;
             let num_frames = data.len() / ch;
 
+            input_latency.report(latency, sample_rate);
+
             // Route input to each track's recording buffer
             for (i, rec_prod) in rec_producers.iter_mut().enumerate() {
                 let samples = extract_channel_data(data, ch, input_channels[i]);
+                input_meters[i].measure(&samples);
                 rec_prod.push_slice(&samples);
             }
 
@@ -281,25 +441,31 @@ impl Session {
             }
         };
 
-        let playback_buffer = self.render_overdub_buffer(playhead_pos);
+        // The count-in clicks even with the metronome off, holding the timeline (but
+        // not the capture) until it is over
+        let count_in_bars = self.metronome.count_in_bars;
+        let metronome = Metronome::new(&self.metronome, &self.tempo, self.sample_rate)
+            .with_count_in(count_in_bars, playhead_pos);
+        self.count_in_frames = metronome.count_in_remaining();
+
+        let (mut mixer, handle) = self.build_mixer(playhead_pos, true);
+        mixer.set_loop(self.transport.loop_range());
+        mixer.set_metronome(Some(metronome));
+
+        self.recording_undo = (0..self.tracks.len())
+            .filter(|&i| self.tracks[i].is_armed())
+            .map(|i| (i, self.history.capture(&self.tracks[i])))
+            .collect();
 
-        let input_stream = input_device.device.build_input_stream(
-            &config,
-            input_data_fn,
-            |err| eprintln!("Shared input stream error: {err}"),
-            None,
-        )?;
+        let input_stream = AudioEngine::open_input(spec, Box::new(input_data_fn))?;
 
         self.master_bus.start(MasterBusConfig {
-            playback_samples: if playback_buffer.is_empty() {
-                None
-            } else {
-                Some(playback_buffer)
-            },
+            mixer: Some(mixer),
             monitor_consumer: Some(monitor_consumer),
             sample_rate: self.sample_rate,
             low_latency: true,
         })?;
+        self.mixer = Some(handle);
 
         // Start input AFTER master bus so both streams begin together
         input_stream.play()?;
@@ -311,22 +477,376 @@ impl Session {
     }
 
     pub fn stop_all_recording(&mut self) {
+        let latency = self.recording_latency_frames();
+
         // Drop shared input stream FIRST to stop audio capture
         self.shared_input_stream = None;
+        self.end_automation_writes();
         self.master_bus.stop();
-
-        // Finalize all recording tracks (save buffers to track data)
+        self.mixer = None;
+
+        // Finalize all recording tracks (save buffers to track data), lined up with
+        // the backing mix the performer heard. Each pass through a loop becomes a take;
+        // a punch keeps only what was played between punch-in and punch-out.
+        let placement = RecordingPlacement {
+            latency_frames: latency,
+            count_in_frames: self.count_in_frames,
+            cycle: self.transport.loop_range(),
+            punch: self.transport.punch_range(),
+        };
+        self.count_in_frames = 0;
         for track in &mut self.tracks {
             if track.state == TrackState::Recording {
-                let _ = track.stop_recording();
+                let _ = track.stop_recording(placement.clone());
             }
         }
         self.transport.stop();
+        let before = std::mem::take(&mut self.recording_undo);
+        self.record_clip_changes("Record", before, false);
 
         // Restart monitoring if any tracks are still armed
         self.refresh_monitoring();
     }
 
+    // --- Loop ---
+
+    pub fn set_loop_start(&mut self, frame: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_loop_editable()?;
+        self.transport.loop_start = frame;
+        self.refresh_loop();
+        Ok(())
+    }
+
+    pub fn set_loop_end(&mut self, frame: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_loop_editable()?;
+        self.transport.loop_end = frame;
+        self.refresh_loop();
+        Ok(())
+    }
+
+    /// Turn looping on or off; returns whether it is now on.
+    pub fn toggle_loop(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.check_loop_editable()?;
+        self.transport.loop_enabled = !self.transport.loop_enabled;
+        self.refresh_loop();
+        Ok(self.transport.loop_enabled)
+    }
+
+    // Takes are cut from the recording using the loop it was made with
+    fn check_loop_editable(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.transport.state == TransportState::Recording {
+            return Err("The loop cannot change while recording".into());
+        }
+        Ok(())
+    }
+
+    /// Hand the current loop to the running mixer. Voices are rebuilt so the ones
+    /// rendered offline cover the new loop.
+    fn refresh_loop(&mut self) {
+        let region = self.transport.loop_range();
+        if let Some(handle) = self.mixer.as_mut() {
+            handle.set_loop(region);
+        }
+        for i in 0..self.tracks.len() {
+            self.refresh_track_voice(i);
+        }
+    }
+
+    // --- Punch ---
+
+    pub fn set_punch_in(&mut self, frame: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_punch_editable()?;
+        self.transport.punch_in = frame;
+        Ok(())
+    }
+
+    pub fn set_punch_out(&mut self, frame: u64) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_punch_editable()?;
+        self.transport.punch_out = frame;
+        Ok(())
+    }
+
+    /// Turn punch recording on or off; returns whether it is now on.
+    pub fn toggle_punch(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.check_punch_editable()?;
+        self.transport.punch_enabled = !self.transport.punch_enabled;
+        Ok(self.transport.punch_enabled)
+    }
+
+    /// Change the pre-roll by `delta` seconds; returns the new pre-roll.
+    pub fn adjust_pre_roll(&mut self, delta: f32) -> f32 {
+        self.transport.pre_roll_seconds =
+            (self.transport.pre_roll_seconds + delta).clamp(0.0, MAX_PRE_ROLL_SECONDS);
+        self.transport.pre_roll_seconds
+    }
+
+    // The recording is cut to the punch it was made with
+    fn check_punch_editable(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.transport.state == TransportState::Recording {
+            return Err("The punch region cannot change while recording".into());
+        }
+        Ok(())
+    }
+
+    // --- Tempo ---
+
+    /// The playhead in bars:beats:ticks.
+    pub fn playhead_musical(&self) -> MusicalTime {
+        self.tempo
+            .frames_to_musical(self.transport.playhead_position, self.sample_rate)
+    }
+
+    /// Change the tempo of the bar under the playhead (and the bars after it up to the
+    /// next change) by `delta` BPM; returns the new tempo.
+    pub fn adjust_tempo(&mut self, delta: f64) -> f64 {
+        let bar = self.playhead_musical().bar;
+        let change = self.tempo.change_at_bar(bar);
+        self.tempo.set_change(TempoChange {
+            bar,
+            bpm: change.bpm + delta,
+            ..change
+        });
+        self.refresh_metronome();
+        self.tempo.change_at_bar(bar).bpm
+    }
+
+    /// Switch the bar under the playhead to the next time signature in
+    /// `TIME_SIGNATURES`; returns it.
+    pub fn cycle_time_signature(&mut self) -> (u8, u8) {
+        let bar = self.playhead_musical().bar;
+        let change = self.tempo.change_at_bar(bar);
+        let current = TIME_SIGNATURES
+            .iter()
+            .position(|&sig| sig == (change.numerator, change.denominator));
+        let (numerator, denominator) =
+            TIME_SIGNATURES[current.map_or(0, |i| (i + 1) % TIME_SIGNATURES.len())];
+        self.tempo.set_change(TempoChange {
+            bar,
+            numerator,
+            denominator,
+            ..change
+        });
+        self.refresh_metronome();
+        (numerator, denominator)
+    }
+
+    /// Remove the tempo change starting at the bar under the playhead.
+    pub fn remove_tempo_change(&mut self) -> Result<u32, Box<dyn std::error::Error>> {
+        let bar = self.playhead_musical().bar;
+        if !self.tempo.remove_change(bar) {
+            return Err(format!("No tempo change at bar {}", bar + 1).into());
+        }
+        self.refresh_metronome();
+        Ok(bar)
+    }
+
+    // --- Metronome ---
+
+    /// Turn the click on or off; returns whether it is now on.
+    pub fn toggle_metronome(&mut self) -> bool {
+        self.metronome.enabled = !self.metronome.enabled;
+        self.refresh_metronome();
+        self.metronome.enabled
+    }
+
+    /// Change the click level by `delta`; returns the new level.
+    pub fn adjust_metronome_level(&mut self, delta: f32) -> f32 {
+        self.metronome.level = (self.metronome.level + delta).clamp(0.0, 1.0);
+        self.refresh_metronome();
+        self.metronome.level
+    }
+
+    /// Switch to the next click sound; returns it.
+    pub fn cycle_metronome_sound(&mut self) -> ClickSound {
+        self.metronome.sound = self.metronome.sound.next();
+        self.refresh_metronome();
+        self.metronome.sound
+    }
+
+    /// Step the count-in through 0..=MAX_COUNT_IN_BARS bars; returns the new count.
+    pub fn cycle_count_in(&mut self) -> u32 {
+        self.metronome.count_in_bars = (self.metronome.count_in_bars + 1) % (MAX_COUNT_IN_BARS + 1);
+        self.metronome.count_in_bars
+    }
+
+    /// Switch the click between the output only and the mix; returns whether it is
+    /// now output-only.
+    pub fn toggle_metronome_output_only(&mut self) -> bool {
+        self.metronome.output_only = !self.metronome.output_only;
+        self.refresh_metronome();
+        self.metronome.output_only
+    }
+
+    /// The click for live playback, if it is on.
+    fn live_metronome(&self) -> Option<Metronome> {
+        self.metronome
+            .enabled
+            .then(|| Metronome::new(&self.metronome, &self.tempo, self.sample_rate))
+    }
+
+    /// Hand the current click settings and tempo map to the running mixer. A recording
+    /// keeps the click it started with so its count-in is not cut short.
+    fn refresh_metronome(&mut self) {
+        if self.transport.state != TransportState::Playing {
+            return;
+        }
+        let metronome = self.live_metronome();
+        if let Some(handle) = self.mixer.as_mut() {
+            handle.set_metronome(metronome);
+        }
+    }
+
+    // --- Latency ---
+
+    /// Round trip reported by the streams of the last recording: input capture delay
+    /// plus output delay, falling back to the buffer sizes when the host reports none.
+    pub fn reported_latency_frames(&self) -> u64 {
+        let input = self
+            .input_latency
+            .frames()
+            .unwrap_or(INPUT_BUFFER_FRAMES as u64);
+        let output = self
+            .master_bus
+            .output_latency_frames()
+            .unwrap_or(OUTPUT_BUFFER_FRAMES as u64);
+        input + output
+    }
+
+    /// How far new recordings are moved earlier to line up with the backing mix.
+    pub fn recording_latency_frames(&self) -> u64 {
+        let settings = AudioEngine::global().lock().unwrap().latency();
+        settings.compensation_frames(self.reported_latency_frames(), self.sample_rate)
+    }
+
+    /// Start measuring the round trip through the audio interface: play clicks on the
+    /// output, record them through a loopback into the input and time their arrival.
+    /// The clicks take about two seconds; `check_calibration` reports the result.
+    pub fn start_calibration(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.transport.is_playing() {
+            return Err("Stop playback before calibrating".into());
+        }
+        if self.is_calibrating() {
+            return Err("Calibration is already running".into());
+        }
+        self.master_bus.stop();
+        self.shared_input_stream = None;
+
+        match self.open_calibration() {
+            Ok(calibration) => {
+                self.calibration = Some(calibration);
+                Ok(())
+            }
+            Err(e) => {
+                self.master_bus.stop();
+                self.refresh_monitoring();
+                Err(e)
+            }
+        }
+    }
+
+    pub fn is_calibrating(&self) -> bool {
+        self.calibration.is_some()
+    }
+
+    /// Once the calibration clicks are done, stop them and return the measured round
+    /// trip in ms, which is saved with the audio preferences. None while they play or
+    /// when no calibration runs.
+    pub fn check_calibration(&mut self) -> Option<Result<f32, Box<dyn std::error::Error>>> {
+        if self
+            .calibration
+            .as_ref()
+            .is_none_or(|c| Instant::now() < c.ends_at)
+        {
+            return None;
+        }
+        let Calibration {
+            input_stream,
+            mut recorded,
+            interval,
+            ..
+        } = self.calibration.take()?;
+        drop(input_stream);
+        self.master_bus.stop();
+        self.refresh_monitoring();
+
+        let recorded: Vec<f32> = recorded.pop_iter().collect();
+        let Some(frames) =
+            latency::measure_round_trip(&recorded, interval, latency::CALIBRATION_CLICKS)
+        else {
+            return Some(Err(
+                "No click was heard - connect an output to an input and try again".into(),
+            ));
+        };
+        let ms = latency::frames_to_ms(frames, self.sample_rate);
+        let engine = AudioEngine::global();
+        let mut engine = engine.lock().unwrap();
+        let mut settings = engine.latency();
+        settings.calibrated_ms = Some(ms);
+        engine.set_latency(settings);
+        Some(Ok(ms))
+    }
+
+    // Open the calibration's input and start its clicks on the output
+    fn open_calibration(&mut self) -> Result<Calibration, Box<dyn std::error::Error>> {
+        let spec = self.input_spec()?;
+        let channels = spec.channels as usize;
+
+        let interval = latency::ms_to_frames(
+            latency::CALIBRATION_CLICK_INTERVAL_MS as f32,
+            self.sample_rate,
+        ) as usize;
+        let total_frames = interval * latency::CALIBRATION_CLICKS;
+
+        // The clicks play through the same mixer and output path as a backing mix
+        let mut click = WavFile::new(self.sample_rate, 1);
+        click.from_f32_samples(&latency::click(self.sample_rate));
+        let click = Arc::new(click);
+        let mut click_track = Track::new("Calibration".to_string());
+        for k in 0..latency::CALIBRATION_CLICKS {
+            click_track.clips.push(Clip::new(
+                format!("calibration-{}", k),
+                Arc::clone(&click),
+                (k * interval) as u64,
+            ));
+        }
+        let voice = build_voice(&click_track, 0, self.sample_rate, self.channels, false);
+        let master =
+            MasterStage::from_channel(&MasterChannel::default(), self.sample_rate, self.channels);
+        let (mixer, _handle) = Mixer::new(
+            vec![voice],
+            Routing::default(),
+            Vec::new(),
+            master,
+            0,
+            self.channels,
+        );
+
+        let (mut producer, recorded) = HeapRb::<f32>::new(total_frames).split();
+        let input_data_fn = move |data: &[f32], _| {
+            for frame in data.chunks_exact(channels) {
+                let _ = producer.try_push(frame.iter().sum());
+            }
+        };
+        let input_stream = AudioEngine::open_input(spec, Box::new(input_data_fn))?;
+
+        // Same start order as recording, so the measured offset is the one recordings see
+        self.master_bus.start(MasterBusConfig {
+            mixer: Some(mixer),
+            monitor_consumer: None,
+            sample_rate: self.sample_rate,
+            low_latency: true,
+        })?;
+        input_stream.play()?;
+
+        let seconds = total_frames as f64 / self.sample_rate as f64 + CALIBRATION_MARGIN_SECONDS;
+        Ok(Calibration {
+            input_stream,
+            recorded,
+            interval,
+            ends_at: Instant::now() + Duration::from_secs_f64(seconds),
+        })
+    }
+
     // --- Monitoring ---
 
     pub fn start_monitoring(&mut self) -> Result<(), Box<dyn std::error::Error>> {
@@ -335,20 +855,12 @@ impl Session {
             return Ok(());
         }
 
-        if self.transport.is_playing() {
+        if self.transport.is_playing() || self.is_calibrating() {
             return Ok(());
         }
 
-        let input_device = AudioEngine::get_input_device()?;
-        let mut config = input_device.config.clone();
-        if config.sample_rate.0 != self.sample_rate {
-            return Err(format!(
-                "Input device sample rate ({}Hz) does not match session sample rate ({}Hz). Change your input device or start a new session.",
-                config.sample_rate.0, self.sample_rate
-            ).into());
-        }
-        let channels = config.channels;
-        config.buffer_size = BufferSize::Fixed(INPUT_BUFFER_FRAMES);
+        let spec = self.input_spec()?;
+        let channels = spec.channels;
 
         let input_channels: Vec<Option<u16>> = self
             .tracks
@@ -356,14 +868,24 @@ impl Session {
             .filter(|t| t.is_armed() && t.monitoring)
             .map(|t| t.input_channel)
             .collect();
+        let input_meters: Vec<Arc<Meter>> = self
+            .tracks
+            .iter()
+            .filter(|t| t.is_armed() && t.monitoring)
+            .map(|t| Arc::clone(&t.input_meter))
+            .collect();
 
         let monitor_ring = HeapRb::<f32>::new(MONITOR_RING_BUFFER_SIZE);
         let (mut monitor_producer, monitor_consumer) = monitor_ring.split();
 
-        let input_data_fn = move |data: &[f32], _: &cpal::InputCallbackInfo| {
+        let input_data_fn = move |data: &[f32], _| {
             let ch = channels as usize;
             let num_frames = data.len() / ch;
 
+            for (meter, &ic) in input_meters.iter().zip(&input_channels) {
+                meter_input(meter, data, ch, ic);
+            }
+
             // Mix selected channels for headphone output
             for frame in 0..num_frames {
                 let mix: f32 = input_channels
@@ -375,18 +897,13 @@ impl Session {
             }
         };
 
-        let input_stream = input_device.device.build_input_stream(
-            &config,
-            input_data_fn,
-            |err| eprintln!("Monitor input stream error: {err}"),
-            None,
-        )?;
+        let input_stream = AudioEngine::open_input(spec, Box::new(input_data_fn))?;
 
         input_stream.play()?;
         self.shared_input_stream = Some(input_stream);
 
         self.master_bus.start(MasterBusConfig {
-            playback_samples: None,
+            mixer: None,
             monitor_consumer: Some(monitor_consumer),
             sample_rate: self.sample_rate,
             low_latency: true,
@@ -396,7 +913,7 @@ impl Session {
     }
 
     pub fn stop_monitoring(&mut self) {
-        if !self.transport.is_playing() {
+        if !self.transport.is_playing() && !self.is_calibrating() {
             self.shared_input_stream = None;
             self.master_bus.stop();
         }
@@ -422,116 +939,1785 @@ impl Session {
             return Err("Track index out of bounds".into());
         }
 
+        let parents = self.tracks.iter().map(|t| t.parent).collect();
+        let track = self.snapshot_track(index);
+        self.detach_track(index);
+        self.history.record(
+            "Remove track",
+            vec![Edit::RemoveTrack {
+                index,
+                track,
+                parents,
+            }],
+            false,
+        );
+        Ok(())
+    }
+
+    // Take a track out of the session, handing its children to its parent
+    fn detach_track(&mut self, index: usize) {
         self.tracks[index].cleanup();
-        self.tracks.remove(index);
+        let removed = self.tracks.remove(index);
+        if let Some(handle) = self.mixer.as_mut() {
+            handle.remove_voice(index);
+        }
 
-        Ok(())
+        // Children of a removed group move up to its parent; later indices shift down
+        for track in self.tracks.iter_mut() {
+            if track.parent == Some(index) {
+                track.parent = removed.parent;
+            }
+            if let Some(parent) = track.parent.as_mut() {
+                if *parent > index {
+                    *parent -= 1;
+                }
+            }
+        }
+        self.refresh_routing();
     }
 
-    // --- FX Chain management ---
+    // --- Groups ---
+
+    /// Add a group (folder) track and return its index.
+    pub fn add_group_track(&mut self, name: String) -> usize {
+        let mut track = Track::new(name);
+        track.is_group = true;
+        let from = self.render_from();
+        if let Some(handle) = self.mixer.as_mut() {
+            let voice = build_voice(&track, from, self.sample_rate, self.channels, true)
+                .with_meter(Arc::clone(&track.output_meter));
+            handle.add_voice(voice);
+        }
+        self.tracks.push(track);
+        self.refresh_routing();
+        self.record_added_track("Add group");
+        self.tracks.len() - 1
+    }
 
-    pub fn add_effect_to_track(
+    /// Move a track into a group, or back to the top level with `None`.
+    pub fn set_track_parent(
         &mut self,
-        track_idx: usize,
-        effect: crate::effects::EffectInstance,
-    ) -> Result<(), String> {
-        let track = self
-            .tracks
-            .get_mut(track_idx)
-            .ok_or_else(|| "Track index out of bounds".to_string())?;
-        track.fx_chain.push(effect);
+        index: usize,
+        parent: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if index >= self.tracks.len() {
+            return Err("Track index out of bounds".into());
+        }
+        if let Some(parent) = parent {
+            let group = self.tracks.get(parent).ok_or("Group index out of bounds")?;
+            if !group.is_group {
+                return Err(format!("{} is not a group track", group.name).into());
+            }
+            if parent == index || self.ancestors(parent).any(|a| a == index) {
+                return Err("A group cannot be nested inside itself".into());
+            }
+        }
+        let before = self.tracks[index].parent;
+        self.tracks[index].parent = parent;
+        self.refresh_routing();
+        if before != parent {
+            self.history.record(
+                "Change group",
+                vec![Edit::Parent {
+                    track: index,
+                    before,
+                    after: parent,
+                }],
+                false,
+            );
+        }
         Ok(())
     }
 
-    pub fn remove_effect_from_track(
+    /// Nesting depth of a track: 0 for top-level tracks.
+    pub fn track_depth(&self, index: usize) -> usize {
+        self.ancestors(index).count()
+    }
+
+    /// Enclosing groups of a track, innermost first.
+    fn ancestors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.tracks.get(index).and_then(|t| t.parent), |&p| {
+            self.tracks.get(p).and_then(|t| t.parent)
+        })
+        // A malformed manifest could contain a cycle
+        .take(self.tracks.len())
+    }
+
+    /// Group tracks ordered children first, so every submix is complete before the
+    /// group it feeds is processed.
+    fn group_order(&self) -> Vec<usize> {
+        let mut groups: Vec<usize> = (0..self.tracks.len())
+            .filter(|&i| self.tracks[i].is_group)
+            .collect();
+        groups.sort_by_key(|&i| std::cmp::Reverse(self.track_depth(i)));
+        groups
+    }
+
+    /// Remove every clip from a track.
+    pub fn clear_track(&mut self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let sample_rate = self.sample_rate;
+        self.clip_edit("Clear track", &[index], false, |session| {
+            let track = session
+                .tracks
+                .get_mut(index)
+                .ok_or("Track index out of bounds")?;
+            track.clips.clear();
+            track.takes.clear();
+            track.selected_take = 0;
+            track.cache_waveform(sample_rate);
+            session.refresh_track_voice(index);
+            Ok(())
+        })
+    }
+
+    /// Delete one clip from a track.
+    pub fn delete_clip(
         &mut self,
         track_idx: usize,
-        effect_idx: usize,
-    ) -> Result<(), String> {
-        let track = self
-            .tracks
-            .get_mut(track_idx)
-            .ok_or_else(|| "Track index out of bounds".to_string())?;
-        if effect_idx >= track.fx_chain.len() {
-            return Err("Effect index out of bounds".to_string());
-        }
-        track.fx_chain.remove(effect_idx);
-        Ok(())
+        clip_idx: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.clip_edit("Delete clip", &[track_idx], false, |session| {
+            let track = session.track_for_clip_edit(track_idx)?;
+            if clip_idx >= track.clips.len() {
+                return Err("Clip index out of bounds".into());
+            }
+            track.clips.remove(clip_idx);
+            session.finish_clip_edit(track_idx);
+            Ok(())
+        })
     }
 
-    pub fn update_effect_param(
+    /// Load an audio file as a new clip on a track at the playhead, converted to the
+    /// session's sample rate and channel count; returns the clip's index. With a
+    /// `project_dir` the converted audio is written to its `clips/` straight away, so
+    /// the project holds its own copy even before it is next saved.
+    pub fn import_audio(
         &mut self,
         track_idx: usize,
-        effect_idx: usize,
-        param: &str,
-        value: &str,
-    ) -> Result<(), String> {
-        let track = self
-            .tracks
-            .get_mut(track_idx)
-            .ok_or_else(|| "Track index out of bounds".to_string())?;
-        if effect_idx >= track.fx_chain.len() {
-            return Err("Effect index out of bounds".to_string());
+        path: &Path,
+        project_dir: Option<&Path>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let track = self.track_for_clip_edit(track_idx)?;
+        if track.is_group {
+            return Err("Group tracks cannot hold clips".into());
         }
-        let updated = track.fx_chain[effect_idx].update_parameter(param, value)?;
-        track.fx_chain[effect_idx] = updated;
-        Ok(())
+        let wav = WavFile::load_from_file(path)?;
+        if wav.frame_count() == 0 {
+            return Err("The file has no audio".into());
+        }
+        let wav = wav.converted(self.sample_rate, self.channels);
+        let starts_at = self.transport.playhead_position;
+
+        self.clip_edit("Import audio", &[track_idx], false, |session| {
+            let track = session.track_for_clip_edit(track_idx)?;
+            let clip = Clip::new(generate_clip_id(&track.name), Arc::new(wav), starts_at);
+            if let Some(dir) = project_dir {
+                project::copy_clip_into_project(&clip, dir)?;
+            }
+            track.clips.push(clip);
+            let clip_idx = track.clips.len() - 1;
+            session.finish_clip_edit(track_idx);
+            Ok(clip_idx)
+        })
     }
 
-    /// Render the entire master mix from the start as f32 samples.
-    pub fn render_full_mix(&self) -> Vec<f32> {
-        self.render_master_buffer(0)
+    /// Mute or unmute one clip; returns whether it is now muted.
+    pub fn toggle_clip_muted(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let sample_rate = self.sample_rate;
+        self.clip_edit("Mute clip", &[track_idx], false, |session| {
+            let track = session
+                .tracks
+                .get_mut(track_idx)
+                .ok_or("Track index out of bounds")?;
+            let clip = track
+                .clips
+                .get_mut(clip_idx)
+                .ok_or("Clip index out of bounds")?;
+            clip.muted = !clip.muted;
+            let muted = clip.muted;
+            track.cache_waveform(sample_rate);
+            session.refresh_track_voice(track_idx);
+            Ok(muted)
+        })
     }
 
-    // --- Internal helpers ---
+    // --- Clip editing ---
+    // Edits only change which part of a recording plays and how loud; the audio itself
+    // is never touched.
 
-    /// Pre-render all non-muted tracks and sum into a mono buffer.
-    fn render_master_buffer(&self, playhead_pos: u64) -> Vec<f32> {
-        self.mix_tracks(playhead_pos, |_| true)
+    /// Split a clip at timeline frame `at`; returns the index of the second part.
+    pub fn split_clip(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        at: u64,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        self.clip_edit("Split clip", &[track_idx], false, |session| {
+            let tail = session
+                .track_for_clip_edit(track_idx)?
+                .split_clip(clip_idx, at)?;
+            session.finish_clip_edit(track_idx);
+            Ok(tail)
+        })
     }
 
-    fn render_overdub_buffer(&self, playhead_pos: u64) -> Vec<f32> {
-        self.mix_tracks(playhead_pos, |_| true)
+    /// Move a clip's start (`end == false`) or end edge by `delta` frames.
+    pub fn trim_clip(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        end: bool,
+        delta: i64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.clip_edit("Trim clip", &[track_idx], true, |session| {
+            let track = session.track_for_clip_edit(track_idx)?;
+            if end {
+                track.trim_clip_end(clip_idx, delta)?;
+            } else {
+                track.trim_clip_start(clip_idx, delta)?;
+            }
+            session.finish_clip_edit(track_idx);
+            Ok(())
+        })
     }
 
-    /// Sum rendered samples from tracks matching the predicate.
-    /// This is where the actual mixing happens - each track renders its audio
-    /// from the playhead position, then we sum them all together.
-    fn mix_tracks(&self, playhead_pos: u64, include: impl Fn(&Track) -> bool) -> Vec<f32> {
-        let mut master: Vec<f32> = Vec::new();
+    /// Slide a clip along its track by `delta` frames; returns its new start.
+    pub fn nudge_clip(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        delta: i64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        self.clip_edit("Move clip", &[track_idx], true, |session| {
+            let start = session
+                .track_for_clip_edit(track_idx)?
+                .move_clip(clip_idx, delta)?;
+            session.finish_clip_edit(track_idx);
+            Ok(start)
+        })
+    }
 
-        for track in &self.tracks {
-            if !include(track) {
-                continue;
-            }
-            // Ask the track to render its audio from this position
-            let rendered = track.render(playhead_pos, self.sample_rate);
-            if rendered.is_empty() {
-                continue;
+    /// Move a clip to another track at the same position; returns its index there.
+    /// A comped section stops belonging to its take, which stays on the old track.
+    pub fn move_clip_to_track(
+        &mut self,
+        from: usize,
+        clip_idx: usize,
+        to: usize,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let target = self.tracks.get(to).ok_or("Track index out of bounds")?;
+        if target.is_group {
+            return Err("Group tracks cannot hold clips".into());
+        }
+        self.clip_edit("Move clip to track", &[from, to], false, |session| {
+            let track = session.track_for_clip_edit(from)?;
+            if clip_idx >= track.clips.len() {
+                return Err("Clip index out of bounds".into());
             }
-            // Mix by summing samples
-            if master.is_empty() {
-                master = rendered;
+            let mut clip = track.clips.remove(clip_idx);
+            clip.take = None;
+            session.finish_clip_edit(from);
+            session.tracks[to].clips.push(clip);
+            session.finish_clip_edit(to);
+            Ok(session.tracks[to].clips.len() - 1)
+        })
+    }
+
+    /// Change a clip's gain by `delta_db`; returns the new gain in dB.
+    pub fn adjust_clip_gain(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        delta_db: f32,
+    ) -> Result<f32, Box<dyn std::error::Error>> {
+        self.clip_edit("Clip gain", &[track_idx], true, |session| {
+            let clip = session.clip_for_edit(track_idx, clip_idx)?;
+            clip.gain_db = (clip.gain_db + delta_db).clamp(MIN_CLIP_GAIN_DB, MAX_CLIP_GAIN_DB);
+            let gain = clip.gain_db;
+            session.finish_clip_edit(track_idx);
+            Ok(gain)
+        })
+    }
+
+    /// Lengthen or shorten a clip's fade-in (`fade_out == false`) or fade-out by
+    /// `delta` frames; returns the new fade length.
+    pub fn adjust_clip_fade(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        fade_out: bool,
+        delta: i64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        self.clip_edit("Clip fade", &[track_idx], true, |session| {
+            let clip = session.clip_for_edit(track_idx, clip_idx)?;
+            let length = clip.length;
+            let fade = if fade_out {
+                &mut clip.fade_out
             } else {
-                // Extend master buffer if this track is longer
-                if rendered.len() > master.len() {
-                    master.resize(rendered.len(), 0.0);
-                }
-                // Add this track's samples to the master mix
-                for (i, &s) in rendered.iter().enumerate() {
-                    master[i] += s;
-                }
-            }
-        }
+                &mut clip.fade_in
+            };
+            fade.frames = fade.frames.saturating_add_signed(delta).min(length);
+            let frames = fade.frames;
+            session.finish_clip_edit(track_idx);
+            Ok(frames)
+        })
+    }
 
-        master
+    /// Switch a clip's fade-in or fade-out to the next shape; returns it.
+    pub fn cycle_clip_fade_shape(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        fade_out: bool,
+    ) -> Result<FadeShape, Box<dyn std::error::Error>> {
+        self.clip_edit("Fade shape", &[track_idx], false, |session| {
+            let clip = session.clip_for_edit(track_idx, clip_idx)?;
+            let fade = if fade_out {
+                &mut clip.fade_out
+            } else {
+                &mut clip.fade_in
+            };
+            fade.shape = fade.shape.next();
+            let shape = fade.shape;
+            session.finish_clip_edit(track_idx);
+            Ok(shape)
+        })
     }
 
-    /// Build a monitor consumer if any armed tracks have monitoring enabled.
-    /// Returns None if no monitoring is active or during recording.
-    fn build_monitor_consumer(&self) -> Option<ringbuf::HeapCons<f32>> {
-        // During playback, we don't set up live monitoring from scratch.
-        // Monitoring is handled by start_monitoring() / recording flow.
-        None
+    /// Lengthen or shorten the crossfade into a clip from the clip before it by
+    /// `delta` frames, making it a manual crossfade; returns the length that plays,
+    /// which may be shorter when the clips run out of audio.
+    pub fn adjust_crossfade(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+        delta: i64,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let sample_rate = self.sample_rate;
+        self.clip_edit("Crossfade", &[track_idx], true, |session| {
+            let track = session.track_for_clip_edit(track_idx)?;
+            let junction = track
+                .junction_into(clip_idx, sample_rate)
+                .ok_or("The clip does not meet another clip at its start")?;
+            let current = junction.region.end - junction.region.start;
+            track.set_crossfade(
+                clip_idx,
+                current.saturating_add_signed(delta),
+                junction.shape,
+                sample_rate,
+            )?;
+            let frames = track
+                .junction_into(clip_idx, sample_rate)
+                .map_or(0, |j| j.region.end - j.region.start);
+            session.finish_clip_edit(track_idx);
+            Ok(frames)
+        })
+    }
+
+    /// Switch the crossfade into a clip to the next shape, making it a manual
+    /// crossfade; returns the new shape.
+    pub fn cycle_crossfade_shape(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+    ) -> Result<FadeShape, Box<dyn std::error::Error>> {
+        let sample_rate = self.sample_rate;
+        self.clip_edit("Crossfade shape", &[track_idx], false, |session| {
+            let track = session.track_for_clip_edit(track_idx)?;
+            let junction = track
+                .junction_into(clip_idx, sample_rate)
+                .ok_or("The clip does not meet another clip at its start")?;
+            let shape = junction.shape.next();
+            let (left, right) = (&track.clips[junction.left].id, &track.clips[clip_idx].id);
+            let frames = match track
+                .crossfades
+                .iter()
+                .find(|x| &x.left == left && &x.right == right)
+            {
+                Some(x) => x.frames,
+                None => junction.region.end - junction.region.start,
+            };
+            track.set_crossfade(clip_idx, frames, shape, sample_rate)?;
+            session.finish_clip_edit(track_idx);
+            Ok(shape)
+        })
+    }
+
+    /// Turn automatic crossfades on or off for a track; manual ones always play.
+    /// Returns whether they are now on.
+    pub fn toggle_auto_crossfade(
+        &mut self,
+        track_idx: usize,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.clip_edit("Auto crossfade", &[track_idx], false, |session| {
+            let track = session.track_for_clip_edit(track_idx)?;
+            track.auto_crossfade = !track.auto_crossfade;
+            let enabled = track.auto_crossfade;
+            session.finish_clip_edit(track_idx);
+            Ok(enabled)
+        })
     }
+
+    fn track_for_clip_edit(
+        &mut self,
+        track_idx: usize,
+    ) -> Result<&mut Track, Box<dyn std::error::Error>> {
+        if self.transport.state == TransportState::Recording {
+            return Err("Clips cannot be edited while recording".into());
+        }
+        Ok(self
+            .tracks
+            .get_mut(track_idx)
+            .ok_or("Track index out of bounds")?)
+    }
+
+    fn clip_for_edit(
+        &mut self,
+        track_idx: usize,
+        clip_idx: usize,
+    ) -> Result<&mut Clip, Box<dyn std::error::Error>> {
+        Ok(self
+            .track_for_clip_edit(track_idx)?
+            .clips
+            .get_mut(clip_idx)
+            .ok_or("Clip index out of bounds")?)
+    }
+
+    // Redraw the track's waveform and hand its new clips to the running mixer
+    fn finish_clip_edit(&mut self, track_idx: usize) {
+        if let Some(track) = self.tracks.get_mut(track_idx) {
+            track.cache_waveform(self.sample_rate);
+        }
+        self.refresh_track_voice(track_idx);
+    }
+
+    // Run an edit of the clips on `tracks` and record it for undo if it changed them
+    fn clip_edit<T>(
+        &mut self,
+        name: &str,
+        tracks: &[usize],
+        coalesce: bool,
+        edit: impl FnOnce(&mut Self) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let before: Vec<(usize, ClipLayout)> = tracks
+            .iter()
+            .filter_map(|&i| Some((i, self.history.capture(self.tracks.get(i)?))))
+            .collect();
+        let result = edit(self)?;
+        self.record_clip_changes(name, before, coalesce);
+        Ok(result)
+    }
+
+    fn record_clip_changes(
+        &mut self,
+        name: &str,
+        before: Vec<(usize, ClipLayout)>,
+        coalesce: bool,
+    ) {
+        let mut edits = Vec::new();
+        for (track, before) in before {
+            let Some(current) = self.tracks.get(track) else {
+                continue;
+            };
+            let after = self.history.capture(current);
+            if after != before {
+                edits.push(Edit::Clips {
+                    track,
+                    before,
+                    after,
+                });
+            }
+        }
+        self.history.record(name, edits, coalesce);
+    }
+
+    // --- Take lanes and comping ---
+
+    /// Move the comping selection `delta` take lanes along, wrapping around; returns
+    /// the selected take.
+    pub fn select_take(
+        &mut self,
+        track_idx: usize,
+        delta: isize,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let track = self
+            .tracks
+            .get_mut(track_idx)
+            .ok_or("Track index out of bounds")?;
+        if track.takes.is_empty() {
+            return Err("Track has no takes".into());
+        }
+        let count = track.takes.len() as isize;
+        track.selected_take = (track.selected_take as isize + delta).rem_euclid(count) as usize;
+        Ok(track.selected_take)
+    }
+
+    /// Make the selected take play over `range`, or over all of it when `range` is None.
+    pub fn comp_selected_take(
+        &mut self,
+        track_idx: usize,
+        range: Option<Range<u64>>,
+    ) -> Result<Range<u64>, Box<dyn std::error::Error>> {
+        let sample_rate = self.sample_rate;
+        self.clip_edit("Comp take", &[track_idx], false, |session| {
+            let track = session
+                .tracks
+                .get_mut(track_idx)
+                .ok_or("Track index out of bounds")?;
+            let take = track
+                .takes
+                .get(track.selected_take)
+                .ok_or("Track has no takes")?;
+            let range = range.unwrap_or(take.starts_at..take.end());
+            let range = range.start.max(take.starts_at)..range.end.min(take.end());
+            track.comp_take(track.selected_take, range.clone())?;
+            track.cache_waveform(sample_rate);
+            session.refresh_track_voice(track_idx);
+            Ok(range)
+        })
+    }
+
+    /// Delete the selected take and the sections of it that play.
+    pub fn remove_selected_take(
+        &mut self,
+        track_idx: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sample_rate = self.sample_rate;
+        self.clip_edit("Delete take", &[track_idx], false, |session| {
+            let track = session
+                .tracks
+                .get_mut(track_idx)
+                .ok_or("Track index out of bounds")?;
+            track.remove_take(track.selected_take)?;
+            track.cache_waveform(sample_rate);
+            session.refresh_track_voice(track_idx);
+            Ok(())
+        })
+    }
+
+    // --- FX Chain management ---
+
+    pub fn fx_chain(&self, target: FxTarget) -> Option<&[EffectInstance]> {
+        match target {
+            FxTarget::Track(i) => self.tracks.get(i).map(|t| t.fx_chain.as_slice()),
+            FxTarget::Aux(i) => self.aux_buses.get(i).map(|b| b.fx_chain.as_slice()),
+            FxTarget::Master => Some(self.master.fx_chain.as_slice()),
+        }
+    }
+
+    /// Display name of the track or bus owning an FX chain.
+    pub fn fx_target_name(&self, target: FxTarget) -> Option<&str> {
+        match target {
+            FxTarget::Track(i) => self.tracks.get(i).map(|t| t.name.as_str()),
+            FxTarget::Aux(i) => self.aux_buses.get(i).map(|b| b.name.as_str()),
+            FxTarget::Master => Some("Master"),
+        }
+    }
+
+    pub fn add_effect(&mut self, target: FxTarget, effect: EffectInstance) -> Result<(), String> {
+        // Buses, groups and the master are processed block by block with no offline fallback
+        let streaming_only = match target {
+            FxTarget::Track(i) => self.tracks.get(i).is_some_and(|t| t.is_group),
+            FxTarget::Aux(_) | FxTarget::Master => true,
+        };
+        if streaming_only && effect.processor(self.sample_rate, self.channels).is_none() {
+            return Err(format!(
+                "{} cannot stream, so it only works on audio tracks",
+                effect.effect_type().name()
+            ));
+        }
+        let chain = self.fx_chain_mut(target)?;
+        chain.push(effect.clone());
+        let index = chain.len() - 1;
+        self.refresh_fx(target);
+        self.history.record(
+            "Add effect",
+            vec![Edit::Effect {
+                target,
+                index,
+                before: None,
+                after: Some(effect),
+            }],
+            false,
+        );
+        Ok(())
+    }
+
+    pub fn remove_effect(&mut self, target: FxTarget, effect_idx: usize) -> Result<(), String> {
+        let chain = self.fx_chain_mut(target)?;
+        if effect_idx >= chain.len() {
+            return Err("Effect index out of bounds".to_string());
+        }
+        let removed = chain.remove(effect_idx);
+        let mut edits = vec![Edit::Effect {
+            target,
+            index: effect_idx,
+            before: Some(removed),
+            after: None,
+        }];
+        // Lanes of the removed effect go with it; later effects' lanes move up
+        if let FxTarget::Track(i) = target {
+            let glide = self.seconds_to_frames(TOUCH_GLIDE_SECONDS);
+            let track = &mut self.tracks[i];
+            let before = track.automation.clone();
+            track.finish_automation_writes(glide);
+            track.remove_effect_automation(effect_idx);
+            if track.automation != before {
+                edits.push(Edit::Automation {
+                    track: i,
+                    before,
+                    after: track.automation.clone(),
+                });
+            }
+        }
+        self.refresh_fx(target);
+        self.history.record("Remove effect", edits, false);
+        Ok(())
+    }
+
+    pub fn update_effect_param(
+        &mut self,
+        target: FxTarget,
+        effect_idx: usize,
+        param: &str,
+        value: &str,
+    ) -> Result<(), String> {
+        let chain = self.fx_chain_mut(target)?;
+        if effect_idx >= chain.len() {
+            return Err("Effect index out of bounds".to_string());
+        }
+        let updated = chain[effect_idx].update_parameter(param, value)?;
+        let before = std::mem::replace(&mut chain[effect_idx], updated.clone());
+        if let FxTarget::Track(track) = target {
+            let old_value = before
+                .parameters()
+                .into_iter()
+                .find(|(name, _)| name == param)
+                .and_then(|(_, v)| v.parse::<f32>().ok());
+            if let (Some(old_value), Ok(new_value)) = (old_value, value.parse::<f32>()) {
+                let target = AutomationTarget::Effect {
+                    index: effect_idx,
+                    param: param.to_string(),
+                };
+                self.touch_automation(track, target, old_value, new_value);
+            }
+        }
+        self.refresh_fx(target);
+        self.history.record(
+            "Effect parameter",
+            vec![Edit::Effect {
+                target,
+                index: effect_idx,
+                before: Some(before),
+                after: Some(updated),
+            }],
+            true,
+        );
+        Ok(())
+    }
+
+    // --- Aux buses ---
+
+    /// Add an aux bus and return its index.
+    pub fn add_aux_bus(&mut self, name: String) -> usize {
+        let bus = AuxBus::new(name);
+        if let Some(handle) = self.mixer.as_mut() {
+            handle.add_bus(BusVoice::from_bus(&bus, self.sample_rate, self.channels));
+        }
+        self.aux_buses.push(bus.clone());
+        let index = self.aux_buses.len() - 1;
+        self.history
+            .record("Add bus", vec![Edit::AddBus { index, bus }], false);
+        index
+    }
+
+    /// Remove an aux bus together with every send feeding it.
+    pub fn remove_aux_bus(&mut self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if index >= self.aux_buses.len() {
+            return Err("Bus index out of bounds".into());
+        }
+        let bus = self.aux_buses[index].clone();
+        let sends = self.detach_aux_bus(index);
+        self.history.record(
+            "Remove bus",
+            vec![Edit::RemoveBus { index, bus, sends }],
+            false,
+        );
+        Ok(())
+    }
+
+    // Take a bus out of the session with the sends feeding it; later buses move
+    // down. Returns the removed sends as (track, position in its sends, send).
+    fn detach_aux_bus(&mut self, index: usize) -> Vec<(usize, usize, AuxSend)> {
+        self.aux_buses.remove(index);
+
+        let mut removed = Vec::new();
+        for (t, track) in self.tracks.iter_mut().enumerate() {
+            let mut position = 0;
+            while position < track.sends.len() {
+                if track.sends[position].bus == index {
+                    removed.push((t, position, track.sends.remove(position)));
+                } else {
+                    position += 1;
+                }
+            }
+            for send in track.sends.iter_mut() {
+                if send.bus > index {
+                    send.bus -= 1;
+                }
+            }
+        }
+        if let Some(handle) = self.mixer.as_mut() {
+            for (i, track) in self.tracks.iter().enumerate() {
+                handle.set_sends(i, track.sends.clone());
+            }
+            handle.remove_bus(index);
+        }
+        removed
+    }
+
+    // Put a removed bus back at `index` with the sends that fed it; later buses move
+    // up. Only called while no mixer runs.
+    fn insert_aux_bus(&mut self, index: usize, bus: &AuxBus, sends: &[(usize, usize, AuxSend)]) {
+        let index = index.min(self.aux_buses.len());
+        for track in self.tracks.iter_mut() {
+            for send in track.sends.iter_mut() {
+                if send.bus >= index {
+                    send.bus += 1;
+                }
+            }
+        }
+        self.aux_buses.insert(index, bus.clone());
+        for &(t, position, send) in sends {
+            if let Some(track) = self.tracks.get_mut(t) {
+                let position = position.min(track.sends.len());
+                track.sends.insert(position, AuxSend { bus: index, ..send });
+            }
+        }
+    }
+
+    /// The track's send into `bus`, if it has one.
+    pub fn send(&self, track: usize, bus: usize) -> Option<&AuxSend> {
+        self.tracks
+            .get(track)?
+            .sends
+            .iter()
+            .find(|send| send.bus == bus)
+    }
+
+    /// Create or update the send from a track into an aux bus.
+    pub fn set_send(
+        &mut self,
+        track_idx: usize,
+        bus: usize,
+        level: f32,
+        pre_fader: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if bus >= self.aux_buses.len() {
+            return Err("Bus index out of bounds".into());
+        }
+        self.edit_mixer(track_idx, "Send", |track| {
+            let send = match track.sends.iter_mut().find(|send| send.bus == bus) {
+                Some(send) => send,
+                None => {
+                    track.sends.push(AuxSend::new(bus));
+                    track.sends.last_mut().unwrap()
+                }
+            };
+            send.level = level.max(0.0);
+            send.pre_fader = pre_fader;
+        })
+        .ok_or("Track index out of bounds")?;
+        Ok(())
+    }
+
+    pub fn remove_send(&mut self, track_idx: usize, bus: usize) {
+        self.edit_mixer(track_idx, "Remove send", |track| {
+            track.sends.retain(|send| send.bus != bus);
+        });
+    }
+
+    // --- Mixer ---
+
+    /// Change a track's mixer settings (volume, pan, mute, solo, sends) through
+    /// `edit`, recording it for undo; repeats of the same edit undo together.
+    /// Returns None for a track that does not exist.
+    pub fn edit_mixer<T>(
+        &mut self,
+        index: usize,
+        name: &str,
+        edit: impl FnOnce(&mut Track) -> T,
+    ) -> Option<T> {
+        let track = self.tracks.get_mut(index)?;
+        let before = MixerState::of(track);
+        let result = edit(track);
+        let after = MixerState::of(track);
+        self.refresh_sends(index);
+        self.sync_mixer();
+        if after != before {
+            self.touch_mixer_automation(index, &before, &after);
+            self.history.record(
+                name,
+                vec![Edit::Mixer {
+                    track: index,
+                    before,
+                    after,
+                }],
+                true,
+            );
+        }
+        Some(result)
+    }
+
+    /// Change an aux bus's volume or mute through `edit`, recording it for undo.
+    /// Returns None for a bus that does not exist.
+    pub fn edit_bus<T>(
+        &mut self,
+        index: usize,
+        name: &str,
+        edit: impl FnOnce(&mut AuxBus) -> T,
+    ) -> Option<T> {
+        let bus = self.aux_buses.get_mut(index)?;
+        let before = (bus.volume, bus.muted);
+        let result = edit(bus);
+        let after = (bus.volume, bus.muted);
+        self.sync_mixer();
+        if after != before {
+            self.history.record(
+                name,
+                vec![Edit::Bus {
+                    bus: index,
+                    before,
+                    after,
+                }],
+                true,
+            );
+        }
+        Some(result)
+    }
+
+    /// Set the master channel's gain, recording it for undo.
+    pub fn set_master_gain(&mut self, gain: f64) {
+        let before = self.master.gain;
+        self.master.gain = gain;
+        self.sync_mixer();
+        if before != gain {
+            self.history.record(
+                "Master volume",
+                vec![Edit::MasterGain {
+                    before,
+                    after: gain,
+                }],
+                true,
+            );
+        }
+    }
+
+    // --- Automation ---
+
+    /// Step a track's automation mode along (Off, Read, Touch, Latch), ending any
+    /// write in progress; returns the new mode.
+    pub fn cycle_automation_mode(&mut self, index: usize) -> Option<AutomationMode> {
+        self.update_automation_writes(index, None);
+        let track = self.tracks.get_mut(index)?;
+        track.automation_mode = track.automation_mode.next();
+        let mode = track.automation_mode;
+        self.refresh_track_voice(index);
+        Some(mode)
+    }
+
+    /// Show the next automatable target's lane on a track; returns its name.
+    pub fn cycle_automation_view(&mut self, index: usize) -> Option<String> {
+        let track = self.tracks.get_mut(index)?;
+        track.automation_view = (track.automation_view + 1) % track.automation_targets().len();
+        Some(track.automation_target_name(&track.viewed_automation()))
+    }
+
+    /// Add a breakpoint at `frame` to the lane the track shows, at the value the
+    /// target has there; returns that value.
+    pub fn add_automation_point(
+        &mut self,
+        index: usize,
+        frame: u64,
+    ) -> Result<f32, Box<dyn std::error::Error>> {
+        self.automation_edit(index, "Add automation point", false, |track| {
+            let target = track.viewed_automation();
+            let value = track
+                .automation_value(&target, frame)
+                .ok_or("The parameter is not numeric")?;
+            let curve = target.default_curve();
+            track.lane_mut(&target).set_point(frame, value, curve);
+            Ok(value)
+        })
+    }
+
+    /// Remove the breakpoint of the shown lane closest to `frame`, within `tolerance`.
+    pub fn remove_automation_point(
+        &mut self,
+        index: usize,
+        frame: u64,
+        tolerance: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.automation_edit(index, "Remove automation point", false, |track| {
+            let target = track.viewed_automation();
+            let lane = track.lane_mut(&target);
+            let point = lane
+                .point_near(frame, tolerance)
+                .ok_or("No automation point at the playhead")?;
+            lane.points.remove(point);
+            track.automation.retain(|l| !l.points.is_empty());
+            Ok(())
+        })
+    }
+
+    /// Move the value of the shown lane's breakpoint closest to `frame` by `delta`,
+    /// kept within the target's range; returns the new value.
+    pub fn adjust_automation_point(
+        &mut self,
+        index: usize,
+        frame: u64,
+        tolerance: u64,
+        delta: f32,
+    ) -> Result<f32, Box<dyn std::error::Error>> {
+        self.automation_edit(index, "Automation point value", true, |track| {
+            let target = track.viewed_automation();
+            let (min, max) = target.range();
+            let lane = track.lane_mut(&target);
+            let point = lane
+                .point_near(frame, tolerance)
+                .ok_or("No automation point at the playhead")?;
+            let value = &mut lane.points[point].value;
+            *value = (*value + delta).clamp(min, max);
+            Ok(*value)
+        })
+    }
+
+    /// Step the curve leaving the shown lane's breakpoint closest to `frame` along;
+    /// returns the new curve.
+    pub fn cycle_automation_curve(
+        &mut self,
+        index: usize,
+        frame: u64,
+        tolerance: u64,
+    ) -> Result<Curve, Box<dyn std::error::Error>> {
+        self.automation_edit(index, "Automation curve", false, |track| {
+            let target = track.viewed_automation();
+            let lane = track.lane_mut(&target);
+            let point = lane
+                .point_near(frame, tolerance)
+                .ok_or("No automation point at the playhead")?;
+            let curve = &mut lane.points[point].curve;
+            *curve = curve.next();
+            Ok(*curve)
+        })
+    }
+
+    // Run an edit of a track's automation lanes, record it for undo and hand the
+    // lanes to the running mixer
+    fn automation_edit<T>(
+        &mut self,
+        index: usize,
+        name: &str,
+        coalesce: bool,
+        edit: impl FnOnce(&mut Track) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let track = self
+            .tracks
+            .get_mut(index)
+            .ok_or("Track index out of bounds")?;
+        if track.is_group {
+            return Err("Group tracks cannot be automated".into());
+        }
+        let before = track.automation.clone();
+        let result = edit(track);
+        // A lane added for an edit that then failed is dropped again
+        track.automation.retain(|l| !l.points.is_empty());
+        let after = track.automation.clone();
+        if after != before {
+            self.history.record(
+                name,
+                vec![Edit::Automation {
+                    track: index,
+                    before,
+                    after,
+                }],
+                coalesce,
+            );
+            self.refresh_track_voice(index);
+        }
+        result
+    }
+
+    // In touch and latch modes, mixer changes made during playback are written into
+    // the track's lanes
+    fn touch_mixer_automation(&mut self, index: usize, before: &MixerState, after: &MixerState) {
+        let mute = |muted: bool| if muted { 1.0 } else { 0.0 };
+        let changes = [
+            (
+                AutomationTarget::Volume,
+                before.volume as f32,
+                after.volume as f32,
+            ),
+            (AutomationTarget::Pan, before.pan, after.pan),
+            (
+                AutomationTarget::Mute,
+                mute(before.muted),
+                mute(after.muted),
+            ),
+        ];
+        for (target, from, to) in changes {
+            if from != to {
+                self.touch_automation(index, target, from, to);
+            }
+        }
+    }
+
+    // Write a live change at the playhead if the track is writing automation
+    fn touch_automation(
+        &mut self,
+        index: usize,
+        target: AutomationTarget,
+        before: f32,
+        value: f32,
+    ) {
+        let Some(frame) = self.mixer.as_ref().map(MixerHandle::position) else {
+            return;
+        };
+        let Some(track) = self.tracks.get_mut(index) else {
+            return;
+        };
+        if !track.is_group && track.touch_automation(target, frame, before, value) {
+            // The lane being written drops out of playback so the live value is heard
+            self.refresh_track_voice(index);
+        }
+    }
+
+    // Follow a track's writes in progress to `position`, or end them all when it is
+    // None, recording lane changes for undo
+    fn update_automation_writes(&mut self, index: usize, position: Option<u64>) {
+        let release = self.seconds_to_frames(TOUCH_RELEASE_SECONDS);
+        let glide = self.seconds_to_frames(TOUCH_GLIDE_SECONDS);
+        let Some(track) = self.tracks.get_mut(index) else {
+            return;
+        };
+        if !track.is_writing_automation() {
+            return;
+        }
+        let before = track.automation.clone();
+        let changed = match position {
+            Some(frame) => track.follow_automation_writes(frame, release, glide),
+            None => track.finish_automation_writes(glide),
+        };
+        if changed {
+            let after = track.automation.clone();
+            self.history.record(
+                "Write automation",
+                vec![Edit::Automation {
+                    track: index,
+                    before,
+                    after,
+                }],
+                false,
+            );
+            self.refresh_track_voice(index);
+        }
+    }
+
+    fn end_automation_writes(&mut self) {
+        for index in 0..self.tracks.len() {
+            self.update_automation_writes(index, None);
+        }
+    }
+
+    fn seconds_to_frames(&self, seconds: f64) -> u64 {
+        (seconds * self.sample_rate as f64) as u64
+    }
+
+    /// Render the entire master mix from the start, through the master FX chain and gain,
+    /// as interleaved f32 samples with `self.channels` channels.
+    pub fn render_full_mix(&self) -> Vec<f32> {
+        self.mix_tracks(0)
+    }
+
+    /// Loudness of the whole master mix.
+    pub fn mix_loudness(&self) -> LoudnessReport {
+        loudness::analyze(&self.render_full_mix(), self.channels, self.sample_rate)
+    }
+
+    /// Loudness of one track's stem: its dry output through its groups and the master,
+    /// as exported.
+    pub fn track_loudness(&self, index: usize) -> Result<LoudnessReport, String> {
+        if index >= self.tracks.len() {
+            return Err("Track index out of bounds".to_string());
+        }
+        let frames = self.export_frames(&ExportRange::Project)?;
+        let mut mixer = self.export_mixer(&frames, &self.audible_tracks(), Stem::Track(index));
+        let length = (mixer.end_frame().max(frames.end) - frames.start) as usize;
+        let mut samples = vec![0.0f32; length * self.channels as usize];
+        mixer.process(&mut samples);
+        Ok(loudness::analyze(&samples, self.channels, self.sample_rate))
+    }
+
+    /// Frames an export of `range` covers, before any tail.
+    pub fn export_frames(&self, range: &ExportRange) -> Result<Range<u64>, String> {
+        let frames = match range {
+            ExportRange::Project => 0..self.tracks.iter().map(Track::clips_end).max().unwrap_or(0),
+            ExportRange::Loop => self
+                .transport
+                .loop_range()
+                .ok_or_else(|| "No loop region set".to_string())?,
+            ExportRange::Custom(frames) => frames.clone(),
+        };
+        if frames.is_empty() {
+            return Err("Nothing to export".to_string());
+        }
+        Ok(frames)
+    }
+
+    /// Offline mixers for every file an export writes, each positioned at the start of
+    /// the range. Track stems skip groups, silent tracks and tracks without clips.
+    pub fn export_renders(&self, settings: &ExportSettings) -> Result<Vec<ExportRender>, String> {
+        let frames = self.export_frames(&settings.range)?;
+        let audible = self.audible_tracks();
+        let stems: Vec<(String, Stem)> = match settings.source {
+            ExportSource::Mix => vec![(self.name.clone(), Stem::Mix)],
+            ExportSource::TrackStems => self
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|&(i, t)| audible[i] && !t.is_group && !t.clips.is_empty())
+                .map(|(i, t)| (t.name.clone(), Stem::Track(i)))
+                .collect(),
+            ExportSource::BusStems => self
+                .aux_buses
+                .iter()
+                .enumerate()
+                .filter(|(_, bus)| !bus.muted)
+                .map(|(i, bus)| (bus.name.clone(), Stem::Bus(i)))
+                .collect(),
+        };
+        if stems.is_empty() {
+            return Err(format!(
+                "No {} to export",
+                settings.source.name().to_lowercase()
+            ));
+        }
+
+        Ok(stems
+            .into_iter()
+            .map(|(name, stem)| {
+                let mixer = self.export_mixer(&frames, &audible, stem);
+                let length = match settings.tail_seconds {
+                    Some(seconds) => frames.end - frames.start + self.seconds_to_frames(seconds),
+                    None => mixer.end_frame().max(frames.end) - frames.start,
+                };
+                ExportRender {
+                    name,
+                    mixer,
+                    frames: length,
+                    sample_rate: self.sample_rate,
+                    channels: self.channels,
+                }
+            })
+            .collect())
+    }
+
+    // --- Solo ---
+
+    /// True while at least one track is soloed.
+    pub fn is_soloing(&self) -> bool {
+        self.tracks.iter().any(|t| t.solo)
+    }
+
+    /// Whether the track at `index` is heard, taking mute, solo and solo-safe into account.
+    pub fn is_audible(&self, index: usize) -> bool {
+        self.audible_tracks().get(index).copied().unwrap_or(false)
+    }
+
+    /// Audibility of every track. A track is heard unless it is muted, or something is
+    /// soloed and neither the track nor an enclosing group is soloed or solo-safe.
+    /// Groups stay open while anything inside them is heard.
+    pub fn audible_tracks(&self) -> Vec<bool> {
+        let count = self.tracks.len();
+        let mut open = vec![true; count];
+        if self.is_soloing() {
+            let soloed = |i: usize| self.tracks[i].solo || self.tracks[i].solo_safe;
+            open = (0..count)
+                .map(|i| soloed(i) || self.ancestors(i).any(soloed))
+                .collect();
+            for i in 0..count {
+                if open[i] {
+                    for ancestor in self.ancestors(i).collect::<Vec<_>>() {
+                        open[ancestor] = true;
+                    }
+                }
+            }
+        }
+        (0..count)
+            .map(|i| open[i] && !self.tracks[i].muted)
+            .collect()
+    }
+
+    /// Toggle solo on a track. An exclusive solo clears every other track's solo and
+    /// leaves this one as the only soloed track (or unsoloes it if it already was).
+    pub fn toggle_solo(&mut self, index: usize, exclusive: bool) {
+        if index >= self.tracks.len() {
+            return;
+        }
+        let before: Vec<MixerState> = self.tracks.iter().map(MixerState::of).collect();
+        let soloed = if exclusive {
+            let only_solo = self
+                .tracks
+                .iter()
+                .enumerate()
+                .all(|(i, t)| t.solo == (i == index));
+            for track in self.tracks.iter_mut() {
+                track.solo = false;
+            }
+            !only_solo
+        } else {
+            !self.tracks[index].solo
+        };
+        self.tracks[index].solo = soloed;
+        self.record_mixer_changes("Solo", before);
+    }
+
+    /// Clear the latched clip indicator on every track, input and the master.
+    pub fn reset_clip_indicators(&self) {
+        for track in &self.tracks {
+            track.input_meter.reset_clip();
+            track.output_meter.reset_clip();
+        }
+        self.master.meter.reset_clip();
+    }
+
+    pub fn toggle_solo_safe(&mut self, index: usize) {
+        let before: Vec<MixerState> = self.tracks.iter().map(MixerState::of).collect();
+        if let Some(track) = self.tracks.get_mut(index) {
+            track.solo_safe = !track.solo_safe;
+        }
+        self.record_mixer_changes("Solo safe", before);
+    }
+
+    fn record_mixer_changes(&mut self, name: &str, before: Vec<MixerState>) {
+        let edits = before
+            .into_iter()
+            .zip(self.tracks.iter())
+            .enumerate()
+            .filter_map(|(track, (before, current))| {
+                let after = MixerState::of(current);
+                (after != before).then_some(Edit::Mixer {
+                    track,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        self.history.record(name, edits, false);
+    }
+
+    // --- Undo ---
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Undo the last edit; returns its name.
+    pub fn undo(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let command = self.history.next_undo().ok_or("Nothing to undo")?;
+        self.check_can_replay(command.edits.iter(), true)?;
+        let command = self.history.undo().ok_or("Nothing to undo")?;
+        for edit in command.edits.iter().rev() {
+            self.apply_edit(edit, true)?;
+        }
+        Ok(command.name)
+    }
+
+    /// Redo the last undone edit; returns its name.
+    pub fn redo(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let command = self.history.next_redo().ok_or("Nothing to redo")?;
+        self.check_can_replay(command.edits.iter(), false)?;
+        let command = self.history.redo().ok_or("Nothing to redo")?;
+        for edit in command.edits.iter() {
+            self.apply_edit(edit, false)?;
+        }
+        Ok(command.name)
+    }
+
+    fn check_can_replay<'a>(
+        &self,
+        edits: impl Iterator<Item = &'a Edit>,
+        undo: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.transport.state == TransportState::Recording {
+            return Err("Cannot undo or redo while recording".into());
+        }
+        let edits: Vec<&Edit> = edits.collect();
+        // Every clip the edits put back must still have its audio
+        for edit in &edits {
+            let layout = match (edit, undo) {
+                (Edit::Clips { before, .. }, true) => before,
+                (Edit::Clips { after, .. }, false) => after,
+                (Edit::AddTrack { track, .. }, false) | (Edit::RemoveTrack { track, .. }, true) => {
+                    &track.clips
+                }
+                _ => continue,
+            };
+            self.history.check_audio(layout)?;
+        }
+        // Net change in the number of tracks
+        let mut track_delta: isize = 0;
+        let mut structural = false;
+        for edit in edits {
+            match (edit, undo) {
+                (Edit::AddTrack { .. }, true) | (Edit::RemoveTrack { .. }, false) => {
+                    track_delta -= 1
+                }
+                (Edit::AddTrack { .. }, false) | (Edit::RemoveTrack { .. }, true) => {
+                    track_delta += 1
+                }
+                (Edit::AddBus { .. } | Edit::RemoveBus { .. }, _) => {}
+                _ => continue,
+            }
+            structural = true;
+        }
+        if structural && self.mixer.is_some() {
+            return Err("Stop playback to undo or redo adding or removing a track or bus".into());
+        }
+        if self.tracks.len() as isize + track_delta < 1 {
+            return Err("Cannot remove the last track".into());
+        }
+        Ok(())
+    }
+
+    // Put the session into the state before (`undo`) or after an edit
+    fn apply_edit(&mut self, edit: &Edit, undo: bool) -> Result<(), String> {
+        match edit {
+            Edit::Clips {
+                track,
+                before,
+                after,
+            } => {
+                let layout = if undo { before } else { after };
+                if let Some(t) = self.tracks.get_mut(*track) {
+                    self.history.restore(layout, t)?;
+                    t.cache_waveform(self.sample_rate);
+                }
+                self.refresh_track_voice(*track);
+            }
+            Edit::Mixer {
+                track,
+                before,
+                after,
+            } => {
+                if let Some(t) = self.tracks.get_mut(*track) {
+                    if undo { before } else { after }.apply(t);
+                }
+                self.refresh_sends(*track);
+                self.sync_mixer();
+            }
+            Edit::Bus { bus, before, after } => {
+                if let Some(b) = self.aux_buses.get_mut(*bus) {
+                    (b.volume, b.muted) = if undo { *before } else { *after };
+                }
+                self.sync_mixer();
+            }
+            Edit::MasterGain { before, after } => {
+                self.master.gain = if undo { *before } else { *after };
+                self.sync_mixer();
+            }
+            Edit::Automation {
+                track,
+                before,
+                after,
+            } => {
+                if let Some(t) = self.tracks.get_mut(*track) {
+                    t.automation = if undo { before } else { after }.clone();
+                }
+                self.refresh_track_voice(*track);
+            }
+            Edit::Effect {
+                target,
+                index,
+                before,
+                after,
+            } => {
+                let (from, to) = if undo {
+                    (after, before)
+                } else {
+                    (before, after)
+                };
+                if let Ok(chain) = self.fx_chain_mut(*target) {
+                    match (from, to) {
+                        (None, Some(effect)) => {
+                            chain.insert((*index).min(chain.len()), effect.clone())
+                        }
+                        (Some(_), None) if *index < chain.len() => {
+                            chain.remove(*index);
+                        }
+                        (Some(_), Some(effect)) if *index < chain.len() => {
+                            chain[*index] = effect.clone()
+                        }
+                        _ => {}
+                    }
+                }
+                self.refresh_fx(*target);
+            }
+            Edit::Parent {
+                track,
+                before,
+                after,
+            } => {
+                if let Some(t) = self.tracks.get_mut(*track) {
+                    t.parent = if undo { *before } else { *after };
+                }
+                self.refresh_routing();
+            }
+            Edit::AddTrack { index, track } => {
+                if undo {
+                    self.detach_track(*index);
+                } else {
+                    self.insert_track(*index, track)?;
+                }
+            }
+            Edit::RemoveTrack {
+                index,
+                track,
+                parents,
+            } => {
+                if undo {
+                    self.insert_track(*index, track)?;
+                    for (t, &parent) in self.tracks.iter_mut().zip(parents) {
+                        t.parent = parent;
+                    }
+                    self.refresh_routing();
+                } else {
+                    self.detach_track(*index);
+                }
+            }
+            Edit::AddBus { index, bus } => {
+                if undo {
+                    self.detach_aux_bus(*index);
+                } else {
+                    self.insert_aux_bus(*index, bus, &[]);
+                }
+            }
+            Edit::RemoveBus { index, bus, sends } => {
+                if undo {
+                    self.insert_aux_bus(*index, bus, sends);
+                } else {
+                    self.detach_aux_bus(*index);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn snapshot_track(&mut self, index: usize) -> TrackSnapshot {
+        let track = &self.tracks[index];
+        TrackSnapshot {
+            name: track.name.clone(),
+            is_group: track.is_group,
+            collapsed: track.collapsed,
+            input_channel: track.input_channel,
+            mixer: MixerState::of(track),
+            fx_chain: track.fx_chain.clone(),
+            clips: self.history.capture(track),
+            automation: track.automation.clone(),
+            automation_mode: track.automation_mode,
+        }
+    }
+
+    // Record the track just added at the end
+    fn record_added_track(&mut self, name: &str) {
+        let index = self.tracks.len() - 1;
+        let track = self.snapshot_track(index);
+        self.history
+            .record(name, vec![Edit::AddTrack { index, track }], false);
+    }
+
+    // Put a removed track back at `index`; only called while no mixer runs
+    fn insert_track(&mut self, index: usize, snapshot: &TrackSnapshot) -> Result<(), String> {
+        let mut track = Track::new(snapshot.name.clone());
+        track.is_group = snapshot.is_group;
+        track.collapsed = snapshot.collapsed;
+        track.input_channel = snapshot.input_channel;
+        snapshot.mixer.apply(&mut track);
+        track.fx_chain = snapshot.fx_chain.clone();
+        track.automation = snapshot.automation.clone();
+        track.automation_mode = snapshot.automation_mode;
+        self.history.restore(&snapshot.clips, &mut track)?;
+        track.cache_waveform(self.sample_rate);
+        let index = index.min(self.tracks.len());
+        for other in self.tracks.iter_mut() {
+            if let Some(parent) = other.parent.as_mut() {
+                if *parent >= index {
+                    *parent += 1;
+                }
+            }
+        }
+        self.tracks.insert(index, track);
+        Ok(())
+    }
+
+    // --- Internal helpers ---
+
+    /// Build a real-time mixer with one voice per track, positioned at `playhead_pos`.
+    /// Muted tracks, and tracks silenced by a solo, get muted voices. `metered` mixers
+    /// feed the track and master meters; offline renders leave them alone.
+    fn build_mixer(&self, playhead_pos: u64, metered: bool) -> (Mixer, MixerHandle) {
+        let audible = self.audible_tracks();
+        let from = self
+            .transport
+            .loop_range()
+            .map_or(playhead_pos, |r| r.start.min(playhead_pos));
+        let voices = self
+            .tracks
+            .iter()
+            .zip(&audible)
+            .map(|(track, &audible)| {
+                let voice = build_voice(track, from, self.sample_rate, self.channels, !audible);
+                if metered {
+                    voice.with_meter(Arc::clone(&track.output_meter))
+                } else {
+                    voice
+                }
+            })
+            .collect();
+        let routing = self.build_routing(&audible, metered);
+        let buses = self
+            .aux_buses
+            .iter()
+            .map(|bus| BusVoice::from_bus(bus, self.sample_rate, self.channels))
+            .collect();
+        let mut master = MasterStage::from_channel(&self.master, self.sample_rate, self.channels);
+        if metered {
+            master = master.with_meter(Arc::clone(&self.master.meter));
+        }
+        Mixer::new(voices, routing, buses, master, playhead_pos, self.channels)
+    }
+
+    /// Earliest frame live voices may be asked to play: the playhead, or the loop start
+    /// when playback will cycle back to before it.
+    fn render_from(&self) -> u64 {
+        let playhead = self.transport.playhead_position;
+        self.transport
+            .loop_range()
+            .map_or(playhead, |r| r.start.min(playhead))
+    }
+
+    /// Group submix buses and where every track and group feeds.
+    fn build_routing(&self, audible: &[bool], metered: bool) -> Routing {
+        let order = self.group_order();
+        let mut slots = vec![None; self.tracks.len()];
+        for (slot, &track) in order.iter().enumerate() {
+            slots[track] = Some(slot);
+        }
+        let output = |track: &Track| track.parent.and_then(|p| slots.get(p).copied().flatten());
+
+        Routing {
+            groups: order
+                .iter()
+                .map(|&t| {
+                    let group = &self.tracks[t];
+                    let bus =
+                        BusVoice::from_group(group, !audible[t], self.sample_rate, self.channels);
+                    if metered {
+                        bus.with_meter(Arc::clone(&group.output_meter))
+                    } else {
+                        bus
+                    }
+                })
+                .collect(),
+            group_outputs: order.iter().map(|&t| output(&self.tracks[t])).collect(),
+            track_outputs: self.tracks.iter().map(output).collect(),
+        }
+    }
+
+    /// Mix every audible track offline, from `playhead_pos` to the end of the last voice.
+    /// Muted tracks, and tracks silenced by another track's solo, are left out.
+    /// Drives the same mixer used for real-time playback, just synchronously.
+    fn mix_tracks(&self, playhead_pos: u64) -> Vec<f32> {
+        let (mut mixer, _handle) = self.build_mixer(playhead_pos, false);
+        // A click that is not output-only is part of the mix
+        if self.metronome.enabled && !self.metronome.output_only {
+            mixer.set_metronome(Some(Metronome::new(
+                &self.metronome,
+                &self.tempo,
+                self.sample_rate,
+            )));
+        }
+        let frames = mixer.end_frame().saturating_sub(playhead_pos) as usize;
+        let mut master = vec![0.0f32; frames * self.channels as usize];
+        mixer.process(&mut master);
+        master
+    }
+
+    /// An offline mixer for one export file, reading clips only within `frames`.
+    fn export_mixer(&self, frames: &Range<u64>, audible: &[bool], stem: Stem) -> Mixer {
+        let voices = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                let chain =
+                    VoiceChain::from_track(track, frames.start, self.sample_rate, self.channels)
+                        .until(frames.end);
+                let muted = !audible[i] || matches!(stem, Stem::Track(t) if t != i);
+                let params = VoiceParams::new(track.volume as f32, track.pan, muted);
+                match stem {
+                    Stem::Mix => TrackVoice::new(chain, Arc::new(params), track.sends.clone()),
+                    Stem::Track(_) => TrackVoice::new(chain, Arc::new(params), Vec::new()),
+                    Stem::Bus(_) => {
+                        TrackVoice::new(chain, Arc::new(params), track.sends.clone()).sends_only()
+                    }
+                }
+            })
+            .collect();
+        let routing = self.build_routing(audible, false);
+        let buses = self
+            .aux_buses
+            .iter()
+            .enumerate()
+            .map(|(i, bus)| {
+                let voice = BusVoice::from_bus(bus, self.sample_rate, self.channels);
+                let muted = bus.muted || matches!(stem, Stem::Bus(b) if b != i);
+                voice.params().set(bus.volume as f32, 0.0, muted);
+                voice
+            })
+            .collect();
+        let master = MasterStage::from_channel(&self.master, self.sample_rate, self.channels);
+        let (mut mixer, _handle) =
+            Mixer::new(voices, routing, buses, master, frames.start, self.channels);
+        if stem == Stem::Mix && self.metronome.enabled && !self.metronome.output_only {
+            mixer.set_metronome(Some(Metronome::new(
+                &self.metronome,
+                &self.tempo,
+                self.sample_rate,
+            )));
+        }
+        mixer
+    }
+
+    /// Push live track parameters to the running mixer and free anything it retired.
+    fn sync_mixer(&mut self) {
+        let audible = self.audible_tracks();
+        let groups = self.group_order();
+        if let Some(handle) = self.mixer.as_mut() {
+            for (i, track) in self.tracks.iter().enumerate() {
+                handle.set_params(i, track.volume as f32, track.pan, !audible[i]);
+            }
+            for (slot, &i) in groups.iter().enumerate() {
+                let group = &self.tracks[i];
+                handle.set_group_params(slot, group.volume as f32, group.pan, !audible[i]);
+            }
+            for (i, bus) in self.aux_buses.iter().enumerate() {
+                handle.set_bus_params(i, bus.volume as f32, bus.muted);
+            }
+            handle.set_master_gain(self.master.gain as f32);
+            handle.collect_retired();
+        }
+    }
+
+    /// Rebuild a track's clips/FX chain in the running mixer after an edit,
+    /// so the change is heard without restarting playback.
+    fn refresh_track_voice(&mut self, index: usize) {
+        let from = self.render_from();
+        let Some(handle) = self.mixer.as_mut() else {
+            return;
+        };
+        if let Some(track) = self.tracks.get(index) {
+            let chain = VoiceChain::from_track(track, from, self.sample_rate, self.channels);
+            handle.replace_chain(index, chain);
+        }
+    }
+
+    fn refresh_sends(&mut self, index: usize) {
+        if let (Some(handle), Some(track)) = (self.mixer.as_mut(), self.tracks.get(index)) {
+            handle.set_sends(index, track.sends.clone());
+        }
+    }
+
+    /// Rebuild the group submixes in the running mixer after the hierarchy or a
+    /// group's FX chain changed.
+    fn refresh_routing(&mut self) {
+        if self.mixer.is_none() {
+            return;
+        }
+        let routing = self.build_routing(&self.audible_tracks(), true);
+        if let Some(handle) = self.mixer.as_mut() {
+            handle.replace_routing(routing);
+        }
+    }
+
+    fn refresh_fx(&mut self, target: FxTarget) {
+        match target {
+            FxTarget::Track(i) if self.tracks.get(i).is_some_and(|t| t.is_group) => {
+                self.refresh_routing()
+            }
+            FxTarget::Track(i) => self.refresh_track_voice(i),
+            FxTarget::Aux(i) => {
+                if let (Some(handle), Some(bus)) = (self.mixer.as_mut(), self.aux_buses.get(i)) {
+                    let (fx, tail_frames) =
+                        chain_processors(&bus.fx_chain, self.sample_rate, self.channels);
+                    handle.replace_bus_fx(i, fx, tail_frames);
+                }
+            }
+            FxTarget::Master => {
+                if let Some(handle) = self.mixer.as_mut() {
+                    let (fx, tail_frames) =
+                        chain_processors(&self.master.fx_chain, self.sample_rate, self.channels);
+                    handle.replace_master_fx(fx, tail_frames);
+                }
+            }
+        }
+    }
+
+    fn fx_chain_mut(&mut self, target: FxTarget) -> Result<&mut Vec<EffectInstance>, String> {
+        match target {
+            FxTarget::Track(i) => self
+                .tracks
+                .get_mut(i)
+                .map(|t| &mut t.fx_chain)
+                .ok_or_else(|| "Track index out of bounds".to_string()),
+            FxTarget::Aux(i) => self
+                .aux_buses
+                .get_mut(i)
+                .map(|b| &mut b.fx_chain)
+                .ok_or_else(|| "Bus index out of bounds".to_string()),
+            FxTarget::Master => Ok(&mut self.master.fx_chain),
+        }
+    }
+
+    /// Build a monitor consumer if any armed tracks have monitoring enabled.
+    /// Returns None if no monitoring is active or during recording.
+    fn build_monitor_consumer(&self) -> Option<ringbuf::HeapCons<f32>> {
+        // During playback, we don't set up live monitoring from scratch.
+        // Monitoring is handled by start_monitoring() / recording flow.
+        None
+    }
+}
+
+/// Which part of the mix an export file holds.
+#[derive(Clone, Copy, PartialEq)]
+enum Stem {
+    Mix,
+    Track(usize),
+    Bus(usize),
+}
+
+fn build_voice(
+    track: &Track,
+    from_frame: u64,
+    sample_rate: u32,
+    channels: u16,
+    muted: bool,
+) -> TrackVoice {
+    let chain = VoiceChain::from_track(track, from_frame, sample_rate, channels);
+    let params = VoiceParams::new(track.volume as f32, track.pan, muted);
+    TrackVoice::new(chain, Arc::new(params), track.sends.clone())
 }
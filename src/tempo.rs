@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+
+pub const TICKS_PER_BEAT: u32 = 960;
+pub const DEFAULT_BPM: f64 = 120.0;
+pub const MIN_BPM: f64 = 20.0;
+pub const MAX_BPM: f64 = 400.0;
+// Time signatures offered when cycling through them in the UI
+pub const TIME_SIGNATURES: [(u8, u8); 6] = [(4, 4), (3, 4), (2, 4), (6, 8), (5, 4), (7, 8)];
+
+/// A tempo and time signature taking effect at the start of `bar` (0-based).
+/// `bpm` counts quarter notes per minute whatever the signature's note value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TempoChange {
+    pub bar: u32,
+    pub bpm: f64,
+    pub numerator: u8,
+    pub denominator: u8,
+}
+
+impl TempoChange {
+    fn frames_per_beat(&self, sample_rate: u32) -> f64 {
+        let quarters_per_beat = 4.0 / self.denominator.max(1) as f64;
+        sample_rate as f64 * 60.0 / self.bpm.max(MIN_BPM) * quarters_per_beat
+    }
+
+    fn beats_per_bar(&self) -> u32 {
+        self.numerator.max(1) as u32
+    }
+}
+
+/// A position in musical time. All fields are 0-based; `Display` shows bars and
+/// beats counted from 1, as on a DAW's counter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MusicalTime {
+    pub bar: u32,
+    pub beat: u32,
+    pub tick: u32,
+}
+
+impl fmt::Display for MusicalTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{:03}", self.bar + 1, self.beat + 1, self.tick)
+    }
+}
+
+/// A line of the bars/beats grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridLine {
+    pub frame: u64,
+    pub is_bar: bool,
+}
+
+/// Tempo changes and time signatures along the timeline. There is always a change at
+/// bar 0 and changes are kept sorted by bar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TempoMap {
+    changes: Vec<TempoChange>,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        TempoMap {
+            changes: vec![TempoChange {
+                bar: 0,
+                bpm: DEFAULT_BPM,
+                numerator: 4,
+                denominator: 4,
+            }],
+        }
+    }
+}
+
+impl TempoMap {
+    /// A map from stored changes, repairing anything a hand-edited manifest got wrong.
+    pub fn from_changes(mut changes: Vec<TempoChange>) -> Self {
+        changes.retain(|c| c.bpm.is_finite() && c.numerator > 0 && c.denominator > 0);
+        for change in changes.iter_mut() {
+            change.bpm = change.bpm.clamp(MIN_BPM, MAX_BPM);
+        }
+        changes.sort_by_key(|c| c.bar);
+        changes.dedup_by_key(|c| c.bar);
+        match changes.first_mut() {
+            Some(first) => first.bar = 0,
+            None => return Self::default(),
+        }
+        TempoMap { changes }
+    }
+
+    pub fn changes(&self) -> &[TempoChange] {
+        &self.changes
+    }
+
+    /// The change in effect at `bar`.
+    pub fn change_at_bar(&self, bar: u32) -> TempoChange {
+        let idx = self.changes.partition_point(|c| c.bar <= bar);
+        self.changes[idx.saturating_sub(1)]
+    }
+
+    /// Set the tempo and time signature from `bar` on, until the next change.
+    pub fn set_change(&mut self, change: TempoChange) {
+        let change = TempoChange {
+            bpm: change.bpm.clamp(MIN_BPM, MAX_BPM),
+            ..change
+        };
+        match self.changes.binary_search_by_key(&change.bar, |c| c.bar) {
+            Ok(idx) => self.changes[idx] = change,
+            Err(idx) => self.changes.insert(idx, change),
+        }
+    }
+
+    /// Remove the change starting at `bar`; the one at bar 0 always stays.
+    pub fn remove_change(&mut self, bar: u32) -> bool {
+        if bar == 0 {
+            return false;
+        }
+        let before = self.changes.len();
+        self.changes.retain(|c| c.bar != bar);
+        self.changes.len() != before
+    }
+
+    /// Each change with the frame it starts at.
+    fn segments(&self, sample_rate: u32) -> impl Iterator<Item = (TempoChange, f64)> + '_ {
+        let mut frame = 0.0;
+        let mut previous: Option<TempoChange> = None;
+        self.changes.iter().map(move |&change| {
+            if let Some(prev) = previous {
+                let bars = (change.bar - prev.bar) as f64;
+                frame += bars * prev.beats_per_bar() as f64 * prev.frames_per_beat(sample_rate);
+            }
+            previous = Some(change);
+            (change, frame)
+        })
+    }
+
+    /// The change in effect at `frame` and the frame it starts at.
+    fn segment_at(&self, frame: u64, sample_rate: u32) -> (TempoChange, f64) {
+        let frame = frame as f64;
+        self.segments(sample_rate)
+            .take_while(|&(_, start)| start <= frame)
+            .last()
+            .unwrap_or((self.changes[0], 0.0))
+    }
+
+    pub fn frames_to_musical(&self, frame: u64, sample_rate: u32) -> MusicalTime {
+        let (change, start) = self.segment_at(frame, sample_rate);
+        let beats = (frame as f64 - start) / change.frames_per_beat(sample_rate);
+        let whole_beats = beats.floor() as u64;
+        let beats_per_bar = change.beats_per_bar() as u64;
+        MusicalTime {
+            bar: change.bar + (whole_beats / beats_per_bar) as u32,
+            beat: (whole_beats % beats_per_bar) as u32,
+            tick: (((beats - beats.floor()) * TICKS_PER_BEAT as f64) as u32)
+                .min(TICKS_PER_BEAT - 1),
+        }
+    }
+
+    pub fn musical_to_frames(&self, time: MusicalTime, sample_rate: u32) -> u64 {
+        let (change, start) = self
+            .segments(sample_rate)
+            .take_while(|&(c, _)| c.bar <= time.bar)
+            .last()
+            .unwrap_or((self.changes[0], 0.0));
+        let beats = (time.bar - change.bar) as f64 * change.beats_per_bar() as f64
+            + time.beat as f64
+            + time.tick as f64 / TICKS_PER_BEAT as f64;
+        (start + beats * change.frames_per_beat(sample_rate)).round() as u64
+    }
+
+    /// The first beat at or after `frame`.
+    pub fn next_beat(&self, frame: u64, sample_rate: u32) -> GridLine {
+        let time = self.frames_to_musical(frame, sample_rate);
+        let mut beat = MusicalTime { tick: 0, ..time };
+        let mut beat_frame = self.musical_to_frames(beat, sample_rate);
+        if beat_frame < frame {
+            beat.beat += 1;
+            if beat.beat >= self.change_at_bar(beat.bar).beats_per_bar() {
+                beat.bar += 1;
+                beat.beat = 0;
+            }
+            beat_frame = self.musical_to_frames(beat, sample_rate);
+        }
+        GridLine {
+            frame: beat_frame,
+            is_bar: beat.beat == 0,
+        }
+    }
+
+    /// Length of one beat in frames at the start of `bar`, and the beats in that bar.
+    pub fn beat_at_bar(&self, bar: u32, sample_rate: u32) -> (f64, u32) {
+        let change = self.change_at_bar(bar);
+        (change.frames_per_beat(sample_rate), change.beats_per_bar())
+    }
+
+    /// Bar and beat lines within `range`. Beat lines are left out when more than
+    /// `max_lines` of them would be drawn.
+    pub fn grid_lines(
+        &self,
+        range: Range<u64>,
+        sample_rate: u32,
+        max_lines: usize,
+    ) -> Vec<GridLine> {
+        let first = self.frames_to_musical(range.start, sample_rate);
+        let mut bar = first.bar;
+        let mut lines = Vec::new();
+        loop {
+            let change = self.change_at_bar(bar);
+            for beat in 0..change.beats_per_bar() {
+                let frame = self.musical_to_frames(MusicalTime { bar, beat, tick: 0 }, sample_rate);
+                if frame >= range.end {
+                    return thin_grid(lines, max_lines);
+                }
+                if frame >= range.start {
+                    lines.push(GridLine {
+                        frame,
+                        is_bar: beat == 0,
+                    });
+                }
+            }
+            bar += 1;
+        }
+    }
+}
+
+// Too many beat lines turn into a blur; keep the bars only
+fn thin_grid(mut lines: Vec<GridLine>, max_lines: usize) -> Vec<GridLine> {
+    if lines.len() > max_lines {
+        lines.retain(|line| line.is_bar);
+    }
+    lines
+}
@@ -0,0 +1,454 @@
+use super::Track;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use strum::{EnumIter, IntoEnumIterator};
+
+// A touched lane is released once it has gone this long without a change
+pub const TOUCH_RELEASE_SECONDS: f64 = 0.5;
+// After a touch is released the lane glides back to its old values over this long
+pub const TOUCH_GLIDE_SECONDS: f64 = 0.1;
+
+/// How an automated value moves from one breakpoint to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, EnumIter)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Stays at the breakpoint's value until the next one
+    Hold,
+    /// Slow start and end, fast in the middle
+    SCurve,
+    /// Slow start, fast end
+    Exponential,
+}
+
+impl Curve {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Curve::Linear => "Linear",
+            Curve::Hold => "Hold",
+            Curve::SCurve => "S-Curve",
+            Curve::Exponential => "Exponential",
+        }
+    }
+
+    /// The curve after this one, wrapping around.
+    pub fn next(&self) -> Curve {
+        let curves: Vec<Curve> = Curve::iter().collect();
+        let idx = curves.iter().position(|c| c == self).unwrap_or(0);
+        curves[(idx + 1) % curves.len()]
+    }
+
+    /// How far (0.0 to 1.0) the value has moved at `t` of the way to the next point.
+    pub fn progress(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Curve::Linear => t,
+            Curve::Hold => 0.0,
+            Curve::SCurve => (1.0 - (t * PI).cos()) * 0.5,
+            Curve::Exponential => ((4.0 * t).exp() - 1.0) / (4.0f32.exp() - 1.0),
+        }
+    }
+}
+
+/// What an automation lane drives.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AutomationTarget {
+    Volume,
+    Pan,
+    /// Muted wherever the lane is at 0.5 or above
+    Mute,
+    /// A numeric parameter of the effect at `index` in the track's FX chain
+    Effect {
+        index: usize,
+        param: String,
+    },
+}
+
+impl AutomationTarget {
+    /// Values the lane can take; effect parameters are only checked by the effect.
+    pub fn range(&self) -> (f32, f32) {
+        match self {
+            AutomationTarget::Volume => (0.0, 2.0),
+            AutomationTarget::Pan => (-1.0, 1.0),
+            AutomationTarget::Mute => (0.0, 1.0),
+            AutomationTarget::Effect { .. } => (0.0, f32::MAX),
+        }
+    }
+
+    /// Curve of new breakpoints: mutes switch, everything else ramps.
+    pub fn default_curve(&self) -> Curve {
+        match self {
+            AutomationTarget::Mute => Curve::Hold,
+            _ => Curve::Linear,
+        }
+    }
+}
+
+/// A value at a timeline frame, reached from the previous breakpoint along that
+/// breakpoint's curve.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub frame: u64,
+    pub value: f32,
+    // Shape of the segment from this breakpoint to the next
+    #[serde(default)]
+    pub curve: Curve,
+}
+
+/// Breakpoints for one target, sorted by frame. Before the first breakpoint the lane
+/// holds its value, and after the last one likewise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationLane {
+    pub target: AutomationTarget,
+    #[serde(default)]
+    pub points: Vec<Breakpoint>,
+}
+
+impl AutomationLane {
+    pub fn new(target: AutomationTarget) -> Self {
+        AutomationLane {
+            target,
+            points: Vec::new(),
+        }
+    }
+
+    /// The lane's value at timeline frame `frame`, or None when it has no breakpoints.
+    /// Two breakpoints on the same frame make the value jump there.
+    pub fn value_at(&self, frame: u64) -> Option<f32> {
+        let first = self.points.first()?;
+        let next = self.points.partition_point(|p| p.frame <= frame);
+        if next == 0 {
+            return Some(first.value);
+        }
+        let from = &self.points[next - 1];
+        let Some(to) = self.points.get(next) else {
+            return Some(from.value);
+        };
+        let t = (frame - from.frame) as f32 / (to.frame - from.frame) as f32;
+        Some(from.value + (to.value - from.value) * from.curve.progress(t))
+    }
+
+    /// Add a breakpoint after any already on the same frame.
+    pub fn insert(&mut self, point: Breakpoint) {
+        let idx = self.points.partition_point(|p| p.frame <= point.frame);
+        self.points.insert(idx, point);
+    }
+
+    /// Set the value at `frame`, replacing the breakpoints already there.
+    pub fn set_point(&mut self, frame: u64, value: f32, curve: Curve) {
+        self.points.retain(|p| p.frame != frame);
+        self.insert(Breakpoint {
+            frame,
+            value,
+            curve,
+        });
+    }
+
+    /// Index of the breakpoint closest to `frame`, if one is within `tolerance` frames.
+    pub fn point_near(&self, frame: u64, tolerance: u64) -> Option<usize> {
+        self.points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.frame.abs_diff(frame) <= tolerance)
+            .min_by_key(|(_, p)| p.frame.abs_diff(frame))
+            .map(|(i, _)| i)
+    }
+}
+
+/// How a track's automation lanes behave during playback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, EnumIter)]
+pub enum AutomationMode {
+    /// Lanes are ignored and the static settings play
+    Off,
+    /// Lanes play back
+    #[default]
+    Read,
+    /// Changes made during playback are written until the control is let go,
+    /// then the lane returns to its old values
+    Touch,
+    /// Changes made during playback are written, and the last value keeps being
+    /// written until playback stops
+    Latch,
+}
+
+impl AutomationMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AutomationMode::Off => "Off",
+            AutomationMode::Read => "Read",
+            AutomationMode::Touch => "Touch",
+            AutomationMode::Latch => "Latch",
+        }
+    }
+
+    /// The mode after this one, wrapping around.
+    pub fn next(&self) -> AutomationMode {
+        let modes: Vec<AutomationMode> = AutomationMode::iter().collect();
+        let idx = modes.iter().position(|m| m == self).unwrap_or(0);
+        modes[(idx + 1) % modes.len()]
+    }
+
+    pub fn writes(&self) -> bool {
+        matches!(self, AutomationMode::Touch | AutomationMode::Latch)
+    }
+}
+
+/// A lane being written from live changes during playback. Its breakpoints join the
+/// lane when the write ends; until then the lane is left out of playback so the live
+/// value is heard.
+#[derive(Debug, Clone)]
+pub struct AutomationWrite {
+    target: AutomationTarget,
+    latch: bool,
+    start: u64,
+    points: Vec<Breakpoint>,
+    last_change: u64,
+    // Furthest frame the playhead has reached since the write started
+    reached: u64,
+}
+
+impl Track {
+    /// Everything on this track that can be automated: volume, pan, mute and each
+    /// numeric effect parameter.
+    pub fn automation_targets(&self) -> Vec<AutomationTarget> {
+        let mut targets = vec![
+            AutomationTarget::Volume,
+            AutomationTarget::Pan,
+            AutomationTarget::Mute,
+        ];
+        for (index, fx) in self.fx_chain.iter().enumerate() {
+            for (param, value) in fx.parameters() {
+                if value.parse::<f32>().is_ok() {
+                    targets.push(AutomationTarget::Effect { index, param });
+                }
+            }
+        }
+        targets
+    }
+
+    /// The target whose lane the DAW shows and edits.
+    pub fn viewed_automation(&self) -> AutomationTarget {
+        let targets = self.automation_targets();
+        targets[self.automation_view % targets.len()].clone()
+    }
+
+    pub fn automation_target_name(&self, target: &AutomationTarget) -> String {
+        match target {
+            AutomationTarget::Volume => "Volume".to_string(),
+            AutomationTarget::Pan => "Pan".to_string(),
+            AutomationTarget::Mute => "Mute".to_string(),
+            AutomationTarget::Effect { index, param } => match self.fx_chain.get(*index) {
+                Some(fx) => format!("{} {} {}", index + 1, fx.name(), param),
+                None => format!("FX {} {}", index + 1, param),
+            },
+        }
+    }
+
+    pub fn lane(&self, target: &AutomationTarget) -> Option<&AutomationLane> {
+        self.automation.iter().find(|l| &l.target == target)
+    }
+
+    /// The lane for `target`, added empty if the track has none yet.
+    pub fn lane_mut(&mut self, target: &AutomationTarget) -> &mut AutomationLane {
+        let idx = match self.automation.iter().position(|l| &l.target == target) {
+            Some(idx) => idx,
+            None => {
+                self.automation.push(AutomationLane::new(target.clone()));
+                self.automation.len() - 1
+            }
+        };
+        &mut self.automation[idx]
+    }
+
+    /// The target's value when nothing automates it.
+    pub fn static_value(&self, target: &AutomationTarget) -> Option<f32> {
+        match target {
+            AutomationTarget::Volume => Some(self.volume as f32),
+            AutomationTarget::Pan => Some(self.pan),
+            AutomationTarget::Mute => Some(if self.muted { 1.0 } else { 0.0 }),
+            AutomationTarget::Effect { index, param } => self
+                .fx_chain
+                .get(*index)?
+                .parameters()
+                .into_iter()
+                .find(|(name, _)| name == param)
+                .and_then(|(_, value)| value.parse().ok()),
+        }
+    }
+
+    /// The target's value at `frame`: its lane's if it has breakpoints, otherwise
+    /// the static setting.
+    pub fn automation_value(&self, target: &AutomationTarget, frame: u64) -> Option<f32> {
+        self.lane(target)
+            .and_then(|l| l.value_at(frame))
+            .or_else(|| self.static_value(target))
+    }
+
+    /// Lanes that play back: none when automation is off, and none being written.
+    pub fn playing_lanes(&self) -> impl Iterator<Item = &AutomationLane> {
+        let reading = self.automation_mode != AutomationMode::Off;
+        self.automation.iter().filter(move |lane| {
+            reading
+                && !lane.points.is_empty()
+                && !self
+                    .automation_writes
+                    .iter()
+                    .any(|w| w.target == lane.target)
+        })
+    }
+
+    pub fn is_writing_automation(&self) -> bool {
+        !self.automation_writes.is_empty()
+    }
+
+    /// Write `value` at `frame` in touch or latch mode; `before` is the value the
+    /// change started from. Returns true when this starts a new write, which takes
+    /// the lane out of playback.
+    pub fn touch_automation(
+        &mut self,
+        target: AutomationTarget,
+        frame: u64,
+        before: f32,
+        value: f32,
+    ) -> bool {
+        if !self.automation_mode.writes() {
+            return false;
+        }
+        let curve = target.default_curve();
+        if let Some(write) = self
+            .automation_writes
+            .iter_mut()
+            .find(|w| w.target == target)
+        {
+            if frame >= write.reached {
+                write.points.push(Breakpoint {
+                    frame,
+                    value,
+                    curve: Curve::Hold,
+                });
+                write.last_change = frame;
+                write.reached = frame;
+                return false;
+            }
+        }
+        // The playhead jumped back (a loop or a locate), so the old write is finished
+        self.finish_automation_write(&target, 0);
+
+        let start_value = self
+            .lane(&target)
+            .and_then(|l| l.value_at(frame))
+            .unwrap_or(before);
+        self.automation_writes.push(AutomationWrite {
+            latch: self.automation_mode == AutomationMode::Latch,
+            start: frame,
+            points: vec![
+                Breakpoint {
+                    frame,
+                    value: start_value,
+                    curve,
+                },
+                Breakpoint {
+                    frame,
+                    value,
+                    curve: Curve::Hold,
+                },
+            ],
+            last_change: frame,
+            reached: frame,
+            target,
+        });
+        true
+    }
+
+    /// Follow the playhead with the writes in progress, ending touches that have
+    /// been let go for `release_frames` and any write the playhead jumped back from.
+    /// Returns true if a write ended and changed the lanes.
+    pub fn follow_automation_writes(
+        &mut self,
+        frame: u64,
+        release_frames: u64,
+        glide_frames: u64,
+    ) -> bool {
+        let mut finished = Vec::new();
+        for write in self.automation_writes.iter_mut() {
+            let released = !write.latch && frame >= write.last_change + release_frames;
+            if frame < write.reached || released {
+                finished.push(write.target.clone());
+            } else {
+                write.reached = frame;
+            }
+        }
+        for target in &finished {
+            self.finish_automation_write(target, glide_frames);
+        }
+        !finished.is_empty()
+    }
+
+    /// End every write in progress, e.g. when playback stops. Returns true if the
+    /// lanes changed.
+    pub fn finish_automation_writes(&mut self, glide_frames: u64) -> bool {
+        let targets: Vec<AutomationTarget> = self
+            .automation_writes
+            .iter()
+            .map(|w| w.target.clone())
+            .collect();
+        for target in &targets {
+            self.finish_automation_write(target, glide_frames);
+        }
+        !targets.is_empty()
+    }
+
+    // Put a write's breakpoints into its lane, replacing what was there from where
+    // the write started to where it ended
+    fn finish_automation_write(&mut self, target: &AutomationTarget, glide_frames: u64) {
+        let Some(idx) = self
+            .automation_writes
+            .iter()
+            .position(|w| &w.target == target)
+        else {
+            return;
+        };
+        let write = self.automation_writes.remove(idx);
+        let end = write.reached;
+        let last = write.points.last().map_or(0.0, |p| p.value);
+        // What the write started from: the lane's value, or the static setting before
+        // the touch changed it when the lane was empty
+        let start_value = write.points.first().map(|p| p.value);
+        // A touch glides back to the lane's old values; a latch leaves them alone
+        let glide_end = if write.latch { end } else { end + glide_frames };
+
+        let lane = self.lane_mut(target);
+        let resume = lane.value_at(glide_end).or(start_value);
+        lane.points
+            .retain(|p| p.frame < write.start || p.frame > glide_end);
+        for point in write.points {
+            lane.insert(point);
+        }
+        lane.insert(Breakpoint {
+            frame: end,
+            value: last,
+            curve: target.default_curve(),
+        });
+        if let (Some(resume), false) = (resume, write.latch) {
+            lane.insert(Breakpoint {
+                frame: glide_end,
+                value: resume,
+                curve: target.default_curve(),
+            });
+        }
+    }
+
+    /// Keep effect lanes on their effects after the effect at `index` was removed.
+    pub fn remove_effect_automation(&mut self, index: usize) {
+        self.automation.retain(
+            |l| !matches!(l.target, AutomationTarget::Effect { index: i, .. } if i == index),
+        );
+        for lane in self.automation.iter_mut() {
+            if let AutomationTarget::Effect { index: i, .. } = &mut lane.target {
+                if *i > index {
+                    *i -= 1;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::{ClipEnvelope, FadeShape, Ramp, Track};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+// Automatic crossfade where one clip ends exactly where the next starts
+pub const AUTO_CROSSFADE_MS: f64 = 10.0;
+
+/// A crossfade set by hand between two clips, which are named by id so it follows
+/// them through edits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Crossfade {
+    pub left: String,
+    pub right: String,
+    pub frames: u64,
+    pub shape: FadeShape,
+}
+
+/// Where two playing clips meet: `left` fades out and `right` fades in over `region`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Junction {
+    pub left: usize,
+    pub right: usize,
+    pub region: Range<u64>,
+    pub shape: FadeShape,
+    pub manual: bool,
+}
+
+/// The part of a clip's audio to stream and how loud each frame is. The window may
+/// reach past the clip's edges to play a crossfade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipPlayback {
+    pub clip: usize,
+    pub starts_at: u64, // timeline frame of the window's first frame
+    pub offset: u64,    // source frame of the window's first frame
+    pub frames: u64,
+    pub envelope: ClipEnvelope,
+}
+
+impl Track {
+    /// Every place where one playing clip runs into the next, with the crossfade
+    /// played there. Overlapping clips crossfade over the overlap and clips that butt
+    /// up against each other over `AUTO_CROSSFADE_MS`, unless a manual crossfade
+    /// says otherwise. A crossfade that needs audio from beyond a clip's edge is
+    /// shortened to the audio that exists.
+    pub fn junctions(&self, sample_rate: u32) -> Vec<Junction> {
+        let auto_frames = (AUTO_CROSSFADE_MS / 1000.0 * sample_rate as f64).round() as u64;
+        let mut playing: Vec<usize> = (0..self.clips.len())
+            .filter(|&i| !self.clips[i].muted)
+            .collect();
+        playing.sort_by_key(|&i| self.clips[i].starts_at);
+
+        let mut junctions = Vec::new();
+        for pair in playing.windows(2) {
+            let (l, r) = (pair[0], pair[1]);
+            let (left, right) = (&self.clips[l], &self.clips[r]);
+            if right.starts_at > left.end() || right.end() <= left.end() {
+                continue;
+            }
+            let manual = self
+                .crossfades
+                .iter()
+                .find(|x| x.left == left.id && x.right == right.id);
+            let (frames, shape) = match manual {
+                Some(x) => (x.frames, x.shape),
+                None if self.auto_crossfade => {
+                    let overlap = left.end() - right.starts_at;
+                    let frames = if overlap > 0 { overlap } else { auto_frames };
+                    (frames, self.crossfade_shape)
+                }
+                None => continue,
+            };
+
+            // Centred on the middle of the overlap (or the edit point)
+            let middle = (right.starts_at + left.end()) / 2;
+            let lead_room = right.starts_at.saturating_sub(right.offset);
+            let tail_room = left.end() + left.frames_after();
+            let start = middle
+                .saturating_sub(frames / 2)
+                .max(lead_room)
+                .max(left.starts_at);
+            let end = (middle.saturating_sub(frames / 2) + frames)
+                .min(tail_room)
+                .min(right.end());
+            if start >= end {
+                continue;
+            }
+            junctions.push(Junction {
+                left: l,
+                right: r,
+                region: start..end,
+                shape,
+                manual: manual.is_some(),
+            });
+        }
+        junctions
+    }
+
+    /// What to stream for each playing clip, crossfades included.
+    pub fn clip_playback(&self, sample_rate: u32) -> Vec<ClipPlayback> {
+        let junctions = self.junctions(sample_rate);
+        self.clips
+            .iter()
+            .enumerate()
+            .filter(|(_, clip)| !clip.muted)
+            .map(|(i, clip)| {
+                let mut envelope = clip.envelope();
+                let mut start = clip.starts_at;
+                let mut end = clip.starts_at + clip.playable_frames();
+                for junction in &junctions {
+                    let ramp = Ramp {
+                        start: junction.region.start,
+                        end: junction.region.end,
+                        shape: junction.shape,
+                    };
+                    if junction.right == i {
+                        envelope.crossfade_in = Some(ramp);
+                        start = start.min(ramp.start);
+                    }
+                    if junction.left == i {
+                        envelope.crossfade_out = Some(ramp);
+                        end = end.max(ramp.end);
+                    }
+                }
+                ClipPlayback {
+                    clip: i,
+                    starts_at: start,
+                    offset: clip.offset - (clip.starts_at - start),
+                    frames: end - start,
+                    envelope,
+                }
+            })
+            .collect()
+    }
+
+    /// The junction where clip `idx` starts, if it runs on from another clip.
+    pub fn junction_into(&self, idx: usize, sample_rate: u32) -> Option<Junction> {
+        self.junctions(sample_rate)
+            .into_iter()
+            .find(|j| j.right == idx)
+    }
+
+    /// Set a manual crossfade into clip `idx` from the clip before it.
+    pub fn set_crossfade(
+        &mut self,
+        idx: usize,
+        frames: u64,
+        shape: FadeShape,
+        sample_rate: u32,
+    ) -> Result<(), String> {
+        let junction = self
+            .junction_into(idx, sample_rate)
+            .ok_or("The clip does not meet another clip at its start")?;
+        let left = self.clips[junction.left].id.clone();
+        let right = self.clips[junction.right].id.clone();
+        self.crossfades
+            .retain(|x| !(x.left == left && x.right == right));
+        self.crossfades.push(Crossfade {
+            left,
+            right,
+            frames: frames.max(1),
+            shape,
+        });
+        Ok(())
+    }
+
+    /// Manual crossfades between clips that still meet; the rest are left over from
+    /// clips that were moved or deleted.
+    pub fn live_crossfades(&self, sample_rate: u32) -> Vec<Crossfade> {
+        let junctions = self.junctions(sample_rate);
+        self.crossfades
+            .iter()
+            .filter(|x| {
+                junctions
+                    .iter()
+                    .any(|j| self.clips[j.left].id == x.left && self.clips[j.right].id == x.right)
+            })
+            .cloned()
+            .collect()
+    }
+}
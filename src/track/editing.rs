@@ -0,0 +1,166 @@
+use super::{generate_clip_id, Clip, Fade, Track};
+use std::ops::Range;
+
+// Trimming never shrinks a clip below this
+const MIN_CLIP_FRAMES: u64 = 1;
+
+impl Track {
+    /// Silence `range` on the timeline by cutting it out of every playing clip:
+    /// clips inside it are removed, clips overlapping an edge are trimmed and clips
+    /// spanning it are split in two. Muted clips (alternate takes) are left alone.
+    /// The audio itself is untouched; clips only change which section they play.
+    pub fn clear_range(&mut self, range: Range<u64>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        let mut kept = Vec::with_capacity(self.clips.len());
+        for clip in self.clips.drain(..) {
+            if clip.muted || clip.end() <= range.start || clip.starts_at >= range.end {
+                kept.push(clip);
+                continue;
+            }
+
+            // The part before the range keeps the clip's identity
+            let tail = (clip.end() > range.end).then(|| {
+                let skipped = range.end - clip.starts_at;
+                let mut tail = clip.clone();
+                tail.id = generate_clip_id(&self.name);
+                tail.starts_at = range.end;
+                tail.offset = clip.offset + skipped;
+                tail.length = clip.length - skipped;
+                tail.fade_in = Fade::default();
+                tail
+            });
+            if clip.starts_at < range.start {
+                let mut head = clip;
+                head.length = range.start - head.starts_at;
+                head.fade_out = Fade::default();
+                kept.push(head);
+            }
+            // The part after the range plays on from where the range ends
+            kept.extend(tail);
+        }
+        self.clips = kept;
+    }
+
+    /// Keep a new recording. When it overlaps audio already on the track it becomes a
+    /// take instead of being mixed with that audio: whatever played there is kept as
+    /// a take too, and with `play` the new take is comped in over its whole range.
+    pub fn add_recording(&mut self, clip: Clip, play: bool) {
+        let range = clip.starts_at..clip.end();
+        let overlaps = |c: &Clip| c.starts_at < range.end && c.end() > range.start;
+        let overlapping =
+            self.clips.iter().any(|c| !c.muted && overlaps(c)) || self.takes.iter().any(overlaps);
+        if !overlapping && play {
+            self.clips.push(clip);
+            return;
+        }
+
+        // Plain clips about to be covered become takes so they can be comped back in
+        for existing in self.clips.iter_mut() {
+            if existing.muted || existing.take.is_some() || !overlaps(existing) {
+                continue;
+            }
+            self.takes.push(existing.clone());
+            existing.take = Some(existing.id.clone());
+        }
+
+        let take = self.takes.len();
+        self.takes.push(clip);
+        if play {
+            self.selected_take = take;
+            let _ = self.comp_take(take, range);
+        }
+    }
+
+    /// Make take `take` play over `range` (clamped to the take), replacing whatever
+    /// played there before.
+    pub fn comp_take(&mut self, take: usize, range: Range<u64>) -> Result<(), String> {
+        let source = self.takes.get(take).ok_or("Take index out of bounds")?;
+        let start = range.start.max(source.starts_at);
+        let end = range.end.min(source.end());
+        if start >= end {
+            return Err("The take does not cover that region".to_string());
+        }
+
+        let mut section = source.clone();
+        section.id = generate_clip_id(&self.name);
+        section.starts_at = start;
+        section.offset = source.offset + (start - source.starts_at);
+        section.length = end - start;
+        section.muted = false;
+        section.fade_in = Fade::default();
+        section.fade_out = Fade::default();
+        section.take = Some(source.id.clone());
+
+        self.clear_range(start..end);
+        self.clips.push(section);
+        Ok(())
+    }
+
+    /// Remove a take lane along with every section of it that plays.
+    pub fn remove_take(&mut self, take: usize) -> Result<(), String> {
+        if take >= self.takes.len() {
+            return Err("Take index out of bounds".to_string());
+        }
+        let removed = self.takes.remove(take);
+        self.clips
+            .retain(|clip| clip.take.as_deref() != Some(removed.id.as_str()));
+        self.selected_take = self.selected_take.min(self.takes.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Cut clip `idx` in two at timeline frame `at`. The first part keeps the clip's
+    /// id and fade-in, the second part (inserted right after it) gets the fade-out.
+    /// Returns the index of the second part.
+    pub fn split_clip(&mut self, idx: usize, at: u64) -> Result<usize, String> {
+        let tail_id = generate_clip_id(&self.name);
+        let clip = self.clips.get_mut(idx).ok_or("Clip index out of bounds")?;
+        if at <= clip.starts_at || at >= clip.end() {
+            return Err("The split point is not inside the clip".to_string());
+        }
+        let head_len = at - clip.starts_at;
+        let mut tail = clip.clone();
+        tail.id = tail_id;
+        tail.starts_at = at;
+        tail.offset = clip.offset + head_len;
+        tail.length = clip.length - head_len;
+        tail.fade_in = Fade::default();
+        clip.length = head_len;
+        clip.fade_out = Fade::default();
+        self.clips.insert(idx + 1, tail);
+        Ok(idx + 1)
+    }
+
+    /// Move the start of clip `idx` by `delta` frames, revealing or hiding audio
+    /// while the rest of the clip stays where it is on the timeline.
+    pub fn trim_clip_start(&mut self, idx: usize, delta: i64) -> Result<(), String> {
+        let clip = self.clips.get_mut(idx).ok_or("Clip index out of bounds")?;
+        let delta = delta
+            .max(-(clip.offset.min(clip.starts_at) as i64))
+            .min(clip.length as i64 - MIN_CLIP_FRAMES as i64);
+        clip.starts_at = clip.starts_at.saturating_add_signed(delta);
+        clip.offset = clip.offset.saturating_add_signed(delta);
+        clip.length = clip.length.saturating_add_signed(-delta);
+        Ok(())
+    }
+
+    /// Move the end of clip `idx` by `delta` frames, up to the end of its audio.
+    pub fn trim_clip_end(&mut self, idx: usize, delta: i64) -> Result<(), String> {
+        let clip = self.clips.get_mut(idx).ok_or("Clip index out of bounds")?;
+        let available = (clip.wav_data.frame_count() as u64).saturating_sub(clip.offset);
+        clip.length = clip
+            .length
+            .saturating_add_signed(delta)
+            .clamp(MIN_CLIP_FRAMES.min(available), available);
+        Ok(())
+    }
+
+    /// Slide clip `idx` along the timeline by `delta` frames, stopping at the start.
+    pub fn move_clip(&mut self, idx: usize, delta: i64) -> Result<u64, String> {
+        let clip = self.clips.get_mut(idx).ok_or("Clip index out of bounds")?;
+        clip.starts_at = clip.starts_at.saturating_add_signed(delta);
+        Ok(clip.starts_at)
+    }
+}
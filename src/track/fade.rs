@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI};
+use strum::{EnumIter, IntoEnumIterator};
+
+/// Curve of a fade or crossfade, from silence to full level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, EnumIter)]
+pub enum FadeShape {
+    #[default]
+    Linear,
+    /// Constant power when crossfading uncorrelated material
+    EqualPower,
+    /// Slow start and end, fast in the middle
+    SCurve,
+}
+
+impl FadeShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FadeShape::Linear => "Linear",
+            FadeShape::EqualPower => "Equal Power",
+            FadeShape::SCurve => "S-Curve",
+        }
+    }
+
+    /// The shape after this one, wrapping around.
+    pub fn next(&self) -> FadeShape {
+        let shapes: Vec<FadeShape> = FadeShape::iter().collect();
+        let idx = shapes.iter().position(|s| s == self).unwrap_or(0);
+        shapes[(idx + 1) % shapes.len()]
+    }
+
+    /// Level at `t` (0.0 to 1.0) through a fade in; a fade out is `gain(1.0 - t)`.
+    pub fn gain(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeShape::Linear => t,
+            FadeShape::EqualPower => (t * FRAC_PI_2).sin(),
+            FadeShape::SCurve => (1.0 - (t * PI).cos()) * 0.5,
+        }
+    }
+}
+
+/// A fade at one edge of a clip.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Fade {
+    #[serde(default)]
+    pub frames: u64,
+    #[serde(default)]
+    pub shape: FadeShape,
+}
+
+/// A crossfade ramp over timeline frames `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ramp {
+    pub start: u64,
+    pub end: u64,
+    pub shape: FadeShape,
+}
+
+/// Level of every frame of a clip: its gain, fades and crossfades. Cheap to copy so
+/// the real-time mixer can carry one per clip.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipEnvelope {
+    pub gain: f32, // linear
+    pub fade_in: Fade,
+    pub fade_out: Fade,
+    pub start: u64, // timeline frame the clip starts at
+    pub length: u64,
+    // Crossfades with the clips before and after; they may reach past the clip's edges
+    pub crossfade_in: Option<Ramp>,
+    pub crossfade_out: Option<Ramp>,
+}
+
+impl ClipEnvelope {
+    /// Level at timeline frame `frame`.
+    pub fn at(&self, frame: u64) -> f32 {
+        let mut level = self.gain;
+        if let Some(ramp) = self.crossfade_in {
+            if frame < ramp.start {
+                return 0.0;
+            }
+            if frame < ramp.end {
+                let t = (frame - ramp.start) as f32 / (ramp.end - ramp.start) as f32;
+                level *= ramp.shape.gain(t);
+            }
+        }
+        if let Some(ramp) = self.crossfade_out {
+            if frame >= ramp.end {
+                return 0.0;
+            }
+            if frame >= ramp.start {
+                let t = (ramp.end - 1 - frame) as f32 / (ramp.end - ramp.start) as f32;
+                level *= ramp.shape.gain(t);
+            }
+        }
+        // The clip's own fades only cover the clip, not audio played for a crossfade
+        if frame >= self.start && frame < self.start + self.length {
+            level *= self.fade_level(frame - self.start);
+        }
+        level
+    }
+
+    fn fade_level(&self, frame: u64) -> f32 {
+        let mut level = 1.0;
+        // Fades longer than the clip are squeezed to fit
+        let fade_in = self.fade_in.frames.min(self.length);
+        let fade_out = self.fade_out.frames.min(self.length);
+        if frame < fade_in {
+            level *= self.fade_in.shape.gain(frame as f32 / fade_in as f32);
+        }
+        let remaining = self.length.saturating_sub(frame);
+        if remaining <= fade_out && fade_out > 0 {
+            level *= self
+                .fade_out
+                .shape
+                .gain(remaining.saturating_sub(1) as f32 / fade_out as f32);
+        }
+        level
+    }
+}
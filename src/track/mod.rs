@@ -1,35 +1,116 @@
+mod automation;
+mod crossfade;
+mod editing;
+mod fade;
 mod monitoring;
 mod playback;
 mod recording;
 
+pub use automation::{
+    AutomationLane, AutomationMode, AutomationTarget, AutomationWrite, Breakpoint, Curve,
+    TOUCH_GLIDE_SECONDS, TOUCH_RELEASE_SECONDS,
+};
+pub use crossfade::{ClipPlayback, Crossfade, Junction, AUTO_CROSSFADE_MS};
+pub use fade::{ClipEnvelope, Fade, FadeShape, Ramp};
+pub use recording::RecordingPlacement;
+
+use crate::bus::AuxSend;
+use crate::channels;
 use crate::effects::EffectInstance;
+use crate::meter::Meter;
 use crate::wav::WavFile;
 use ringbuf::HeapProd;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     Arc, RwLock,
 };
 use std::time::{SystemTime, UNIX_EPOCH};
 
-pub const LATENCY_MS: f32 = 10.0;
-
 // Fixed chunk size for recording waveform: each peak point = this many raw samples.
 // ~20ms at 48kHz → stable left-to-right waveform growth during recording.
 pub const RECORDING_WAVEFORM_CHUNK_SIZE: usize = 960;
 const WAVEFORM_MAX_POINTS: usize = 500;
 
+#[derive(Clone)]
 pub struct Clip {
     pub id: String,
-    pub wav_data: WavFile,
+    // Shared so the real-time mixer can stream it without copying
+    pub wav_data: Arc<WavFile>,
     pub starts_at: u64, // frame position on the timeline
+    // The section of `wav_data` that plays, in source frames
+    pub offset: u64,
+    pub length: u64,
+    // Muted clips are kept but not played
+    pub muted: bool,
+    // Id of the take this clip plays a section of, when it was comped from a take lane
+    pub take: Option<String>,
+    pub gain_db: f32,
+    pub fade_in: Fade,
+    pub fade_out: Fade,
 }
 
+impl Clip {
+    /// A clip playing all of `wav_data` from `starts_at`.
+    pub fn new(id: String, wav_data: Arc<WavFile>, starts_at: u64) -> Self {
+        let length = wav_data.frame_count() as u64;
+        Clip {
+            id,
+            wav_data,
+            starts_at,
+            offset: 0,
+            length,
+            muted: false,
+            take: None,
+            gain_db: 0.0,
+            fade_in: Fade::default(),
+            fade_out: Fade::default(),
+        }
+    }
+
+    /// Timeline frame just past the clip's last frame.
+    pub fn end(&self) -> u64 {
+        self.starts_at + self.length
+    }
+
+    /// Frames that can actually play: `length`, cut short if the source is shorter.
+    pub fn playable_frames(&self) -> u64 {
+        self.length
+            .min((self.wav_data.frame_count() as u64).saturating_sub(self.offset))
+    }
+
+    /// Source frames past the end of the clip, available to a crossfade.
+    pub fn frames_after(&self) -> u64 {
+        (self.wav_data.frame_count() as u64).saturating_sub(self.offset + self.length)
+    }
+
+    /// The clip's level on its own, without crossfades.
+    pub fn envelope(&self) -> ClipEnvelope {
+        ClipEnvelope {
+            gain: 10f32.powf(self.gain_db / 20.0),
+            fade_in: self.fade_in,
+            fade_out: self.fade_out,
+            start: self.starts_at,
+            length: self.playable_frames(),
+            crossfade_in: None,
+            crossfade_out: None,
+        }
+    }
+}
+
+/// A clip id unique within the session: crossfades and the undo history find clips
+/// by id.
 pub fn generate_clip_id(track_name: &str) -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
     let ts = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
-    format!("{}-{}", track_name, ts)
+    format!(
+        "{}-{}-{}",
+        track_name,
+        ts,
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -76,18 +157,46 @@ pub struct Track {
     pub monitoring: bool,
     pub state: TrackState,
 
-    // FX chain
+    // FX chain and aux sends
     pub fx_chain: Vec<EffectInstance>,
+    pub sends: Vec<AuxSend>,
 
     // Playback data (recorded or loaded)
     pub clips: Vec<Clip>,
+    // Take lanes: overlapping recordings kept whole; `clips` plays the comped sections
+    pub takes: Vec<Clip>,
     pub recording_start_position: u64,
+    // Crossfades where clips meet: manual ones by clip pair, automatic ones otherwise
+    pub crossfades: Vec<Crossfade>,
+    pub auto_crossfade: bool,
+    pub crossfade_shape: FadeShape, // shape of automatic crossfades
 
     // Playback state
     pub volume: f64,
+    pub pan: f32, // -1.0 (left) .. 1.0 (right)
     pub muted: bool,
+    pub solo: bool,
+    pub solo_safe: bool, // keeps playing while other tracks are soloed
     pub input_channel: Option<u16>,
 
+    // Automation lanes and how they play; writes in progress during touch/latch playback
+    pub automation: Vec<AutomationLane>,
+    pub automation_mode: AutomationMode,
+    automation_writes: Vec<AutomationWrite>,
+
+    // Group hierarchy: group tracks have no clips and sum their children
+    pub is_group: bool,
+    pub parent: Option<usize>, // index of the enclosing group track
+
+    // Level meters: the live input while armed, the post-fader output during playback
+    pub input_meter: Arc<Meter>,
+    pub output_meter: Arc<Meter>,
+
+    // Display state
+    pub collapsed: bool,
+    pub selected_take: usize,   // take lane the comping keys act on
+    pub automation_view: usize, // index into automation_targets() of the lane shown
+
     // Recording ring buffer producer (lock-free, written by audio callback)
     recording_producer: Option<HeapProd<f32>>,
     recording_channels: Option<u16>,
@@ -109,11 +218,29 @@ impl Default for Track {
             monitoring: false,
             state: TrackState::Idle,
             fx_chain: vec![],
+            sends: Vec::new(),
             clips: Vec::new(),
+            takes: Vec::new(),
             recording_start_position: 0,
+            crossfades: Vec::new(),
+            auto_crossfade: true,
+            crossfade_shape: FadeShape::EqualPower,
             volume: 1.0,
+            pan: 0.0,
             muted: false,
+            solo: false,
+            solo_safe: false,
             input_channel: None,
+            automation: Vec::new(),
+            automation_mode: AutomationMode::default(),
+            automation_writes: Vec::new(),
+            is_group: false,
+            parent: None,
+            input_meter: Arc::new(Meter::new()),
+            output_meter: Arc::new(Meter::new()),
+            collapsed: false,
+            selected_take: 0,
+            automation_view: 0,
             recording_producer: None,
             recording_channels: None,
             recording_sample_rate: None,
@@ -148,37 +275,36 @@ impl Track {
 
     /// Frame position of the end of the furthest clip.
     pub fn clips_end(&self) -> u64 {
-        self.clips
-            .iter()
-            .map(|clip| clip.starts_at + clip.wav_data.frame_count() as u64)
-            .max()
-            .unwrap_or(0)
+        self.clips.iter().map(Clip::end).max().unwrap_or(0)
     }
 
-    /// Mix all clips into a single mono buffer starting from `from_frame`.
-    /// Multi-channel clips are downmixed to mono by averaging channels per frame.
-    pub fn mix_clips(&self, from_frame: u64) -> (Vec<f32>, u64) {
+    /// Mix all clips into an interleaved buffer with `channels` channels, starting from `from_frame`.
+    /// Clips whose channel count differs are remapped per frame (mono clips land centred).
+    pub fn mix_clips(&self, from_frame: u64, sample_rate: u32, channels: u16) -> (Vec<f32>, u64) {
         let end_frame = self.clips_end();
-        if from_frame >= end_frame {
+        if from_frame >= end_frame || channels == 0 {
             return (Vec::new(), end_frame);
         }
 
+        let out_ch = channels as usize;
         let buffer_len = (end_frame - from_frame) as usize;
-        let mut mixed = vec![0.0f32; buffer_len];
+        let mut mixed = vec![0.0f32; buffer_len * out_ch];
 
-        for clip in &self.clips {
+        for playback in self.clip_playback(sample_rate) {
+            let clip = &self.clips[playback.clip];
             let clip_samples = clip.wav_data.to_f32_samples();
-            let channels = clip.wav_data.header.num_channels as usize;
-            let frame_count = clip.wav_data.frame_count();
+            let clip_ch = clip.wav_data.header.num_channels as usize;
 
-            for frame in 0..frame_count {
-                let absolute_pos = clip.starts_at + frame as u64;
+            for frame in 0..playback.frames {
+                let absolute_pos = playback.starts_at + frame;
                 if absolute_pos >= from_frame && absolute_pos < end_frame {
-                    let buf_idx = (absolute_pos - from_frame) as usize;
-                    // Downmix: average all channels for this frame
-                    let start = frame * channels;
-                    let sum: f32 = clip_samples[start..start + channels].iter().sum();
-                    mixed[buf_idx] += sum / channels as f32;
+                    let buf_idx = (absolute_pos - from_frame) as usize * out_ch;
+                    let start = (playback.offset + frame) as usize * clip_ch;
+                    channels::mix_frame_into(
+                        &clip_samples[start..start + clip_ch],
+                        &mut mixed[buf_idx..buf_idx + out_ch],
+                        playback.envelope.at(absolute_pos),
+                    );
                 }
             }
         }
@@ -188,7 +314,7 @@ impl Track {
 
     pub fn cleanup(&mut self) {
         if self.state == TrackState::Recording {
-            let _ = self.stop_recording();
+            let _ = self.stop_recording(RecordingPlacement::default());
         }
 
         if self.is_armed() {
@@ -215,4 +341,3 @@ fn downsample_bipolar(samples: &[f32], chunk_size: usize, exact_chunks: bool) ->
         })
         .collect()
 }
-
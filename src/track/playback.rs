@@ -1,27 +1,41 @@
 use super::Track;
+use crate::channels;
 
 impl Track {
-    /// Render track audio: mix clips -> apply FX chain -> apply volume.
+    /// Render track audio: mix clips -> apply FX chain -> apply volume -> apply pan.
+    /// The result is interleaved with `channels` channels.
     /// Returns empty Vec for muted tracks or tracks with no clips.
-    pub fn render(&self, from_sample: u64, sample_rate: u32) -> Vec<f32> {
+    pub fn render(&self, from_sample: u64, sample_rate: u32, channels: u16) -> Vec<f32> {
         if self.muted || self.clips.is_empty() {
             return Vec::new();
         }
-        let (buffer, _) = self.mix_clips(from_sample);
-        let mut buffer = self.apply_fx(buffer, sample_rate);
+        let mut buffer = self.render_pre_fader(from_sample, sample_rate, channels);
         self.apply_volume(&mut buffer);
+        channels::apply_balance(&mut buffer, channels, self.pan);
         buffer
     }
 
+    /// Render clips through the FX chain only, before volume and pan.
+    pub fn render_pre_fader(&self, from_sample: u64, sample_rate: u32, channels: u16) -> Vec<f32> {
+        let (buffer, _) = self.mix_clips(from_sample, sample_rate, channels);
+        self.apply_fx(buffer, sample_rate, channels)
+    }
+
+    /// Total FX tail of the chain in frames (tails add up in series).
+    pub fn fx_tail_frames(&self, sample_rate: u32) -> u64 {
+        let tail: f32 = self.fx_chain.iter().map(|fx| fx.tail_seconds()).sum();
+        (tail * sample_rate as f32) as u64
+    }
+
     /// Apply the FX chain to a buffer.
     /// Some effects (like Delay) may extend the buffer to include tails.
-    fn apply_fx(&self, mut buffer: Vec<f32>, sample_rate: u32) -> Vec<f32> {
+    fn apply_fx(&self, mut buffer: Vec<f32>, sample_rate: u32, channels: u16) -> Vec<f32> {
         if self.fx_chain.is_empty() {
             return buffer;
         }
 
         for effect in &self.fx_chain {
-            let _ = effect.apply(&mut buffer, sample_rate);
+            let _ = effect.apply_interleaved(&mut buffer, sample_rate, channels);
         }
 
         buffer
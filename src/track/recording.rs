@@ -7,11 +7,25 @@ use ringbuf::{
     traits::{Consumer, Split},
     HeapCons, HeapProd, HeapRb,
 };
+use std::ops::Range;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc, RwLock,
 };
 
+/// Where a finished recording lands on the timeline.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingPlacement {
+    /// Round trip through the audio interface; the recording is moved this much earlier
+    pub latency_frames: u64,
+    /// Frames captured during a count-in, before playback started moving
+    pub count_in_frames: u64,
+    /// Loop region playback cycled through while recording
+    pub cycle: Option<Range<u64>>,
+    /// Only audio inside this region is kept
+    pub punch: Option<Range<u64>>,
+}
+
 impl Track {
     /// Prepare this track for recording: set up buffers and waveform thread.
     /// Does NOT open any audio device or stream — the Session owns the shared input stream.
@@ -123,7 +137,17 @@ impl Track {
         self.recording_producer.take()
     }
 
-    pub fn stop_recording(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Finish recording and keep what was captured as a new clip. Whatever was
+    /// captured during a count-in is dropped and the clip is moved `latency_frames`
+    /// earlier to undo the round trip through the audio interface; audio that would
+    /// land before the start of the timeline is dropped too.
+    /// When playback cycled through `cycle`, every pass becomes its own take and the
+    /// last complete pass plays. With a `punch` range only the audio inside it is kept.
+    /// Recordings that overlap existing audio replace it and join the take lanes.
+    pub fn stop_recording(
+        &mut self,
+        placement: RecordingPlacement,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         if self.state != TrackState::Recording {
             return Err("Not currently recording".into());
         }
@@ -132,27 +156,59 @@ impl Track {
         self.waveform_thread.stop_waveform();
 
         // Join the waveform thread to receive all accumulated samples
-        let samples = if let Some(handle) = self.waveform_thread.waveform.take() {
+        let mut samples = if let Some(handle) = self.waveform_thread.waveform.take() {
             handle.join().unwrap_or_default()
         } else {
             Vec::new()
         };
 
+        let RecordingPlacement {
+            latency_frames,
+            count_in_frames,
+            cycle,
+            punch,
+        } = placement;
+        let channels = self.recording_channels.unwrap_or(1);
+        let early_frames =
+            count_in_frames + latency_frames.saturating_sub(self.recording_start_position);
+        let trimmed = (early_frames as usize * channels as usize).min(samples.len());
+        samples.drain(..trimmed);
+
         if !samples.is_empty() {
-            let channels = self.recording_channels.unwrap_or(1);
             let sample_rate = self.recording_sample_rate.unwrap_or(48000);
-
-            let mut wav = WavFile::new(sample_rate, channels);
-            wav.from_f32_samples(&samples);
-
-            self.clips.push(Clip {
-                id: generate_clip_id(&self.name),
-                wav_data: wav,
-                starts_at: self.recording_start_position,
-            });
+            let starts_at = self.recording_start_position.saturating_sub(latency_frames);
+            let id = generate_clip_id(&self.name);
+
+            // The mixer only cycles when recording started before the loop end
+            let cycle = cycle.filter(|c| c.start < c.end && self.recording_start_position < c.end);
+            let frames = samples.len() / channels as usize;
+            let passes = match (cycle, punch) {
+                (Some(cycle), _) => loop_passes(frames, starts_at, cycle),
+                (None, Some(punch)) => punch_window(frames, starts_at, punch)
+                    .map(|(offset, len, start)| vec![(offset, len, start, true)])
+                    .unwrap_or_default(),
+                (None, None) => vec![(0, frames, starts_at, true)],
+            };
+            let playing = passes
+                .iter()
+                .rposition(|&(_, _, _, complete)| complete)
+                .unwrap_or(passes.len().saturating_sub(1));
+
+            for (take, &(offset, frames, pass_start, _)) in passes.iter().enumerate() {
+                let ch = channels as usize;
+                let mut wav = WavFile::new(sample_rate, channels);
+                wav.from_f32_samples(&samples[offset * ch..(offset + frames) * ch]);
+                let take_id = if passes.len() > 1 {
+                    format!("{}-take{}", id, take + 1)
+                } else {
+                    id.clone()
+                };
+                let clip = Clip::new(take_id, Arc::new(wav), pass_start);
+                self.add_recording(clip, take == playing);
+            }
         }
 
-        self.cache_waveform();
+        self.cache_waveform(self.recording_sample_rate.unwrap_or(48000));
 
         self.recording_channels = None;
         self.recording_sample_rate = None;
@@ -162,13 +218,14 @@ impl Track {
 
     /// Recompute the waveform cache from all clips.
     /// Called once after recording stops, or after loading clips from a project.
-    pub(crate) fn cache_waveform(&mut self) {
+    pub(crate) fn cache_waveform(&mut self, sample_rate: u32) {
         if self.clips.is_empty() {
             self.clips_waveform.clear();
             return;
         }
 
-        let (mixed, _) = self.mix_clips(0);
+        // Waveform display is mono: let the mixer downmix every clip
+        let (mixed, _) = self.mix_clips(0, sample_rate, 1);
         if mixed.is_empty() {
             self.clips_waveform.clear();
             return;
@@ -197,3 +254,28 @@ impl Track {
         None
     }
 }
+
+/// Split `frames` of audio recorded from `starts_at` while playback cycled through
+/// `cycle` into passes: (first frame, frame count, timeline start, reached the loop end).
+/// The first pass runs from the recording start to the loop end, the rest from the
+/// loop start.
+fn loop_passes(frames: usize, starts_at: u64, cycle: Range<u64>) -> Vec<(usize, usize, u64, bool)> {
+    let first_len = cycle.end.saturating_sub(starts_at) as usize;
+    let loop_len = (cycle.end - cycle.start) as usize;
+    let mut passes = vec![(0, first_len.min(frames), starts_at, frames >= first_len)];
+    let mut offset = first_len;
+    while offset < frames {
+        let len = loop_len.min(frames - offset);
+        passes.push((offset, len, cycle.start, len == loop_len));
+        offset += len;
+    }
+    passes
+}
+
+/// The part of `frames` of audio recorded from `starts_at` that falls inside `punch`:
+/// (first frame, frame count, timeline start), or None if none of it does.
+fn punch_window(frames: usize, starts_at: u64, punch: Range<u64>) -> Option<(usize, usize, u64)> {
+    let start = punch.start.max(starts_at);
+    let end = punch.end.min(starts_at + frames as u64);
+    (start < end).then(|| ((start - starts_at) as usize, (end - start) as usize, start))
+}
@@ -1,14 +1,67 @@
 use super::screen_trait::ScreenTrait;
 use super::{App, Screen};
 use crate::audio_engine::AudioEngine;
+use crate::device::DeviceCapabilities;
+use crate::latency;
 use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
     Frame,
 };
 
+mod layout_config {
+    pub const OFFSET_STEP_MS: f32 = 0.5;
+    pub const MAX_OFFSET_MS: f32 = 500.0;
+    pub const LATENCY_TITLE: &str =
+        "Recording Latency (c: Calibrate via loopback | x: Clear calibration | +/-: Offset)";
+    pub const CAPABILITIES_TITLE: &str = "Supports";
+    // Share of each device row given to the list, the rest shows what the device supports
+    pub const DEVICE_LIST_PERCENT: u16 = 60;
+}
+
+/// What the highlighted device supports, one line per property.
+fn capabilities_text(capabilities: Option<Result<DeviceCapabilities, String>>) -> String {
+    let capabilities = match capabilities {
+        Some(Ok(capabilities)) => capabilities,
+        Some(Err(e)) => return format!("Unavailable: {}", e),
+        None => return "No device".to_string(),
+    };
+    let join = |items: Vec<String>| {
+        if items.is_empty() {
+            "-".to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+    let rates = join(
+        capabilities
+            .sample_rates
+            .iter()
+            .map(|&rate| format!("{}", rate as f64 / 1000.0))
+            .collect(),
+    );
+    let channels = join(
+        capabilities
+            .channels
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+    );
+    let buffer = match capabilities.buffer_frames {
+        Some(frames) => format!("{} - {} frames", frames.start(), frames.end()),
+        None => "set by the host".to_string(),
+    };
+    format!(
+        "Sample rates: {} kHz\nChannels: {}\nFormats: {}\nBuffer: {}",
+        rates,
+        channels,
+        join(capabilities.sample_formats),
+        buffer
+    )
+}
+
 pub struct AudioPreferencesScreen;
 
 fn get_prefs(app: &App) -> (usize, usize, usize) {
@@ -28,13 +81,24 @@ fn set_prefs(app: &mut App, panel: usize, input: usize, output: usize) {
     };
 }
 
+/// Report a finished latency calibration. Called every tick so it finishes from any screen.
+pub fn check_calibration(app: &mut App) {
+    if let Some(result) = app.session.check_calibration() {
+        app.status = match result {
+            Ok(ms) => format!("Measured round trip: {:.1} ms", ms),
+            Err(e) => format!("Calibration failed: {}", e),
+        };
+    }
+}
+
 impl ScreenTrait for AudioPreferencesScreen {
     fn render(&self, f: &mut Frame, app: &App, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Percentage(45),
-                Constraint::Percentage(45),
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+                Constraint::Length(3),
                 Constraint::Min(3),
             ])
             .split(area);
@@ -42,7 +106,7 @@ impl ScreenTrait for AudioPreferencesScreen {
         let (selected_panel, input_selected, output_selected) = get_prefs(app);
 
         let ctx = AudioEngine::global();
-        let ctx = ctx.lock().unwrap();
+        let mut ctx = ctx.lock().unwrap();
 
         let input_items: Vec<ListItem> = ctx
             .input_devices()
@@ -108,8 +172,67 @@ impl ScreenTrait for AudioPreferencesScreen {
                 .title("Output Devices (Enter to select)"),
         );
 
-        f.render_widget(input_list, chunks[0]);
-        f.render_widget(output_list, chunks[1]);
+        let device_row = |area: Rect| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(layout_config::DEVICE_LIST_PERCENT),
+                    Constraint::Percentage(100 - layout_config::DEVICE_LIST_PERCENT),
+                ])
+                .split(area)
+        };
+        let input_row = device_row(chunks[0]);
+        let output_row = device_row(chunks[1]);
+        f.render_widget(input_list, input_row[0]);
+        f.render_widget(output_list, output_row[0]);
+
+        // Each list shows what its highlighted device supports
+        let input_capabilities = ctx
+            .input_devices()
+            .get(input_selected)
+            .cloned()
+            .map(|name| ctx.input_capabilities(&name));
+        let output_capabilities = ctx
+            .output_devices()
+            .get(output_selected)
+            .cloned()
+            .map(|name| ctx.output_capabilities(&name));
+        for (capabilities, area) in [
+            (input_capabilities, input_row[1]),
+            (output_capabilities, output_row[1]),
+        ] {
+            let panel = Paragraph::new(capabilities_text(capabilities))
+                .wrap(Wrap { trim: true })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(layout_config::CAPABILITIES_TITLE),
+                );
+            f.render_widget(panel, area);
+        }
+
+        let sample_rate = app.session.sample_rate;
+        let settings = ctx.latency();
+        let calibrated = match settings.calibrated_ms {
+            _ if app.session.is_calibrating() => "measuring...".to_string(),
+            Some(ms) => format!("{:.1} ms", ms),
+            None => "none".to_string(),
+        };
+        let reported = latency::frames_to_ms(app.session.reported_latency_frames(), sample_rate);
+        let applied = latency::frames_to_ms(
+            settings.compensation_frames(app.session.reported_latency_frames(), sample_rate),
+            sample_rate,
+        );
+        let latency_info = Paragraph::new(format!(
+            "Reported: {:.1} ms | Calibrated: {} | Offset: {:+.1} ms | Applied: {:.1} ms",
+            reported, calibrated, settings.offset_ms, applied
+        ))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(layout_config::LATENCY_TITLE),
+        );
+        f.render_widget(latency_info, chunks[2]);
 
         let refresh_style = if selected_panel == 2 {
             Style::default().fg(Color::Black).bg(Color::Green)
@@ -122,7 +245,7 @@ impl ScreenTrait for AudioPreferencesScreen {
             .title("Press 'r' to Refresh Devices | Esc to go back")
             .style(refresh_style);
 
-        f.render_widget(refresh_button, chunks[2]);
+        f.render_widget(refresh_button, chunks[3]);
     }
 
     fn handle_input(
@@ -130,6 +253,15 @@ impl ScreenTrait for AudioPreferencesScreen {
         app: &mut App,
         key: KeyCode,
     ) -> Result<bool, Box<dyn std::error::Error>> {
+        // Calibration opens the devices itself, so it starts without the engine locked
+        if key == KeyCode::Char('c') {
+            app.status = match app.session.start_calibration() {
+                Ok(()) => "Calibrating: listening for the clicks...".to_string(),
+                Err(e) => format!("Calibration failed: {}", e),
+            };
+            return Ok(false);
+        }
+
         let engine = AudioEngine::global();
         let mut engine = engine.lock().unwrap();
 
@@ -182,6 +314,24 @@ impl ScreenTrait for AudioPreferencesScreen {
             KeyCode::Char('r') | KeyCode::Char('R') => {
                 engine.refresh_devices();
             }
+            KeyCode::Char('x') => {
+                let mut settings = engine.latency();
+                settings.calibrated_ms = None;
+                engine.set_latency(settings);
+                app.status = "Latency calibration cleared".to_string();
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char('-') => {
+                let step = if key == KeyCode::Char('-') {
+                    -layout_config::OFFSET_STEP_MS
+                } else {
+                    layout_config::OFFSET_STEP_MS
+                };
+                let mut settings = engine.latency();
+                settings.offset_ms = (settings.offset_ms + step)
+                    .clamp(-layout_config::MAX_OFFSET_MS, layout_config::MAX_OFFSET_MS);
+                engine.set_latency(settings);
+                app.status = format!("Latency offset: {:+.1} ms", settings.offset_ms);
+            }
             KeyCode::Esc => {
                 app.screen = Screen::MainMenu { selected: 0 };
             }
@@ -0,0 +1,276 @@
+use super::screen_trait::ScreenTrait;
+use super::{App, Screen};
+use crate::session::FxTarget;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+mod layout_config {
+    pub const LEVEL_STEP: f32 = 0.1;
+    pub const MAX_LEVEL: f32 = 2.0;
+    pub const INSTRUCTIONS: &str =
+        "Left/Right: Bus | Up/Down: Row | n: New | d: Del Bus | +/-: Level | p: Pre/Post | m: Mute | Bksp: Del Send | Enter/E: FX | Esc: Back";
+}
+
+pub struct AuxBusesScreen;
+
+// Row 0 is the bus return itself, rows 1.. are the sends from each track
+fn get_state(app: &App) -> (usize, usize) {
+    match app.screen {
+        Screen::AuxBuses {
+            selected_bus,
+            selected_row,
+        } => (selected_bus, selected_row),
+        _ => (0, 0),
+    }
+}
+
+fn set_state(app: &mut App, selected_bus: usize, selected_row: usize) {
+    app.screen = Screen::AuxBuses {
+        selected_bus,
+        selected_row,
+    };
+}
+
+fn step_level(level: f32, delta: f32) -> f32 {
+    ((level + delta) * 10.0)
+        .round()
+        .clamp(0.0, layout_config::MAX_LEVEL * 10.0)
+        / 10.0
+}
+
+impl ScreenTrait for AuxBusesScreen {
+    fn render(&self, f: &mut Frame, app: &App, area: Rect) {
+        let (selected_bus, selected_row) = get_state(app);
+
+        let main_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(3)])
+            .split(area);
+        let content_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(main_chunks[0]);
+
+        // Left panel: the buses
+        let bus_items: Vec<ListItem> = app
+            .session
+            .aux_buses
+            .iter()
+            .enumerate()
+            .map(|(i, bus)| {
+                let style = if i == selected_bus {
+                    Style::default().fg(Color::Black).bg(Color::Cyan)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let muted = if bus.muted { " | MUTED" } else { "" };
+                ListItem::new(Line::from(Span::styled(
+                    format!(
+                        "{} | Vol: {:.0}% | FX: {}{}",
+                        bus.name,
+                        bus.volume * 100.0,
+                        bus.fx_chain.len(),
+                        muted
+                    ),
+                    style,
+                )))
+            })
+            .collect();
+        let bus_list = if bus_items.is_empty() {
+            List::new(vec![ListItem::new(Line::from(Span::styled(
+                "No aux buses - press 'n' to add one",
+                Style::default().fg(Color::DarkGray),
+            )))])
+        } else {
+            List::new(bus_items)
+        };
+        f.render_widget(
+            bus_list.block(Block::default().borders(Borders::ALL).title("Aux Buses")),
+            content_chunks[0],
+        );
+
+        // Right panel: the selected bus's return and every track's send into it
+        let mut rows: Vec<ListItem> = Vec::new();
+        if let Some(bus) = app.session.aux_buses.get(selected_bus) {
+            let fx_names: Vec<String> = bus.fx_chain.iter().map(|fx| fx.display_name()).collect();
+            let fx_label = if fx_names.is_empty() {
+                "none".to_string()
+            } else {
+                fx_names.join(" > ")
+            };
+            rows.push(ListItem::new(Line::from(Span::styled(
+                format!(
+                    "Return | Vol: {:.0}% | FX: {}",
+                    bus.volume * 100.0,
+                    fx_label
+                ),
+                row_style(selected_row == 0),
+            ))));
+
+            for (i, track) in app.session.tracks.iter().enumerate() {
+                let label = match app.session.send(i, selected_bus) {
+                    Some(send) => format!(
+                        "{}: {:.0}% {}",
+                        track.name,
+                        send.level * 100.0,
+                        if send.pre_fader { "pre" } else { "post" }
+                    ),
+                    None => format!("{}: --", track.name),
+                };
+                rows.push(ListItem::new(Line::from(Span::styled(
+                    label,
+                    row_style(selected_row == i + 1),
+                ))));
+            }
+        }
+        let title = app
+            .session
+            .aux_buses
+            .get(selected_bus)
+            .map(|bus| format!("Sends - {}", bus.name))
+            .unwrap_or_else(|| "Sends".to_string());
+        f.render_widget(
+            List::new(rows).block(Block::default().borders(Borders::ALL).title(title)),
+            content_chunks[1],
+        );
+
+        let instructions = Paragraph::new(Line::from(Span::styled(
+            layout_config::INSTRUCTIONS,
+            Style::default().fg(Color::Cyan),
+        )))
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(instructions, main_chunks[1]);
+    }
+
+    fn handle_input(
+        &self,
+        app: &mut App,
+        key: KeyCode,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let (selected_bus, selected_row) = get_state(app);
+        let bus_count = app.session.aux_buses.len();
+        let row_count = 1 + app.session.tracks.len();
+        let has_bus = selected_bus < bus_count;
+        // Track whose send is selected, if a send row is selected
+        let send_track = selected_row.checked_sub(1);
+
+        match key {
+            KeyCode::Left if selected_bus > 0 => set_state(app, selected_bus - 1, selected_row),
+            KeyCode::Right if selected_bus + 1 < bus_count => {
+                set_state(app, selected_bus + 1, selected_row)
+            }
+            KeyCode::Up if selected_row > 0 => set_state(app, selected_bus, selected_row - 1),
+            KeyCode::Down if selected_row + 1 < row_count => {
+                set_state(app, selected_bus, selected_row + 1)
+            }
+
+            KeyCode::Char('n') => {
+                let name = format!("Aux {}", bus_count + 1);
+                let index = app.session.add_aux_bus(name.clone());
+                set_state(app, index, 0);
+                app.status = format!("{} added", name);
+            }
+
+            KeyCode::Char('d') if has_bus => match app.session.remove_aux_bus(selected_bus) {
+                Ok(()) => {
+                    set_state(app, selected_bus.saturating_sub(1), 0);
+                    app.status = "Bus removed".to_string();
+                }
+                Err(e) => app.status = format!("Cannot remove bus: {}", e),
+            },
+
+            KeyCode::Char('m') if has_bus => {
+                app.session
+                    .edit_bus(selected_bus, "Mute bus", |bus| bus.muted = !bus.muted);
+                let bus = &app.session.aux_buses[selected_bus];
+                let state = if bus.muted { "muted" } else { "unmuted" };
+                app.status = format!("{} {}", bus.name, state);
+            }
+
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char('-') if has_bus => {
+                let delta = if key == KeyCode::Char('-') {
+                    -layout_config::LEVEL_STEP
+                } else {
+                    layout_config::LEVEL_STEP
+                };
+                match send_track {
+                    None => {
+                        app.session.edit_bus(selected_bus, "Bus volume", |bus| {
+                            bus.volume = step_level(bus.volume as f32, delta) as f64
+                        });
+                        let bus = &app.session.aux_buses[selected_bus];
+                        app.status = format!("{} volume: {:.0}%", bus.name, bus.volume * 100.0);
+                    }
+                    Some(track) => {
+                        let (level, pre_fader) = app
+                            .session
+                            .send(track, selected_bus)
+                            .map_or((0.0, false), |s| (s.level, s.pre_fader));
+                        let level = step_level(level, delta);
+                        match app.session.set_send(track, selected_bus, level, pre_fader) {
+                            Ok(()) => {
+                                app.status =
+                                    format!("Track {} send: {:.0}%", track + 1, level * 100.0)
+                            }
+                            Err(e) => app.status = format!("Cannot set send: {}", e),
+                        }
+                    }
+                }
+            }
+
+            KeyCode::Char('p') if has_bus => {
+                if let Some(track) = send_track {
+                    if let Some(send) = app.session.send(track, selected_bus).copied() {
+                        let pre_fader = !send.pre_fader;
+                        app.session
+                            .set_send(track, selected_bus, send.level, pre_fader)?;
+                        let mode = if pre_fader { "pre-fader" } else { "post-fader" };
+                        app.status = format!("Track {} send is {}", track + 1, mode);
+                    }
+                }
+            }
+
+            KeyCode::Backspace | KeyCode::Delete if has_bus => {
+                if let Some(track) = send_track {
+                    app.session.remove_send(track, selected_bus);
+                    app.status = format!("Track {} send removed", track + 1);
+                }
+            }
+
+            KeyCode::Enter if has_bus => {
+                app.screen = Screen::FxChainEditor {
+                    target: FxTarget::Aux(selected_bus),
+                    selected_effect: 0,
+                    editing_param: None,
+                    add_mode: false,
+                    add_mode_selected: 0,
+                };
+            }
+
+            KeyCode::Esc => {
+                app.screen = Screen::Daw {
+                    selected_track: 0,
+                    scroll_offset: 0,
+                    selected_clip: None,
+                };
+            }
+            _ => {}
+        }
+
+        Ok(false)
+    }
+}
+
+fn row_style(selected: bool) -> Style {
+    if selected {
+        Style::default().fg(Color::Black).bg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::White)
+    }
+}
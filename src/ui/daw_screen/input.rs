@@ -1,4 +1,5 @@
 use super::layout_config;
+use crate::ui::export_screen::ExportScreen;
 use crate::ui::{App, Screen};
 use crossterm::event::KeyCode;
 
@@ -67,6 +68,11 @@ fn ensure_playhead_visible(app: &mut App) {
     }
 }
 
+// How far from the playhead an automation point can be to be edited
+fn automation_tolerance(app: &App) -> u64 {
+    (app.session.sample_rate as f64 * layout_config::AUTOMATION_POINT_TOLERANCE_SECONDS) as u64
+}
+
 pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::error::Error>> {
     let track_count = app.session.tracks.len();
     let max_selected = track_count.saturating_sub(1);
@@ -85,32 +91,57 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
     }
 
     match key {
-        KeyCode::Up => {
-            if sel > 0 {
-                set_selected_track(app, sel - 1);
-                set_selected_clip(app, None);
+        // With a clip selected, Up/Down carry it to the neighbouring track
+        KeyCode::Up | KeyCode::Down if selected_clip(app).is_some() => {
+            let clip_idx = selected_clip(app).unwrap_or(0);
+            let target = if key == KeyCode::Up {
+                sel.checked_sub(1)
+            } else {
+                Some(sel + 1).filter(|&t| t < track_count)
+            };
+            if let Some(target) = target {
+                match app.session.move_clip_to_track(sel, clip_idx, target) {
+                    Ok(idx) => {
+                        set_selected_track(app, target);
+                        set_selected_clip(app, Some(idx));
+                        app.status = format!("Clip moved to track {}", target + 1);
+                    }
+                    Err(e) => app.status = format!("Cannot move clip: {}", e),
+                }
             }
         }
-        KeyCode::Down => {
-            if sel < max_selected {
-                set_selected_track(app, sel + 1);
-                set_selected_clip(app, None);
-            }
+        KeyCode::Up if sel > 0 => {
+            set_selected_track(app, sel - 1);
+            set_selected_clip(app, None);
+        }
+        KeyCode::Down if sel < max_selected => {
+            set_selected_track(app, sel + 1);
+            set_selected_clip(app, None);
+        }
+        KeyCode::PageUp if sel > 0 => {
+            let page = layout_config::LANES_PER_SCREEN as usize;
+            set_selected_track(app, sel.saturating_sub(page));
+            set_selected_clip(app, None);
+        }
+        KeyCode::PageDown if sel < max_selected => {
+            let page = layout_config::LANES_PER_SCREEN as usize;
+            set_selected_track(app, (sel + page).min(max_selected));
+            set_selected_clip(app, None);
         }
         KeyCode::Left => {
             if let Some(clip_idx) = selected_clip(app) {
                 if !app.session.transport.is_playing() {
                     // Move the Clip to the left
-                    let delta = (app.session.sample_rate as f64
+                    let delta = -(app.session.sample_rate as f64
                         * layout_config::PLAYHEAD_DELTA_SECONDS)
-                        as u64;
-                    let track = &mut app.session.tracks[sel];
-                    track.clips[clip_idx].starts_at =
-                        track.clips[clip_idx].starts_at.saturating_sub(delta);
-                    track.cache_waveform();
-                    let secs =
-                        track.clips[clip_idx].starts_at as f64 / app.session.sample_rate as f64;
-                    app.status = format!("Clip moved to {:.1}s", secs);
+                        as i64;
+                    app.status = match app.session.nudge_clip(sel, clip_idx, delta) {
+                        Ok(start) => format!(
+                            "Clip moved to {:.1}s",
+                            start as f64 / app.session.sample_rate as f64
+                        ),
+                        Err(e) => format!("Cannot move clip: {}", e),
+                    };
                 }
             } else if !app.session.transport.is_playing() {
                 let delta = -(app.session.sample_rate as f64
@@ -130,14 +161,14 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
                     // Move the Clip to the right
                     let delta = (app.session.sample_rate as f64
                         * layout_config::PLAYHEAD_DELTA_SECONDS)
-                        as u64;
-                    let track = &mut app.session.tracks[sel];
-                    track.clips[clip_idx].starts_at =
-                        track.clips[clip_idx].starts_at.saturating_add(delta);
-                    track.cache_waveform();
-                    let secs =
-                        track.clips[clip_idx].starts_at as f64 / app.session.sample_rate as f64;
-                    app.status = format!("Clip moved to {:.1}s", secs);
+                        as i64;
+                    app.status = match app.session.nudge_clip(sel, clip_idx, delta) {
+                        Ok(start) => format!(
+                            "Clip moved to {:.1}s",
+                            start as f64 / app.session.sample_rate as f64
+                        ),
+                        Err(e) => format!("Cannot move clip: {}", e),
+                    };
                 }
             } else if !app.session.transport.is_playing() {
                 let delta =
@@ -151,12 +182,10 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
                 app.status = format!("Playhead: {:.1}s", secs);
             }
         }
-        KeyCode::Char('h') => {
-            if !app.session.transport.is_playing() {
-                app.session.transport.reset_playhead();
-                set_scroll_offset(app, 0);
-                app.status = "Playhead reset to start".to_string();
-            }
+        KeyCode::Char('h') if !app.session.transport.is_playing() => {
+            app.session.transport.reset_playhead();
+            set_scroll_offset(app, 0);
+            app.status = "Playhead reset to start".to_string();
         }
 
         // Manual timeline scrolling
@@ -171,6 +200,149 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
             set_scroll_offset(app, offset + scroll_step);
         }
 
+        // Loop region: '{' and '}' set its ends at the playhead, 'o' turns it on and off
+        KeyCode::Char('{') | KeyCode::Char('}') => {
+            let playhead = app.session.transport.playhead_position;
+            let result = if key == KeyCode::Char('{') {
+                app.session.set_loop_start(playhead)
+            } else {
+                app.session.set_loop_end(playhead)
+            };
+            let which = if key == KeyCode::Char('{') {
+                "start"
+            } else {
+                "end"
+            };
+            let secs = playhead as f64 / app.session.sample_rate as f64;
+            app.status = match result {
+                Ok(()) if app.session.transport.loop_start >= app.session.transport.loop_end => {
+                    format!(
+                        "Loop {} at {:.1}s (set the end after the start)",
+                        which, secs
+                    )
+                }
+                Ok(()) => format!("Loop {} at {:.1}s", which, secs),
+                Err(e) => format!("Cannot set loop: {}", e),
+            };
+        }
+        KeyCode::Char('o') => match app.session.toggle_loop() {
+            Ok(true) if app.session.transport.loop_range().is_none() => {
+                app.status = "Loop on (set start and end with { and })".to_string()
+            }
+            Ok(true) => app.status = "Loop on".to_string(),
+            Ok(false) => app.status = "Loop off".to_string(),
+            Err(e) => app.status = format!("Cannot toggle loop: {}", e),
+        },
+
+        // Punch region: 'I' and 'O' set punch-in/out at the playhead, 'P' turns it on
+        // and off, '(' and ')' shorten and lengthen the pre-roll
+        KeyCode::Char('I') | KeyCode::Char('O') => {
+            let playhead = app.session.transport.playhead_position;
+            let result = if key == KeyCode::Char('I') {
+                app.session.set_punch_in(playhead)
+            } else {
+                app.session.set_punch_out(playhead)
+            };
+            let which = if key == KeyCode::Char('I') {
+                "in"
+            } else {
+                "out"
+            };
+            let secs = playhead as f64 / app.session.sample_rate as f64;
+            app.status = match result {
+                Ok(()) if app.session.transport.punch_in >= app.session.transport.punch_out => {
+                    format!(
+                        "Punch {} at {:.1}s (set punch-out after punch-in)",
+                        which, secs
+                    )
+                }
+                Ok(()) => format!("Punch {} at {:.1}s", which, secs),
+                Err(e) => format!("Cannot set punch: {}", e),
+            };
+        }
+        KeyCode::Char('P') => match app.session.toggle_punch() {
+            Ok(true) if app.session.transport.punch_range().is_none() => {
+                app.status = "Punch on (set punch-in and out with I and O)".to_string()
+            }
+            Ok(true) => app.status = "Punch on".to_string(),
+            Ok(false) => app.status = "Punch off".to_string(),
+            Err(e) => app.status = format!("Cannot toggle punch: {}", e),
+        },
+        KeyCode::Char('(') | KeyCode::Char(')') => {
+            let delta = if key == KeyCode::Char(')') {
+                layout_config::PRE_ROLL_STEP_SECONDS
+            } else {
+                -layout_config::PRE_ROLL_STEP_SECONDS
+            };
+            let pre_roll = app.session.adjust_pre_roll(delta);
+            app.status = format!("Pre-roll: {:.1}s", pre_roll);
+        }
+
+        // Tempo map: edits apply from the bar under the playhead
+        KeyCode::Char('y') | KeyCode::Char('Y') => {
+            let delta = if key == KeyCode::Char('Y') {
+                layout_config::TEMPO_STEP_BPM
+            } else {
+                -layout_config::TEMPO_STEP_BPM
+            };
+            let bpm = app.session.adjust_tempo(delta);
+            let bar = app.session.playhead_musical().bar;
+            app.status = format!("Tempo from bar {}: {:.0} BPM", bar + 1, bpm);
+        }
+        KeyCode::Char('w') => {
+            let (numerator, denominator) = app.session.cycle_time_signature();
+            let bar = app.session.playhead_musical().bar;
+            app.status = format!(
+                "Time signature from bar {}: {}/{}",
+                bar + 1,
+                numerator,
+                denominator
+            );
+        }
+        KeyCode::Char('W') => {
+            app.status = match app.session.remove_tempo_change() {
+                Ok(bar) => format!("Tempo change at bar {} removed", bar + 1),
+                Err(e) => format!("Cannot remove tempo change: {}", e),
+            };
+        }
+        KeyCode::Char('G') => {
+            app.show_grid = !app.show_grid;
+            app.status = format!("Grid {}", if app.show_grid { "on" } else { "off" });
+        }
+
+        // Metronome
+        KeyCode::Char('e') => {
+            let on = app.session.toggle_metronome();
+            app.status = format!("Click {}", if on { "on" } else { "off" });
+        }
+        KeyCode::Char('C') => {
+            let sound = app.session.cycle_metronome_sound();
+            app.status = format!("Click sound: {}", sound.name());
+        }
+        KeyCode::Char('j') | KeyCode::Char('J') => {
+            let delta = if key == KeyCode::Char('J') {
+                layout_config::CLICK_LEVEL_STEP
+            } else {
+                -layout_config::CLICK_LEVEL_STEP
+            };
+            let level = app.session.adjust_metronome_level(delta);
+            app.status = format!("Click level: {:.0}%", level * 100.0);
+        }
+        KeyCode::Char('K') => {
+            app.status = match app.session.cycle_count_in() {
+                0 => "Count-in off".to_string(),
+                1 => "Count-in: 1 bar".to_string(),
+                bars => format!("Count-in: {} bars", bars),
+            };
+        }
+        KeyCode::Char('L') => {
+            app.status = if app.session.toggle_metronome_output_only() {
+                "Click goes to the output only".to_string()
+            } else {
+                "Click is mixed in before the master channel".to_string()
+            };
+        }
+
         // Global transport control
         KeyCode::Char(' ') | KeyCode::Enter => match app.session.toggle_playback() {
             Ok(_) => {
@@ -184,6 +356,10 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
             Err(e) => app.status = format!("Playback error: {}", e),
         },
 
+        KeyCode::Char('a') if app.session.tracks[sel].is_group => {
+            app.status = "Group tracks cannot be armed".to_string();
+        }
+
         KeyCode::Char('a') => {
             let track = &mut app.session.tracks[sel];
             if track.is_armed() {
@@ -252,14 +428,16 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
 
         KeyCode::Char('c') => {
             set_selected_clip(app, None);
-            app.session.tracks[sel].clips.clear();
-            app.session.tracks[sel].cache_waveform();
-            app.status = format!("Track {} cleared", sel + 1);
+            match app.session.clear_track(sel) {
+                Ok(()) => app.status = format!("Track {} cleared", sel + 1),
+                Err(e) => app.status = format!("Cannot clear track: {}", e),
+            }
         }
 
         // Volume and mute
         KeyCode::Char('m') => {
-            app.session.tracks[sel].muted = !app.session.tracks[sel].muted;
+            app.session
+                .edit_mixer(sel, "Mute track", |track| track.muted = !track.muted);
             let status = if app.session.tracks[sel].muted {
                 "muted"
             } else {
@@ -268,18 +446,168 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
             app.status = format!("Track {} {}", sel + 1, status);
         }
 
+        KeyCode::Char('s') | KeyCode::Char('S') => {
+            // Shift+s soloes this track exclusively, clearing every other solo
+            let exclusive = key == KeyCode::Char('S');
+            app.session.toggle_solo(sel, exclusive);
+            let status = if app.session.tracks[sel].solo {
+                "soloed"
+            } else {
+                "unsoloed"
+            };
+            let mode = if exclusive { " (exclusive)" } else { "" };
+            app.status = format!("Track {} {}{}", sel + 1, status, mode);
+        }
+
+        KeyCode::Char('l') => {
+            app.session.toggle_solo_safe(sel);
+            let status = if app.session.tracks[sel].solo_safe {
+                "solo safe"
+            } else {
+                "no longer solo safe"
+            };
+            app.status = format!("Track {} {}", sel + 1, status);
+        }
+
+        KeyCode::Char('k') => {
+            app.session.reset_clip_indicators();
+            app.status = "Clip indicators cleared".to_string();
+        }
+
+        KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char('-')
+            if selected_clip(app).is_some() =>
+        {
+            let clip_idx = selected_clip(app).unwrap_or(0);
+            let delta = if key == KeyCode::Char('-') {
+                -layout_config::CLIP_GAIN_STEP_DB
+            } else {
+                layout_config::CLIP_GAIN_STEP_DB
+            };
+            app.status = match app.session.adjust_clip_gain(sel, clip_idx, delta) {
+                Ok(gain) => format!("Clip {} gain: {:+.1} dB", clip_idx + 1, gain),
+                Err(e) => format!("Cannot change clip gain: {}", e),
+            };
+        }
         KeyCode::Char('+') | KeyCode::Char('=') => {
-            let track = &mut app.session.tracks[sel];
-            track.volume = (track.volume + 0.1).min(2.0);
+            app.session.edit_mixer(sel, "Track volume", |track| {
+                track.volume = (track.volume + 0.1).min(2.0)
+            });
+            let track = &app.session.tracks[sel];
             app.status = format!("Track {} volume: {:.0}%", sel + 1, track.volume * 100.0);
         }
 
         KeyCode::Char('-') => {
-            let track = &mut app.session.tracks[sel];
-            track.volume = (track.volume - 0.1).max(0.0);
+            app.session.edit_mixer(sel, "Track volume", |track| {
+                track.volume = (track.volume - 0.1).max(0.0)
+            });
+            let track = &app.session.tracks[sel];
             app.status = format!("Track {} volume: {:.0}%", sel + 1, track.volume * 100.0);
         }
 
+        KeyCode::Char(',') => {
+            app.session.edit_mixer(sel, "Track pan", |track| {
+                track.pan = ((track.pan - 0.1) * 10.0).round().max(-10.0) / 10.0
+            });
+            let track = &app.session.tracks[sel];
+            app.status = format!(
+                "Track {} pan: {}",
+                sel + 1,
+                crate::channels::format_pan(track.pan)
+            );
+        }
+
+        KeyCode::Char('.') => {
+            app.session.edit_mixer(sel, "Track pan", |track| {
+                track.pan = ((track.pan + 0.1) * 10.0).round().min(10.0) / 10.0
+            });
+            let track = &app.session.tracks[sel];
+            app.status = format!(
+                "Track {} pan: {}",
+                sel + 1,
+                crate::channels::format_pan(track.pan)
+            );
+        }
+
+        KeyCode::Char('v') | KeyCode::Char('V') => {
+            let delta = if key == KeyCode::Char('V') { 0.1 } else { -0.1 };
+            let gain = ((app.session.master.gain + delta) * 10.0)
+                .round()
+                .clamp(0.0, 20.0)
+                / 10.0;
+            app.session.set_master_gain(gain);
+            app.status = format!("Master volume: {:.0}%", gain * 100.0);
+        }
+
+        KeyCode::Char('g') => {
+            let group_num = app.session.tracks.iter().filter(|t| t.is_group).count() + 1;
+            let index = app.session.add_group_track(format!("Group {}", group_num));
+            set_selected_track(app, index);
+            set_selected_clip(app, None);
+            app.status = format!("Group {} added", group_num);
+        }
+
+        KeyCode::Char('p') => {
+            // Cycle the selected track through: top level -> each group it can join
+            let candidates: Vec<usize> = (0..track_count)
+                .filter(|&g| app.session.tracks[g].is_group && g != sel)
+                .collect();
+            let current = app.session.tracks[sel].parent;
+            let start = current
+                .and_then(|p| candidates.iter().position(|&g| g == p))
+                .map_or(0, |pos| pos + 1);
+            // Try each following option in turn, skipping groups nested inside this track
+            let options: Vec<Option<usize>> = candidates
+                .iter()
+                .skip(start)
+                .map(|&g| Some(g))
+                .chain(std::iter::once(None))
+                .collect();
+            for parent in options {
+                if app.session.set_track_parent(sel, parent).is_ok() {
+                    app.status = match parent {
+                        Some(g) => format!(
+                            "Track {} moved into {}",
+                            sel + 1,
+                            app.session.tracks[g].name
+                        ),
+                        None => format!("Track {} moved to top level", sel + 1),
+                    };
+                    break;
+                }
+            }
+        }
+
+        KeyCode::Char('b') => {
+            app.screen = Screen::AuxBuses {
+                selected_bus: 0,
+                selected_row: 0,
+            };
+        }
+
+        KeyCode::Char('z') => {
+            let track = &mut app.session.tracks[sel];
+            track.collapsed = !track.collapsed;
+            let state = if track.collapsed {
+                "collapsed"
+            } else {
+                "expanded"
+            };
+            app.status = format!("Track {} {}", sel + 1, state);
+        }
+
+        KeyCode::Char('Z') => {
+            // Collapse everything unless all lanes already are, in which case expand them
+            let collapse = app.session.tracks.iter().any(|t| !t.collapsed);
+            for track in app.session.tracks.iter_mut() {
+                track.collapsed = collapse;
+            }
+            app.status = if collapse {
+                "All tracks collapsed".to_string()
+            } else {
+                "All tracks expanded".to_string()
+            };
+        }
+
         KeyCode::Char('i') => {
             let track = &mut app.session.tracks[sel];
             track.input_channel = match track.input_channel {
@@ -298,37 +626,8 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
         }
 
         KeyCode::Char('x') => {
-            let samples = app.session.render_full_mix();
-            if samples.is_empty() {
-                app.status = "Nothing to export".to_string();
-            } else {
-                let mut wav = crate::wav::WavFile::new(app.session.sample_rate, 1);
-                wav.from_f32_samples(&samples);
-                let dir = app
-                    .project_dir
-                    .clone()
-                    .unwrap_or_else(|| std::path::PathBuf::from("."));
-                let name = &app.session.name;
-                let path = {
-                    let base = dir.join(format!("{}_mix.wav", name));
-                    if !base.exists() {
-                        base
-                    } else {
-                        let mut n = 1u32;
-                        loop {
-                            let p = dir.join(format!("{}_mix_{}.wav", name, n));
-                            if !p.exists() {
-                                break p;
-                            }
-                            n += 1;
-                        }
-                    }
-                };
-                match wav.save_to_file(&path) {
-                    Ok(()) => app.status = format!("Exported to {}", path.display()),
-                    Err(e) => app.status = format!("Export error: {}", e),
-                }
-            }
+            let offset = scroll_offset(app);
+            ExportScreen::open(app, sel, offset);
         }
 
         KeyCode::Char('n') => {
@@ -363,43 +662,233 @@ pub fn handle_input(app: &mut App, key: KeyCode) -> Result<bool, Box<dyn std::er
             }
         }
 
-        KeyCode::Tab => {
-            if !app.session.transport.is_playing() && track_count > 0 {
-                let clip_count = app.session.tracks[sel].clips.len();
-                if clip_count > 0 {
-                    let next = match selected_clip(app) {
-                        None => Some(0),
-                        Some(idx) if idx + 1 < clip_count => Some(idx + 1),
-                        Some(_) => None,
+        KeyCode::Tab if !app.session.transport.is_playing() && track_count > 0 => {
+            let clip_count = app.session.tracks[sel].clips.len();
+            if clip_count > 0 {
+                let next = match selected_clip(app) {
+                    None => Some(0),
+                    Some(idx) if idx + 1 < clip_count => Some(idx + 1),
+                    Some(_) => None,
+                };
+                set_selected_clip(app, next);
+                if let Some(idx) = next {
+                    let clip = &app.session.tracks[sel].clips[idx];
+                    let secs = clip.starts_at as f64 / app.session.sample_rate as f64;
+                    app.status = format!("Clip {} selected (at {:.1}s)", idx + 1, secs);
+                } else {
+                    app.status = "Clip deselected".to_string();
+                }
+            }
+        }
+
+        KeyCode::Char('u') => {
+            // Mute or unmute the selected clip
+            if let Some(clip_idx) = selected_clip(app) {
+                match app.session.toggle_clip_muted(sel, clip_idx) {
+                    Ok(true) => app.status = format!("Clip {} muted", clip_idx + 1),
+                    Ok(false) => app.status = format!("Clip {} unmuted", clip_idx + 1),
+                    Err(e) => app.status = format!("Cannot mute clip: {}", e),
+                }
+            }
+        }
+
+        // Comping: 't' picks the next take lane, 'T' plays it over the loop region
+        // (or all of it when no region is marked), Delete removes it
+        KeyCode::Char('t') if track_count > 0 => match app.session.select_take(sel, 1) {
+            Ok(take) => {
+                let count = app.session.tracks[sel].takes.len();
+                app.status = format!("Take {}/{} selected", take + 1, count);
+            }
+            Err(e) => app.status = format!("Cannot select take: {}", e),
+        },
+        KeyCode::Char('T') if track_count > 0 => {
+            let transport = &app.session.transport;
+            let region = (transport.loop_start < transport.loop_end)
+                .then_some(transport.loop_start..transport.loop_end);
+            let take = app.session.tracks[sel].selected_take;
+            app.status = match app.session.comp_selected_take(sel, region) {
+                Ok(range) => {
+                    let sr = app.session.sample_rate as f64;
+                    format!(
+                        "Take {} comped over {:.1}s-{:.1}s",
+                        take + 1,
+                        range.start as f64 / sr,
+                        range.end as f64 / sr
+                    )
+                }
+                Err(e) => format!("Cannot comp take: {}", e),
+            };
+            set_selected_clip(app, None);
+        }
+        KeyCode::Delete if track_count > 0 && !app.session.transport.is_playing() => {
+            let take = app.session.tracks[sel].selected_take;
+            app.status = match app.session.remove_selected_take(sel) {
+                Ok(()) => format!("Take {} deleted", take + 1),
+                Err(e) => format!("Cannot delete take: {}", e),
+            };
+            set_selected_clip(app, None);
+        }
+
+        // Split the selected clip (or the one under the playhead) at the playhead
+        KeyCode::Char('/') if track_count > 0 => {
+            let playhead = app.session.transport.playhead_position;
+            let clip_idx = selected_clip(app).or_else(|| {
+                app.session.tracks[sel]
+                    .clips
+                    .iter()
+                    .position(|c| c.starts_at < playhead && c.end() > playhead)
+            });
+            if let Some(clip_idx) = clip_idx {
+                app.status = match app.session.split_clip(sel, clip_idx, playhead) {
+                    Ok(tail) => {
+                        set_selected_clip(app, Some(tail));
+                        format!("Clip {} split", clip_idx + 1)
+                    }
+                    Err(e) => format!("Cannot split clip: {}", e),
+                };
+            }
+        }
+
+        // Trim and fade the selected clip: 1/2 start edge, 3/4 end edge, 5/6 fade-in,
+        // 7/8 fade-out, 9/0 fade shapes
+        KeyCode::Char(c @ '1'..='9') | KeyCode::Char(c @ '0') if selected_clip(app).is_some() => {
+            let clip_idx = selected_clip(app).unwrap_or(0);
+            let sr = app.session.sample_rate as f64;
+            let trim_step = (sr * layout_config::CLIP_TRIM_STEP_SECONDS) as i64;
+            let fade_step = (sr * layout_config::CLIP_FADE_STEP_SECONDS) as i64;
+            let result = match c {
+                '1' | '2' | '3' | '4' => {
+                    let delta = if matches!(c, '1' | '3') {
+                        -trim_step
+                    } else {
+                        trim_step
                     };
-                    set_selected_clip(app, next);
-                    if let Some(idx) = next {
-                        let clip = &app.session.tracks[sel].clips[idx];
-                        let secs = clip.starts_at as f64 / app.session.sample_rate as f64;
-                        app.status = format!("Clip {} selected (at {:.1}s)", idx + 1, secs);
+                    let end = matches!(c, '3' | '4');
+                    app.session.trim_clip(sel, clip_idx, end, delta).map(|()| {
+                        let clip = &app.session.tracks[sel].clips[clip_idx];
+                        format!(
+                            "Clip {}: {:.2}s-{:.2}s",
+                            clip_idx + 1,
+                            clip.starts_at as f64 / sr,
+                            clip.end() as f64 / sr
+                        )
+                    })
+                }
+                '5' | '6' | '7' | '8' => {
+                    let delta = if matches!(c, '5' | '7') {
+                        -fade_step
                     } else {
-                        app.status = "Clip deselected".to_string();
-                    }
+                        fade_step
+                    };
+                    let fade_out = matches!(c, '7' | '8');
+                    let which = if fade_out { "out" } else { "in" };
+                    app.session
+                        .adjust_clip_fade(sel, clip_idx, fade_out, delta)
+                        .map(|frames| format!("Fade-{}: {:.2}s", which, frames as f64 / sr))
+                }
+                _ => {
+                    let fade_out = c == '0';
+                    let which = if fade_out { "out" } else { "in" };
+                    app.session
+                        .cycle_clip_fade_shape(sel, clip_idx, fade_out)
+                        .map(|shape| format!("Fade-{} shape: {}", which, shape.name()))
                 }
+            };
+            app.status = result.unwrap_or_else(|e| format!("Cannot edit clip: {}", e));
+        }
+
+        // Crossfade into the selected clip from the one before it
+        KeyCode::Char(c @ ('<' | '>')) if selected_clip(app).is_some() => {
+            let clip_idx = selected_clip(app).unwrap_or(0);
+            let sr = app.session.sample_rate as f64;
+            let step = (sr * layout_config::CROSSFADE_STEP_SECONDS) as i64;
+            let delta = if c == '<' { -step } else { step };
+            app.status = match app.session.adjust_crossfade(sel, clip_idx, delta) {
+                Ok(frames) => format!("Crossfade: {:.3}s", frames as f64 / sr),
+                Err(e) => format!("Cannot edit crossfade: {}", e),
+            };
+        }
+        KeyCode::Char('X') if selected_clip(app).is_some() => {
+            let clip_idx = selected_clip(app).unwrap_or(0);
+            app.status = match app.session.cycle_crossfade_shape(sel, clip_idx) {
+                Ok(shape) => format!("Crossfade shape: {}", shape.name()),
+                Err(e) => format!("Cannot edit crossfade: {}", e),
+            };
+        }
+        KeyCode::Char('A') if track_count > 0 => {
+            app.status = match app.session.toggle_auto_crossfade(sel) {
+                Ok(true) => "Automatic crossfades on".to_string(),
+                Ok(false) => "Automatic crossfades off".to_string(),
+                Err(e) => format!("Cannot change crossfades: {}", e),
+            };
+        }
+
+        KeyCode::Char('U') if track_count > 0 => {
+            if let Some(mode) = app.session.cycle_automation_mode(sel) {
+                app.status = format!("Track {} automation: {}", sel + 1, mode.name());
+            }
+        }
+        KeyCode::Char('N') if track_count > 0 => {
+            if let Some(name) = app.session.cycle_automation_view(sel) {
+                app.status = format!("Track {} shows {} automation", sel + 1, name);
             }
         }
+        KeyCode::Char('B') if track_count > 0 => {
+            let frame = app.session.transport.playhead_position;
+            app.status = match app.session.add_automation_point(sel, frame) {
+                Ok(value) => format!("Automation point added at {:.2}", value),
+                Err(e) => format!("Cannot add automation point: {}", e),
+            };
+        }
+        KeyCode::Char('D') if track_count > 0 => {
+            let frame = app.session.transport.playhead_position;
+            let tolerance = automation_tolerance(app);
+            app.status = match app.session.remove_automation_point(sel, frame, tolerance) {
+                Ok(()) => "Automation point removed".to_string(),
+                Err(e) => format!("Cannot remove automation point: {}", e),
+            };
+        }
+        KeyCode::Char('R') if track_count > 0 => {
+            let frame = app.session.transport.playhead_position;
+            let tolerance = automation_tolerance(app);
+            app.status = match app.session.cycle_automation_curve(sel, frame, tolerance) {
+                Ok(curve) => format!("Automation curve: {}", curve.name()),
+                Err(e) => format!("Cannot change automation curve: {}", e),
+            };
+        }
+        KeyCode::Char(c @ (';' | '\'')) if track_count > 0 => {
+            let frame = app.session.transport.playhead_position;
+            let tolerance = automation_tolerance(app);
+            let track = &app.session.tracks[sel];
+            let step = layout_config::automation_step(
+                &track.viewed_automation(),
+                track.static_value(&track.viewed_automation()),
+            );
+            let delta = if c == ';' { -step } else { step };
+            app.status = match app
+                .session
+                .adjust_automation_point(sel, frame, tolerance, delta)
+            {
+                Ok(value) => format!("Automation point value: {:.2}", value),
+                Err(e) => format!("Cannot change automation point: {}", e),
+            };
+        }
 
         KeyCode::Backspace => {
             if let Some(clip_idx) = selected_clip(app) {
                 if !app.session.transport.is_playing() {
-                    app.session.tracks[sel].clips.remove(clip_idx);
-                    app.session.tracks[sel].cache_waveform();
+                    app.status = match app.session.delete_clip(sel, clip_idx) {
+                        Ok(()) => format!("Clip {} deleted", clip_idx + 1),
+                        Err(e) => format!("Cannot delete clip: {}", e),
+                    };
                     set_selected_clip(app, None);
-                    app.status = format!("Clip {} deleted", clip_idx + 1);
                 }
             }
         }
 
-        KeyCode::Esc => {
-            if selected_clip(app).is_some() {
-                set_selected_clip(app, None);
-                app.status = "Clip deselected".to_string();
-            }
+        KeyCode::Esc if selected_clip(app).is_some() => {
+            set_selected_clip(app, None);
+            app.status = "Clip deselected".to_string();
         }
 
         _ => {}
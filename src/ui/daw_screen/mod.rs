@@ -8,8 +8,12 @@ use super::screen_trait::ScreenTrait;
 use super::App;
 
 pub(crate) mod layout_config {
+    use crate::meter::{level_db, MeterDisplay, METER_FLOOR_DB};
+    use crate::track::AutomationTarget;
     use ratatui::layout::Constraint;
-    use ratatui::style::Color;
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span};
+    use std::ops::Range;
 
     pub const SELECTED_BORDER: Color = Color::Yellow;
     pub const DEFAULT_BORDER: Color = Color::White;
@@ -19,49 +23,232 @@ pub(crate) mod layout_config {
     pub const RECORDING_SELECTED_BORDER: Color = Color::LightMagenta;
     pub const LANE_STATUS_ARMED: &str = "ARMED";
     pub const LANE_STATUS_MUTED: &str = "MUTED";
+    pub const LANE_STATUS_SOLOED_OUT: &str = "MUTED (solo)";
+    pub const LANE_FLAG_SOLO: &str = "SOLO";
+    pub const LANE_FLAG_SOLO_SAFE: &str = "SAFE";
     pub const LANE_STATUS_ACTIVE: &str = "ACTIVE";
+    pub const LANE_STATUS_GROUP: &str = "GROUP";
+    pub const GROUP_MARKER: &str = "\u{25be} ";
+    pub const CHILD_MARKER: &str = "\u{2514} ";
     pub const LANE_STATUS_RECORDING: &str = "\u{1f534} REC";
     pub const WAVEFORM_SENSITIVITY: f32 = 4.0;
+    pub const LOOP_COLOR: Color = Color::Blue;
+    pub const PUNCH_COLOR: Color = Color::Red;
+    pub const MUTED_CLIP_COLOR: Color = Color::Rgb(70, 70, 70);
+    pub const TAKE_COLOR: Color = Color::Magenta;
+    pub const FADE_COLOR: Color = Color::Yellow;
+    pub const XFADE_COLOR: Color = Color::LightCyan;
+    pub const GRID_BAR_COLOR: Color = Color::Rgb(90, 90, 90);
+    pub const GRID_BEAT_COLOR: Color = Color::Rgb(50, 50, 50);
+    // Beat lines are hidden when they would be closer together than this many cells
+    pub const GRID_MIN_CELLS_PER_BEAT: u16 = 3;
+    pub const TEMPO_STEP_BPM: f64 = 1.0;
+    pub const CLICK_LEVEL_STEP: f32 = 0.1;
+    pub const CLIP_GAIN_STEP_DB: f32 = 1.0;
+    pub const CLIP_TRIM_STEP_SECONDS: f64 = 0.1;
+    pub const CLIP_FADE_STEP_SECONDS: f64 = 0.05;
+    pub const CROSSFADE_STEP_SECONDS: f64 = 0.01;
+    pub const AUTOMATION_COLOR: Color = Color::LightYellow;
+    pub const AUTOMATION_POINT_TOLERANCE_SECONDS: f64 = 0.25;
+    // Points sampled across the view to draw an automation lane
+    pub const AUTOMATION_DRAW_STEPS: usize = 200;
     pub const TIMELINE_SECONDS: u64 = 20;
     pub const PLAYHEAD_DELTA_SECONDS: f64 = 0.5;
     pub const SCROLL_STEP_SECONDS: u64 = 5;
+    pub const PRE_ROLL_STEP_SECONDS: f32 = 0.5;
+    pub const LANES_PER_SCREEN: u16 = 3;
+    pub const MIN_LANE_HEIGHT: u16 = 6;
+    pub const COLLAPSED_LANE_HEIGHT: u16 = 3;
+    pub const METER_WIDTH: usize = 24;
+    pub const METER_INPUT_LABEL: &str = "In ";
+    pub const METER_OUTPUT_LABEL: &str = "Out ";
+    pub const METER_MASTER_LABEL: &str = "Master ";
+    pub const METER_CLIP_LABEL: &str = " CLIP ";
+    pub const METER_RMS_CELL: &str = "\u{2588}";
+    pub const METER_PEAK_CELL: &str = "\u{2592}";
+    pub const METER_HOLD_CELL: &str = "\u{2502}";
+    pub const METER_EMPTY_CELL: &str = "\u{00b7}";
+    // Cells above these levels (dBFS) turn yellow and red
+    pub const METER_WARN_DB: f32 = -18.0;
+    pub const METER_HOT_DB: f32 = -6.0;
     pub const GLOBAL_INSTRUCTIONS: &str =
-        "n: Add | d: Del | Space: Play | Left/Right: Playhead | [/]: Scroll | h: Reset | Tab: Clip | Bksp: Del Clip | Ctrl+S: Save";
+        "n: Add | d: Del | Space: Play | Left/Right: Playhead | [/]: Scroll | PgUp/PgDn: Tracks | s/S: Solo/Excl | l: Solo Safe | g: Group | p: Parent | b: Aux Buses | v/V: Master Vol | F: Master FX | {/}: Loop In/Out | o: Loop | I/O: Punch In/Out | P: Punch | (/): Pre-roll | u: Mute Clip | t/T: Take/Comp | Del: Del Take | y/Y: Tempo -/+ | w: Time Sig | W: Del Tempo | G: Grid | e: Click | C: Click Sound | j/J: Click Vol | K: Count-in | L: Click Out Only | z/Z: Collapse | k: Clear Clips | h: Reset | Tab: Clip | Bksp: Del Clip | /: Split | Clip: Left/Right Nudge, Up/Down Track, +/- Gain, 1-4 Trim, 5-8 Fades, 9/0 Fade Shape, </> Xfade, X: Xfade Shape | A: Auto Xfade | U: Automation Mode | N: Show Lane | B/D: Add/Del Point | ;/': Point Value | R: Point Curve | Ctrl+Z/Y: Undo/Redo | x: Export | Ctrl+O: Import | Ctrl+S: Save";
 
-    pub fn get_lane_constraints(track_count: usize) -> Vec<Constraint> {
-        let denominator = track_count.max(3) as u32;
-        (0..track_count)
-            .map(|_| Constraint::Ratio(1, denominator))
+    /// Height of one lane: expanded lanes take a third of the tracks area
+    /// (never less than MIN_LANE_HEIGHT), collapsed lanes only show their title.
+    pub fn lane_height(collapsed: bool, area_height: u16) -> u16 {
+        if collapsed {
+            COLLAPSED_LANE_HEIGHT
+        } else {
+            (area_height / LANES_PER_SCREEN).max(MIN_LANE_HEIGHT)
+        }
+    }
+
+    /// Lanes are split into pages that each fit in `area_height`.
+    /// Returns the range of tracks on the page that holds `selected`.
+    pub fn visible_lanes(collapsed: &[bool], selected: usize, area_height: u16) -> Range<usize> {
+        let mut start = 0;
+        let mut used = 0u16;
+        for (i, &is_collapsed) in collapsed.iter().enumerate() {
+            let height = lane_height(is_collapsed, area_height);
+            if i > start && used + height > area_height {
+                if selected < i {
+                    return start..i;
+                }
+                start = i;
+                used = 0;
+            }
+            used += height;
+        }
+        start..collapsed.len()
+    }
+
+    pub fn get_lane_constraints(collapsed: &[bool], area_height: u16) -> Vec<Constraint> {
+        collapsed
+            .iter()
+            .map(|&c| Constraint::Length(lane_height(c, area_height)))
+            .chain(std::iter::once(Constraint::Min(0)))
             .collect()
     }
 
+    /// Track name indented by its depth in the group hierarchy, with group tracks marked.
+    pub fn lane_name(name: &str, depth: usize, is_group: bool) -> String {
+        let indent = if depth > 0 {
+            format!("{}{}", "  ".repeat(depth - 1), CHILD_MARKER)
+        } else {
+            String::new()
+        };
+        let marker = if is_group { GROUP_MARKER } else { "" };
+        format!("{}{}{}", indent, marker, name)
+    }
+
     pub fn format_lane_title(
         name: &str,
         volume: f64,
+        pan: f32,
         input_channel: Option<u16>,
+        solo: bool,
+        solo_safe: bool,
         status: &str,
     ) -> String {
         let input_label = match input_channel {
-            None => "All",
-            Some(0) => "In 1",
-            Some(1) => "In 2",
-            Some(n) => {
-                return format!(
-                    "{} | Vol: {:.0}% | In {} | {}",
-                    name,
-                    volume * 100.0,
-                    n + 1,
-                    status
-                )
-            }
+            None => "All".to_string(),
+            Some(n) => format!("In {}", n + 1),
         };
-        format!(
-            "{} | Vol: {:.0}% | {} | {}",
+        let mut title = format!(
+            "{} | Vol: {:.0}% | Pan: {} | {} | {}",
             name,
             volume * 100.0,
+            crate::channels::format_pan(pan),
             input_label,
             status,
-        )
+        );
+        if solo {
+            title.push_str(&format!(" | {}", LANE_FLAG_SOLO));
+        }
+        if solo_safe {
+            title.push_str(&format!(" | {}", LANE_FLAG_SOLO_SAFE));
+        }
+        title
+    }
+
+    /// Lane title suffix naming the take lane comping acts on, if the track has takes.
+    pub fn format_take_label(selected_take: usize, take_count: usize) -> String {
+        if take_count == 0 {
+            return String::new();
+        }
+        format!(" | Take {}/{}", selected_take + 1, take_count)
+    }
+
+    /// Lane title suffix with the automation mode and the lane shown, if it has points.
+    pub fn format_automation_label(mode: &str, lane: &str, points: usize) -> String {
+        if points == 0 {
+            return format!(" | Auto: {}", mode);
+        }
+        format!(" | Auto: {} {} ({} pts)", mode, lane, points)
+    }
+
+    /// How much one key press moves an automation point: whole steps for integer effect
+    /// parameters, tenths for other effect parameters.
+    pub fn automation_step(target: &AutomationTarget, current: Option<f32>) -> f32 {
+        match target {
+            AutomationTarget::Volume => 0.05,
+            AutomationTarget::Pan => 0.1,
+            AutomationTarget::Mute => 1.0,
+            AutomationTarget::Effect { .. } => {
+                if current.is_some_and(|v| v.fract() == 0.0) {
+                    1.0
+                } else {
+                    0.1
+                }
+            }
+        }
+    }
+
+    /// Height in the lane canvas (-0.9 to 0.9) of an automation value.
+    pub fn automation_y(target: &AutomationTarget, value: f32, highest: f32) -> f64 {
+        let (min, max) = match target {
+            AutomationTarget::Effect { .. } => (0.0, highest.max(1.0)),
+            _ => target.range(),
+        };
+        let t = ((value - min) / (max - min)).clamp(0.0, 1.0);
+        (t as f64 * 1.8) - 0.9
+    }
+
+    fn meter_cells(level: f32) -> usize {
+        let db = level_db(level);
+        (((db - METER_FLOOR_DB) / -METER_FLOOR_DB) * METER_WIDTH as f32).round() as usize
+    }
+
+    fn meter_cell_color(cell: usize) -> Color {
+        let db = METER_FLOOR_DB * (1.0 - (cell + 1) as f32 / METER_WIDTH as f32);
+        if db > METER_HOT_DB {
+            Color::Red
+        } else if db > METER_WARN_DB {
+            Color::Yellow
+        } else {
+            Color::Green
+        }
+    }
+
+    /// One-line level meter on a dB scale: solid cells up to the RMS level, shaded
+    /// cells up to the peak, a marker at the held peak, then the held peak in dBFS
+    /// and a latched clip flag.
+    pub fn meter_line(label: &str, meter: MeterDisplay) -> Line<'static> {
+        let rms = meter_cells(meter.rms);
+        let peak = meter_cells(meter.peak);
+        let hold = meter_cells(meter.hold);
+
+        let mut spans = vec![Span::raw(label.to_string())];
+        for cell in 0..METER_WIDTH {
+            let (symbol, color) = if cell < rms {
+                (METER_RMS_CELL, meter_cell_color(cell))
+            } else if cell < peak {
+                (METER_PEAK_CELL, meter_cell_color(cell))
+            } else if hold > 0 && cell + 1 == hold {
+                (METER_HOLD_CELL, meter_cell_color(cell))
+            } else {
+                (METER_EMPTY_CELL, Color::DarkGray)
+            };
+            spans.push(Span::styled(symbol, Style::default().fg(color)));
+        }
+
+        let hold_label = if meter.hold > 0.0 && level_db(meter.hold) > METER_FLOOR_DB {
+            format!(" {:>5.1} dB ", level_db(meter.hold))
+        } else {
+            " -inf dB ".to_string()
+        };
+        spans.push(Span::raw(hold_label));
+        if meter.clipped {
+            spans.push(Span::styled(
+                METER_CLIP_LABEL,
+                Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        Line::from(spans)
     }
 }
 
@@ -36,15 +36,76 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
         .playhead_seconds(app.session.sample_rate);
     let minutes = (playhead_secs / 60.0) as u32;
     let secs = playhead_secs % 60.0;
+    let musical = app.session.playhead_musical();
 
     let label = if is_playing {
-        format!("\u{25b6} Playing  {:02}:{:05.2}", minutes, secs)
+        format!("\u{25b6} Playing  {:02}:{:05.2}  {}", minutes, secs, musical)
     } else {
-        format!("\u{23f9} Stopped  {:02}:{:05.2}", minutes, secs)
+        format!("\u{23f9} Stopped  {:02}:{:05.2}  {}", minutes, secs, musical)
     };
 
+    let track_count = app.session.tracks.len();
+    let collapsed: Vec<bool> = app.session.tracks.iter().map(|t| t.collapsed).collect();
+    let tracks_area = main_chunks[2];
+    let visible = layout_config::visible_lanes(&collapsed, selected_track_idx, tracks_area.height);
+
+    let tempo = app.session.tempo.change_at_bar(musical.bar);
+    let mut transport_title = format!(
+        "Transport | Master: {:.0}% | FX: {} | {:.0} BPM {}/{}",
+        app.session.master.gain * 100.0,
+        app.session.master.fx_chain.len(),
+        tempo.bpm,
+        tempo.numerator,
+        tempo.denominator
+    );
+    let metronome = &app.session.metronome;
+    if metronome.enabled {
+        transport_title.push_str(&format!(
+            " | Click: {} {:.0}%",
+            metronome.sound.name(),
+            metronome.level * 100.0
+        ));
+    }
+    if metronome.count_in_bars > 0 {
+        transport_title.push_str(&format!(" | Count-in: {}", metronome.count_in_bars));
+    }
+    if let Some(region) = app.session.transport.loop_range() {
+        let sr = app.session.sample_rate as f64;
+        transport_title.push_str(&format!(
+            " | Loop: {:.1}s-{:.1}s",
+            region.start as f64 / sr,
+            region.end as f64 / sr
+        ));
+    }
+    if let Some(region) = app.session.transport.punch_range() {
+        let sr = app.session.sample_rate as f64;
+        transport_title.push_str(&format!(
+            " | Punch: {:.1}s-{:.1}s (pre-roll {:.1}s)",
+            region.start as f64 / sr,
+            region.end as f64 / sr,
+            app.session.transport.pre_roll_seconds
+        ));
+    }
+    if visible.len() < track_count {
+        transport_title.push_str(&format!(
+            " | Tracks {}-{} of {}",
+            visible.start + 1,
+            visible.end,
+            track_count
+        ));
+    }
+
+    let master_meter = layout_config::meter_line(
+        layout_config::METER_MASTER_LABEL,
+        app.session.master.meter.display(),
+    );
     let gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title("Transport"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(transport_title)
+                .title_bottom(master_meter),
+        )
         .gauge_style(Style::default().fg(if is_playing {
             Color::Green
         } else {
@@ -60,22 +121,33 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
         .style(Style::default().fg(Color::Gray));
     f.render_widget(instructions, main_chunks[1]);
 
-    let track_count = app.session.tracks.len();
-    let constraints = layout_config::get_lane_constraints(track_count);
+    // Bars/beats grid for the visible part of the timeline, shared by every lane
+    let timeline_samples = app.session.sample_rate as u64 * layout_config::TIMELINE_SECONDS;
+    let grid = if app.show_grid {
+        let max_lines = (tracks_area.width / layout_config::GRID_MIN_CELLS_PER_BEAT) as usize;
+        app.session.tempo.grid_lines(
+            scroll_offset..scroll_offset + timeline_samples,
+            app.session.sample_rate,
+            max_lines,
+        )
+    } else {
+        Vec::new()
+    };
+
+    let constraints =
+        layout_config::get_lane_constraints(&collapsed[visible.clone()], tracks_area.height);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(constraints)
-        .split(main_chunks[2]);
+        .split(tracks_area);
 
-    for (i, chunk) in chunks.iter().enumerate() {
-        if i >= track_count {
-            break;
-        }
+    for (i, chunk) in visible.zip(chunks.iter()) {
         let track = &app.session.tracks[i];
         let is_selected = selected_track_idx == i;
+        let is_audible = app.session.is_audible(i);
 
         let border_color =
-            if app.session.transport.is_playing() && !track.muted && !track.clips.is_empty() {
+            if app.session.transport.is_playing() && is_audible && !track.clips.is_empty() {
                 layout_config::SELECTED_BORDER // Show yellow when actively playing
             } else if track.state == crate::track::TrackState::Recording && is_selected {
                 layout_config::RECORDING_SELECTED_BORDER
@@ -97,11 +169,46 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
             layout_config::LANE_STATUS_ARMED
         } else if track.muted {
             layout_config::LANE_STATUS_MUTED
+        } else if !is_audible {
+            layout_config::LANE_STATUS_SOLOED_OUT
+        } else if track.is_group {
+            layout_config::LANE_STATUS_GROUP
         } else {
             layout_config::LANE_STATUS_ACTIVE
         };
 
-        let title = layout_config::format_lane_title(&track.name, track.volume, track.input_channel, status);
+        let name =
+            layout_config::lane_name(&track.name, app.session.track_depth(i), track.is_group);
+        let title = layout_config::format_lane_title(
+            &name,
+            track.volume,
+            track.pan,
+            track.input_channel,
+            track.solo,
+            track.solo_safe,
+            status,
+        ) + &layout_config::format_take_label(track.selected_take, track.takes.len());
+        let automation_target = track.viewed_automation();
+        let automation_lane = track.lane(&automation_target);
+        let title = title
+            + &layout_config::format_automation_label(
+                track.automation_mode.name(),
+                &track.automation_target_name(&automation_target),
+                automation_lane.map_or(0, |l| l.points.len()),
+            );
+
+        // Armed tracks meter what they are about to record, everything else its output
+        let meter = if track.is_armed() {
+            layout_config::meter_line(
+                layout_config::METER_INPUT_LABEL,
+                track.input_meter.display(),
+            )
+        } else {
+            layout_config::meter_line(
+                layout_config::METER_OUTPUT_LABEL,
+                track.output_meter.display(),
+            )
+        };
 
         let block = Block::default()
             .borders(Borders::ALL)
@@ -111,10 +218,10 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
                 BorderType::Plain
             })
             .border_style(Style::default().fg(border_color))
-            .title(title);
+            .title(title)
+            .title_bottom(meter);
 
         // Every track shows a timeline canvas with playhead
-        let sample_rate = app.session.sample_rate;
         let playhead_pos = app.session.transport.playhead_position;
         let is_recording = track.state == crate::track::TrackState::Recording;
         let rec_start_pos = track.recording_start_position;
@@ -129,20 +236,68 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
             None
         };
 
-        // Clip bounds with selection state: (start, end, is_selected_clip)
-        let clip_bounds: Vec<(f64, f64, bool)> = track
+        // Clip bounds with selection state: (start, end, is_selected_clip, is_muted,
+        // plays the selected take)
+        let selected_take = track.takes.get(track.selected_take);
+        let clip_bounds: Vec<(f64, f64, bool, bool, bool)> = track
             .clips
             .iter()
             .enumerate()
             .map(|(ci, c)| {
                 let start = c.starts_at as f64;
-                let end = (c.starts_at + c.wav_data.frame_count() as u64) as f64;
+                let end = c.end() as f64;
                 let sel = is_selected && selected_clip_idx == Some(ci);
-                (start, end, sel)
+                let comped = selected_take.is_some_and(|t| c.take.as_ref() == Some(&t.id));
+                (start, end, sel, c.muted, comped)
             })
             .collect();
+        // Fade ramps: (start, fade-in end, fade-out start, end)
+        let fades: Vec<(f64, f64, f64, f64)> = track
+            .clips
+            .iter()
+            .filter(|c| c.fade_in.frames > 0 || c.fade_out.frames > 0)
+            .map(|c| {
+                let fade_in = c.fade_in.frames.min(c.length);
+                let fade_out = c.fade_out.frames.min(c.length);
+                (
+                    c.starts_at as f64,
+                    (c.starts_at + fade_in) as f64,
+                    (c.end() - fade_out) as f64,
+                    c.end() as f64,
+                )
+            })
+            .collect();
+        // Crossfades where clips meet, drawn as an X over the overlap
+        let crossfades: Vec<(f64, f64)> = track
+            .junctions(app.session.sample_rate)
+            .iter()
+            .map(|j| (j.region.start as f64, j.region.end as f64))
+            .collect();
+        let take_bounds = selected_take.map(|t| (t.starts_at as f64, t.end() as f64));
+        // The shown automation lane: its curve sampled across the view and its points
+        let view_frames = (scroll_offset, scroll_offset + timeline_samples);
+        let mut automation_curve: Vec<(f64, f64)> = Vec::new();
+        let mut automation_points: Vec<(f64, f64)> = Vec::new();
+        if let Some(lane) = automation_lane {
+            let highest = lane
+                .points
+                .iter()
+                .map(|p| p.value)
+                .fold(track.static_value(&automation_target).unwrap_or(0.0), f32::max);
+            let y = |value| layout_config::automation_y(&automation_target, value, highest);
+            let steps = layout_config::AUTOMATION_DRAW_STEPS as u64;
+            for step in 0..=steps {
+                let frame = view_frames.0 + (view_frames.1 - view_frames.0) * step / steps;
+                if let Some(value) = lane.value_at(frame) {
+                    automation_curve.push((frame as f64, y(value)));
+                }
+            }
+            automation_points = lane.points.iter().map(|p| (p.frame as f64, y(p.value))).collect();
+        }
+        let loop_region = app.session.transport.loop_range();
+        let punch_region = app.session.transport.punch_range();
 
-        let timeline_samples = sample_rate as u64 * layout_config::TIMELINE_SECONDS;
+        let grid = &grid;
         let view_start = scroll_offset as f64;
         let view_end = (scroll_offset + timeline_samples) as f64;
 
@@ -160,10 +315,29 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
                     color: Color::DarkGray,
                 });
 
+                // Bars/beats grid
+                for line in grid {
+                    ctx.draw(&Line {
+                        x1: line.frame as f64,
+                        y1: -1.0,
+                        x2: line.frame as f64,
+                        y2: 1.0,
+                        color: if line.is_bar {
+                            layout_config::GRID_BAR_COLOR
+                        } else {
+                            layout_config::GRID_BEAT_COLOR
+                        },
+                    });
+                }
+
                 // Draw clip boundaries (box: left, right, top, bottom)
-                for &(start, end, is_clip_selected) in &clip_bounds {
+                for &(start, end, is_clip_selected, is_muted, is_comped) in &clip_bounds {
                     let clip_color = if is_clip_selected {
                         Color::Green
+                    } else if is_muted {
+                        layout_config::MUTED_CLIP_COLOR
+                    } else if is_comped {
+                        layout_config::TAKE_COLOR
                     } else {
                         Color::DarkGray
                     };
@@ -175,6 +349,20 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
                     ctx.draw(&Line { x1: end, y1: -0.95, x2: end, y2: 0.95, color: clip_color });
                 }
 
+                // Fade ramps inside the clip boxes
+                for &(start, fade_in_end, fade_out_start, end) in &fades {
+                    if fade_in_end > start {
+                        ctx.draw(&Line { x1: start, y1: -0.95, x2: fade_in_end, y2: 0.95, color: layout_config::FADE_COLOR });
+                    }
+                    if end > fade_out_start {
+                        ctx.draw(&Line { x1: fade_out_start, y1: 0.95, x2: end, y2: -0.95, color: layout_config::FADE_COLOR });
+                    }
+                }
+                for &(start, end) in &crossfades {
+                    ctx.draw(&Line { x1: start, y1: -0.95, x2: end, y2: 0.95, color: layout_config::XFADE_COLOR });
+                    ctx.draw(&Line { x1: start, y1: 0.95, x2: end, y2: -0.95, color: layout_config::XFADE_COLOR });
+                }
+
                 // Draw existing clips waveform
                 if let Some(ref waveform) = clips_waveform {
                     let spp = if clips_waveform_len > 0 {
@@ -184,7 +372,7 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
                     };
                     for (j, &(min, max)) in waveform.iter().enumerate() {
                         let x = j as f64 * spp;
-                        if !clip_bounds.iter().any(|&(s, e, _)| x >= s && x <= e) {
+                        if !clip_bounds.iter().any(|&(s, e, _, _, _)| x >= s && x <= e) {
                             continue;
                         }
                         let y_min = (min * layout_config::WAVEFORM_SENSITIVITY).clamp(-1.0, 1.0) as f64;
@@ -210,6 +398,63 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
                     }
                 }
 
+                // Automation lane over the clips, with a tick at each point
+                for pair in automation_curve.windows(2) {
+                    ctx.draw(&Line {
+                        x1: pair[0].0,
+                        y1: pair[0].1,
+                        x2: pair[1].0,
+                        y2: pair[1].1,
+                        color: layout_config::AUTOMATION_COLOR,
+                    });
+                }
+                for &(x, y) in &automation_points {
+                    ctx.draw(&Line {
+                        x1: x,
+                        y1: y - 0.1,
+                        x2: x,
+                        y2: y + 0.1,
+                        color: layout_config::AUTOMATION_COLOR,
+                    });
+                }
+
+                // Extent of the take lane comping acts on
+                if let Some((start, end)) = take_bounds {
+                    ctx.draw(&Line {
+                        x1: start,
+                        y1: -0.8,
+                        x2: end,
+                        y2: -0.8,
+                        color: layout_config::TAKE_COLOR,
+                    });
+                }
+
+                // Loop region boundaries
+                if let Some(region) = &loop_region {
+                    for x in [region.start as f64, region.end as f64] {
+                        ctx.draw(&Line {
+                            x1: x,
+                            y1: -1.0,
+                            x2: x,
+                            y2: 1.0,
+                            color: layout_config::LOOP_COLOR,
+                        });
+                    }
+                }
+
+                // Punch region boundaries
+                if let Some(region) = &punch_region {
+                    for x in [region.start as f64, region.end as f64] {
+                        ctx.draw(&Line {
+                            x1: x,
+                            y1: -1.0,
+                            x2: x,
+                            y2: 1.0,
+                            color: layout_config::PUNCH_COLOR,
+                        });
+                    }
+                }
+
                 // Draw playhead line (always visible, cyan)
                 ctx.draw(&Line {
                     x1: playhead_pos as f64,
@@ -1,13 +1,17 @@
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use std::time::Duration;
 
-use super::audio_preferences_screen::AudioPreferencesScreen;
+use super::audio_preferences_screen::{self, AudioPreferencesScreen};
+use super::aux_buses_screen::AuxBusesScreen;
 use super::daw_screen::DawScreen;
+use super::export_screen::{self, ExportScreen};
 use super::fx_chain_editor_screen::FxChainEditorScreen;
+use super::import_screen::ImportAudioScreen;
 use super::main_menu_screen::MainMenuScreen;
 use super::screen_trait::ScreenTrait;
 use super::{App, Screen};
 use crate::project;
+use crate::session::FxTarget;
 
 mod event_config {
     use crossterm::event::KeyCode;
@@ -34,6 +38,12 @@ impl AppEventHandler {
         // Check if playback has finished and reset transport state
         app.session.check_playback_status();
 
+        // Report an export that finished in the background
+        export_screen::check_export(app);
+
+        // Report a latency calibration once its clicks have played
+        audio_preferences_screen::check_calibration(app);
+
         // Auto-scroll timeline to follow playhead during playback/recording
         if app.session.transport.is_playing() {
             if let Screen::Daw {
@@ -67,12 +77,51 @@ impl AppEventHandler {
                 return Ok(false);
             }
 
-            // Shift+E: open FX Chain Editor (only in DAW screen)
+            // Ctrl+O: import an audio file onto the selected track (only in DAW screen)
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('o') {
+                if let Screen::Daw {
+                    selected_track,
+                    scroll_offset,
+                    ..
+                } = app.screen
+                {
+                    ImportAudioScreen::open(app, selected_track, scroll_offset);
+                }
+                return Ok(false);
+            }
+
+            // Ctrl+Z / Ctrl+Y: undo and redo session edits from the editing screens
+            if key.modifiers.contains(KeyModifiers::CONTROL)
+                && matches!(key.code, KeyCode::Char('z') | KeyCode::Char('y'))
+            {
+                if matches!(
+                    app.screen,
+                    Screen::Daw { .. } | Screen::AuxBuses { .. } | Screen::FxChainEditor { .. }
+                ) {
+                    Self::undo_redo(app, key.code == KeyCode::Char('y'));
+                }
+                return Ok(false);
+            }
+
+            // Shift+E: open FX Chain Editor (DAW screen for tracks, aux screen for buses)
+            // Shift+F: open the master FX chain from the DAW screen
             // Crossterm reports Shift+e as KeyCode::Char('E') (uppercase)
-            if key.code == KeyCode::Char('E') {
-                if let Screen::Daw { selected_track, .. } = app.screen {
+            if matches!(key.code, KeyCode::Char('E') | KeyCode::Char('F')) {
+                let target = match (key.code, &app.screen) {
+                    (KeyCode::Char('E'), Screen::Daw { selected_track, .. }) => {
+                        Some(FxTarget::Track(*selected_track))
+                    }
+                    (KeyCode::Char('E'), Screen::AuxBuses { selected_bus, .. })
+                        if *selected_bus < app.session.aux_buses.len() =>
+                    {
+                        Some(FxTarget::Aux(*selected_bus))
+                    }
+                    (KeyCode::Char('F'), Screen::Daw { .. }) => Some(FxTarget::Master),
+                    _ => None,
+                };
+                if let Some(target) = target {
                     app.screen = Screen::FxChainEditor {
-                        track_index: selected_track,
+                        target,
                         selected_effect: 0,
                         editing_param: None,
                         add_mode: false,
@@ -101,8 +150,14 @@ impl AppEventHandler {
                         ) {
                             return Self::route_to_screen_handler(app, key.code);
                         }
-                        // FxChainEditor handles its own Esc (returns to DAW)
-                        if matches!(app.screen, Screen::FxChainEditor { .. }) {
+                        // FxChainEditor, AuxBuses, Export and ImportAudio handle their own Esc
+                        if matches!(
+                            app.screen,
+                            Screen::FxChainEditor { .. }
+                                | Screen::AuxBuses { .. }
+                                | Screen::Export { .. }
+                                | Screen::ImportAudio { .. }
+                        ) {
                             return Self::route_to_screen_handler(app, key.code);
                         }
                         Self::handle_back_key(app);
@@ -137,6 +192,45 @@ impl AppEventHandler {
         }
     }
 
+    fn undo_redo(app: &mut App, redo: bool) {
+        let result = if redo {
+            app.session.redo().map(|name| format!("Redo: {}", name))
+        } else {
+            app.session.undo().map(|name| format!("Undo: {}", name))
+        };
+        app.status = result.unwrap_or_else(|e| e.to_string());
+
+        // The edit may have removed what the screen was pointing at
+        let track_count = app.session.tracks.len();
+        match app.screen {
+            Screen::Daw {
+                ref mut selected_track,
+                ref mut selected_clip,
+                ..
+            } => {
+                *selected_track = (*selected_track).min(track_count.saturating_sub(1));
+                *selected_clip = None;
+            }
+            Screen::FxChainEditor {
+                target,
+                ref mut selected_effect,
+                ..
+            } => match app.session.fx_chain(target) {
+                Some(chain) => {
+                    *selected_effect = (*selected_effect).min(chain.len().saturating_sub(1))
+                }
+                None => {
+                    app.screen = Screen::Daw {
+                        selected_track: 0,
+                        selected_clip: None,
+                        scroll_offset: 0,
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
     fn route_to_screen_handler(
         app: &mut App,
         key: KeyCode,
@@ -147,7 +241,10 @@ impl AppEventHandler {
             Screen::OpenProject { .. } => MainMenuScreen.handle_input(app, key),
             Screen::Daw { .. } => DawScreen.handle_input(app, key),
             Screen::AudioPreferences { .. } => AudioPreferencesScreen.handle_input(app, key),
+            Screen::AuxBuses { .. } => AuxBusesScreen.handle_input(app, key),
             Screen::FxChainEditor { .. } => FxChainEditorScreen.handle_input(app, key),
+            Screen::Export { .. } => ExportScreen.handle_input(app, key),
+            Screen::ImportAudio { .. } => ImportAudioScreen.handle_input(app, key),
         }
     }
 }
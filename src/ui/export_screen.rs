@@ -0,0 +1,342 @@
+use super::screen_trait::ScreenTrait;
+use super::{App, Screen};
+use crate::export::{ExportJob, ExportRange, ExportSettings, ExportSource, SAMPLE_RATES};
+use crate::wav::SampleFormat;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
+    Frame,
+};
+use std::path::{Path, PathBuf};
+
+mod layout_config {
+    use ratatui::style::Color;
+
+    pub const TITLE: &str = "Export";
+    pub const SELECTED_FG: Color = Color::Black;
+    pub const SELECTED_BG: Color = Color::Green;
+    pub const DEFAULT_FG: Color = Color::White;
+    pub const DISABLED_FG: Color = Color::DarkGray;
+    pub const PROGRESS_COLOR: Color = Color::Green;
+    pub const ROWS: [&str; 10] = [
+        "Source",
+        "Range",
+        "Start",
+        "End",
+        "Sample rate",
+        "Bit depth",
+        "Channels",
+        "Tail",
+        "Normalise",
+        "Export",
+    ];
+    pub const START_ROW: usize = 2;
+    pub const END_ROW: usize = 3;
+    pub const EXPORT_ROW: usize = 9;
+    // Start and end move by this much, and the range is never shorter
+    pub const RANGE_STEP_SECONDS: f64 = 1.0;
+    pub const INSTRUCTIONS: &str =
+        "Up/Down: Select | Left/Right: Change | Enter: Change / Export | m: Measure Loudness | c: Cancel Export | Esc: Back";
+
+    pub fn format_time(seconds: f64) -> String {
+        let minutes = (seconds / 60.0).floor();
+        format!("{:02}:{:05.2}", minutes, seconds - minutes * 60.0)
+    }
+}
+
+pub struct ExportScreen;
+
+impl ExportScreen {
+    /// Open the dialog with the settings used last, or the session's format.
+    pub fn open(app: &mut App, selected_track: usize, scroll_offset: u64) {
+        if app.export_settings.is_none() {
+            app.export_settings = Some(ExportSettings::new(
+                app.session.sample_rate,
+                app.session.channels,
+            ));
+        }
+        app.screen = Screen::Export {
+            selected_row: 0,
+            selected_track,
+            scroll_offset,
+        };
+    }
+}
+
+fn settings(app: &App) -> ExportSettings {
+    app.export_settings
+        .clone()
+        .unwrap_or_else(|| ExportSettings::new(app.session.sample_rate, app.session.channels))
+}
+
+fn row_value(app: &App, settings: &ExportSettings, row: usize) -> String {
+    let frames = app.session.export_frames(&settings.range);
+    let seconds =
+        |frame: u64| layout_config::format_time(frame as f64 / app.session.sample_rate as f64);
+    match row {
+        0 => settings.source.name().to_string(),
+        1 => settings.range.name().to_string(),
+        layout_config::START_ROW => frames.map_or("-".to_string(), |f| seconds(f.start)),
+        layout_config::END_ROW => frames.map_or("-".to_string(), |f| seconds(f.end)),
+        4 => format!("{} Hz", settings.sample_rate),
+        5 => settings.format.name().to_string(),
+        6 => match settings.channels {
+            1 => "Mono".to_string(),
+            2 => "Stereo".to_string(),
+            n => format!("{} channels", n),
+        },
+        7 => settings.tail_label(),
+        8 => settings.normalize.label(),
+        _ => String::new(),
+    }
+}
+
+/// Change the setting on `row`, forwards or backwards for start and end.
+fn change(app: &mut App, settings: &mut ExportSettings, row: usize, forward: bool) {
+    match row {
+        0 => settings.source = settings.source.next(),
+        1 => {
+            settings.range = match settings.range {
+                ExportRange::Project => ExportRange::Loop,
+                ExportRange::Loop => {
+                    let frames = app
+                        .session
+                        .export_frames(&ExportRange::Project)
+                        .unwrap_or(0..app.session.sample_rate as u64);
+                    ExportRange::Custom(frames)
+                }
+                ExportRange::Custom(_) => ExportRange::Project,
+            }
+        }
+        layout_config::START_ROW | layout_config::END_ROW => {
+            // Moving either end turns whatever range is shown into a custom one
+            let step = (layout_config::RANGE_STEP_SECONDS * app.session.sample_rate as f64) as u64;
+            let mut frames = app
+                .session
+                .export_frames(&settings.range)
+                .unwrap_or(0..step);
+            if row == layout_config::START_ROW {
+                frames.start = if forward {
+                    (frames.start + step).min(frames.end.saturating_sub(step))
+                } else {
+                    frames.start.saturating_sub(step)
+                };
+            } else {
+                frames.end = if forward {
+                    frames.end + step
+                } else {
+                    frames.end.saturating_sub(step).max(frames.start + step)
+                };
+            }
+            settings.range = ExportRange::Custom(frames);
+        }
+        4 => {
+            let idx = SAMPLE_RATES
+                .iter()
+                .position(|&r| r == settings.sample_rate)
+                .map_or(0, |i| (i + 1) % SAMPLE_RATES.len());
+            settings.sample_rate = SAMPLE_RATES[idx];
+        }
+        5 => {
+            let idx = SampleFormat::ALL
+                .iter()
+                .position(|&f| f == settings.format)
+                .unwrap_or(0);
+            settings.format = SampleFormat::ALL[(idx + 1) % SampleFormat::ALL.len()];
+        }
+        6 => settings.channels = if settings.channels == 1 { 2 } else { 1 },
+        7 => settings.tail_seconds = settings.next_tail(),
+        8 => settings.normalize = settings.normalize.next(),
+        _ => {}
+    }
+}
+
+/// `dir/<base>[.<extension>]`, numbered if that already exists.
+fn unique_path(dir: &Path, base: &str, extension: Option<&str>) -> PathBuf {
+    let with_extension = |name: String| match extension {
+        Some(ext) => format!("{}.{}", name, ext),
+        None => name,
+    };
+    let first = dir.join(with_extension(base.to_string()));
+    if !first.exists() {
+        return first;
+    }
+    (1u32..)
+        .map(|n| dir.join(with_extension(format!("{}_{}", base, n))))
+        .find(|p| !p.exists())
+        .unwrap_or(first)
+}
+
+fn start_export(app: &mut App, settings: ExportSettings) {
+    if app.export_job.is_some() {
+        app.status = "An export is already running".to_string();
+        return;
+    }
+    let renders = match app.session.export_renders(&settings) {
+        Ok(renders) => renders,
+        Err(e) => {
+            app.status = e;
+            return;
+        }
+    };
+    let dir = app
+        .project_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+    let name = &app.session.name;
+    let destination = match settings.source {
+        ExportSource::Mix => unique_path(&dir, &format!("{}_mix", name), Some("wav")),
+        _ => unique_path(&dir, &format!("{}_stems", name), None),
+    };
+    app.status = format!("Exporting to {}", destination.display());
+    app.export_job = Some(ExportJob::start(renders, settings, destination));
+}
+
+/// Report a finished export. Called every tick so exports finish from any screen.
+pub fn check_export(app: &mut App) {
+    if !app.export_job.as_ref().is_some_and(|job| job.is_finished()) {
+        return;
+    }
+    if let Some(job) = app.export_job.take() {
+        app.status = match job.finish() {
+            Ok(paths) if paths.len() == 1 => format!("Exported to {}", paths[0].display()),
+            Ok(paths) => match paths.first().and_then(|p| p.parent()) {
+                Some(dir) => format!("Exported {} stems to {}", paths.len(), dir.display()),
+                None => format!("Exported {} stems", paths.len()),
+            },
+            Err(e) => format!("Export error: {}", e),
+        };
+    }
+}
+
+impl ScreenTrait for ExportScreen {
+    fn render(&self, f: &mut Frame, app: &App, area: Rect) {
+        let Screen::Export { selected_row, .. } = app.screen else {
+            return;
+        };
+        let settings = settings(app);
+        let custom = matches!(settings.range, ExportRange::Custom(_));
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(area);
+
+        let items: Vec<ListItem> = layout_config::ROWS
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let style = if i == selected_row {
+                    Style::default()
+                        .fg(layout_config::SELECTED_FG)
+                        .bg(layout_config::SELECTED_BG)
+                } else if !custom && (i == layout_config::START_ROW || i == layout_config::END_ROW)
+                {
+                    Style::default().fg(layout_config::DISABLED_FG)
+                } else {
+                    Style::default().fg(layout_config::DEFAULT_FG)
+                };
+                let text = if i == layout_config::EXPORT_ROW {
+                    format!("[ {} ]", label)
+                } else {
+                    format!("{:<12} {}", label, row_value(app, &settings, i))
+                };
+                ListItem::new(text).style(style)
+            })
+            .collect();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(layout_config::TITLE),
+        );
+        f.render_widget(list, chunks[0]);
+
+        let (ratio, label) = match &app.export_job {
+            Some(job) => {
+                let fraction = job.progress().fraction();
+                let label = if job.progress().is_cancelled() {
+                    "Cancelling...".to_string()
+                } else {
+                    format!("Rendering {:.0}%", fraction * 100.0)
+                };
+                (fraction, label)
+            }
+            None => (0.0, "Idle".to_string()),
+        };
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Progress"))
+            .gauge_style(Style::default().fg(layout_config::PROGRESS_COLOR))
+            .ratio(ratio)
+            .label(label);
+        f.render_widget(gauge, chunks[1]);
+
+        let instructions = Paragraph::new(layout_config::INSTRUCTIONS)
+            .style(Style::default().fg(Color::Gray))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(instructions, chunks[2]);
+    }
+
+    fn handle_input(
+        &self,
+        app: &mut App,
+        key: KeyCode,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Screen::Export {
+            selected_row,
+            selected_track,
+            scroll_offset,
+        } = app.screen
+        else {
+            return Ok(false);
+        };
+        let mut settings = settings(app);
+
+        match key {
+            KeyCode::Up => {
+                app.screen = Screen::Export {
+                    selected_row: selected_row.saturating_sub(1),
+                    selected_track,
+                    scroll_offset,
+                };
+            }
+            KeyCode::Down => {
+                app.screen = Screen::Export {
+                    selected_row: (selected_row + 1).min(layout_config::EXPORT_ROW),
+                    selected_track,
+                    scroll_offset,
+                };
+            }
+            KeyCode::Enter if selected_row == layout_config::EXPORT_ROW => {
+                start_export(app, settings.clone());
+            }
+            KeyCode::Enter | KeyCode::Right => change(app, &mut settings, selected_row, true),
+            KeyCode::Left => change(app, &mut settings, selected_row, false),
+            KeyCode::Char('m') => {
+                app.status = format!("Mix: {}", app.session.mix_loudness().summary());
+            }
+            KeyCode::Char('c') => match &app.export_job {
+                Some(job) => {
+                    job.progress().cancel();
+                    app.status = "Cancelling export".to_string();
+                }
+                None => app.status = "No export running".to_string(),
+            },
+            KeyCode::Esc => {
+                app.screen = Screen::Daw {
+                    selected_track,
+                    scroll_offset,
+                    selected_clip: None,
+                };
+            }
+            _ => {}
+        }
+        app.export_settings = Some(settings);
+        Ok(false)
+    }
+}
@@ -1,6 +1,7 @@
 use super::screen_trait::ScreenTrait;
 use super::{App, Screen};
 use crate::effects::EffectType;
+use crate::session::FxTarget;
 use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
@@ -14,35 +15,35 @@ use strum::IntoEnumIterator;
 pub struct FxChainEditorScreen;
 
 // Helper to extract state from Screen::FxChainEditor
-fn get_state(app: &App) -> (usize, usize, Option<usize>, bool, usize) {
+fn get_state(app: &App) -> (FxTarget, usize, Option<usize>, bool, usize) {
     match app.screen {
         Screen::FxChainEditor {
-            track_index,
+            target,
             selected_effect,
             editing_param,
             add_mode,
             add_mode_selected,
         } => (
-            track_index,
+            target,
             selected_effect,
             editing_param,
             add_mode,
             add_mode_selected,
         ),
-        _ => (0, 0, None, false, 0),
+        _ => (FxTarget::Track(0), 0, None, false, 0),
     }
 }
 
 fn set_state(
     app: &mut App,
-    track_index: usize,
+    target: FxTarget,
     selected_effect: usize,
     editing_param: Option<usize>,
     add_mode: bool,
     add_mode_selected: usize,
 ) {
     app.screen = Screen::FxChainEditor {
-        track_index,
+        target,
         selected_effect,
         editing_param,
         add_mode,
@@ -52,15 +53,15 @@ fn set_state(
 
 impl ScreenTrait for FxChainEditorScreen {
     fn render(&self, f: &mut Frame, app: &App, area: Rect) {
-        let (track_index, selected_effect, editing_param, add_mode, add_mode_selected) =
+        let (target, selected_effect, editing_param, add_mode, add_mode_selected) =
             get_state(app);
 
-        // Get track name for title
-        let track_name = app
+        // Get track or bus name for title
+        let target_name = app
             .session
-            .get_track(track_index)
-            .map(|t| t.name.clone())
-            .unwrap_or_else(|| "Unknown".to_string());
+            .fx_target_name(target)
+            .unwrap_or("Unknown")
+            .to_string();
 
         // Main layout: content area + instructions bar
         let main_chunks = Layout::default()
@@ -85,9 +86,9 @@ impl ScreenTrait for FxChainEditorScreen {
                 f,
                 app,
                 content_chunks[0],
-                track_index,
+                target,
                 selected_effect,
-                &track_name,
+                &target_name,
             );
 
             // Render parameters panel
@@ -95,7 +96,7 @@ impl ScreenTrait for FxChainEditorScreen {
                 f,
                 app,
                 content_chunks[1],
-                track_index,
+                target,
                 selected_effect,
                 editing_param,
             );
@@ -110,77 +111,69 @@ impl ScreenTrait for FxChainEditorScreen {
         app: &mut App,
         key: KeyCode,
     ) -> Result<bool, Box<dyn std::error::Error>> {
-        let (track_index, selected_effect, editing_param, add_mode, add_mode_selected) =
+        let (target, selected_effect, editing_param, add_mode, add_mode_selected) =
             get_state(app);
 
         if add_mode {
             return self.handle_add_mode_input(
                 app,
                 key,
-                track_index,
+                target,
                 selected_effect,
                 add_mode_selected,
             );
         }
 
-        if editing_param.is_some() {
+        if let Some(editing_param) = editing_param {
             return self.handle_param_edit_input(
                 app,
                 key,
-                track_index,
+                target,
                 selected_effect,
-                editing_param.unwrap(),
+                editing_param,
             );
         }
 
         // Normal mode: navigating effects list
-        let fx_chain_len = app
-            .session
-            .get_track(track_index)
-            .map(|t| t.fx_chain.len())
-            .unwrap_or(0);
+        let fx_chain_len = app.session.fx_chain(target).map_or(0, |c| c.len());
 
         // Total items: "Add Effect" + existing effects
         let total_items = 1 + fx_chain_len;
 
         match key {
-            KeyCode::Up => {
-                if selected_effect > 0 {
-                    set_state(
-                        app,
-                        track_index,
-                        selected_effect - 1,
-                        None,
-                        false,
-                        add_mode_selected,
-                    );
-                }
+            KeyCode::Up if selected_effect > 0 => {
+                set_state(
+                    app,
+                    target,
+                    selected_effect - 1,
+                    None,
+                    false,
+                    add_mode_selected,
+                );
             }
-            KeyCode::Down => {
-                if selected_effect < total_items.saturating_sub(1) {
-                    set_state(
-                        app,
-                        track_index,
-                        selected_effect + 1,
-                        None,
-                        false,
-                        add_mode_selected,
-                    );
-                }
+            KeyCode::Down if selected_effect < total_items.saturating_sub(1) => {
+                set_state(
+                    app,
+                    target,
+                    selected_effect + 1,
+                    None,
+                    false,
+                    add_mode_selected,
+                );
             }
             KeyCode::Enter => {
                 if selected_effect == 0 {
                     // "Add Effect" selected - enter add mode
-                    set_state(app, track_index, selected_effect, None, true, 0);
+                    set_state(app, target, selected_effect, None, true, 0);
                 } else {
                     // Effect selected - enter parameter editing mode if it has params
                     let effect_idx = selected_effect - 1;
-                    if let Some(track) = app.session.get_track(track_index) {
-                        if let Some(effect) = track.fx_chain.get(effect_idx) {
+                    if let Some(chain) = app.session.fx_chain(target) {
+                        if let Some(effect) = chain.get(effect_idx) {
                             if !effect.parameters().is_empty() {
                                 set_state(
                                     app,
-                                    track_index,
+                                    target,
                                     selected_effect,
                                     Some(0),
                                     false,
@@ -191,47 +184,48 @@ impl ScreenTrait for FxChainEditorScreen {
                     }
                 }
             }
-            KeyCode::Delete | KeyCode::Backspace => {
-                if selected_effect > 0 {
-                    let effect_idx = selected_effect - 1;
-                    if app
-                        .session
-                        .remove_effect_from_track(track_index, effect_idx)
-                        .is_ok()
-                    {
-                        // Adjust selection if we removed the last effect
-                        let new_len = app
-                            .session
-                            .get_track(track_index)
-                            .map(|t| t.fx_chain.len())
-                            .unwrap_or(0);
-                        let new_selected = if selected_effect > new_len {
-                            new_len
-                        } else {
-                            selected_effect
-                        };
-                        set_state(
-                            app,
-                            track_index,
-                            new_selected,
-                            None,
-                            false,
-                            add_mode_selected,
-                        );
-                        app.status = "Effect removed".to_string();
-                    }
+            KeyCode::Delete | KeyCode::Backspace if selected_effect > 0 => {
+                let effect_idx = selected_effect - 1;
+                if app.session.remove_effect(target, effect_idx).is_ok() {
+                    // Adjust selection if we removed the last effect
+                    let new_len = app.session.fx_chain(target).map_or(0, |c| c.len());
+                    let new_selected = if selected_effect > new_len {
+                        new_len
+                    } else {
+                        selected_effect
+                    };
+                    set_state(
+                        app,
+                        target,
+                        new_selected,
+                        None,
+                        false,
+                        add_mode_selected,
+                    );
+                    app.status = "Effect removed".to_string();
                 }
             }
             KeyCode::Char('a') | KeyCode::Char('A') => {
                 // Enter add mode
-                set_state(app, track_index, selected_effect, None, true, 0);
+                set_state(app, target, selected_effect, None, true, 0);
             }
             KeyCode::Esc => {
-                // Return to DAW screen
-                app.screen = Screen::Daw {
-                    selected_track: track_index,
-                    scroll_offset: 0,
-                    selected_clip: None,
+                // Return to the screen the chain was opened from
+                app.screen = match target {
+                    FxTarget::Track(selected_track) => Screen::Daw {
+                        selected_track,
+                        scroll_offset: 0,
+                        selected_clip: None,
+                    },
+                    FxTarget::Master => Screen::Daw {
+                        selected_track: 0,
+                        scroll_offset: 0,
+                        selected_clip: None,
+                    },
+                    FxTarget::Aux(selected_bus) => Screen::AuxBuses {
+                        selected_bus,
+                        selected_row: 0,
+                    },
                 };
             }
             _ => {}
@@ -247,9 +241,9 @@ impl FxChainEditorScreen {
         f: &mut Frame,
         app: &App,
         area: Rect,
-        track_index: usize,
+        target: FxTarget,
         selected_effect: usize,
-        track_name: &str,
+        target_name: &str,
     ) {
         let mut items: Vec<ListItem> = Vec::new();
 
@@ -265,8 +259,8 @@ impl FxChainEditorScreen {
         )])));
 
         // Existing effects
-        if let Some(track) = app.session.get_track(track_index) {
-            for (i, effect) in track.fx_chain.iter().enumerate() {
+        if let Some(chain) = app.session.fx_chain(target) {
+            for (i, effect) in chain.iter().enumerate() {
                 let effect_index = i + 1; // +1 because "Add Effect" is at 0
                 let is_selected = selected_effect == effect_index;
 
@@ -287,7 +281,7 @@ impl FxChainEditorScreen {
         let list = List::new(items).block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(format!("FX Chain - {}", track_name)),
+                .title(format!("FX Chain - {}", target_name)),
         );
 
         f.render_widget(list, area);
@@ -298,7 +292,7 @@ impl FxChainEditorScreen {
         f: &mut Frame,
         app: &App,
         area: Rect,
-        track_index: usize,
+        target: FxTarget,
         selected_effect: usize,
         editing_param: Option<usize>,
     ) {
@@ -312,8 +306,8 @@ impl FxChainEditorScreen {
             ))));
         } else {
             let effect_idx = selected_effect - 1;
-            if let Some(track) = app.session.get_track(track_index) {
-                if let Some(effect) = track.fx_chain.get(effect_idx) {
+            if let Some(chain) = app.session.fx_chain(target) {
+                if let Some(effect) = chain.get(effect_idx) {
                     let params = effect.parameters();
                     if params.is_empty() {
                         items.push(ListItem::new(Line::from(Span::styled(
@@ -340,11 +334,8 @@ impl FxChainEditorScreen {
             }
         }
 
-        let list = List::new(items).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Parameters"),
-        );
+        let list =
+            List::new(items).block(Block::default().borders(Borders::ALL).title("Parameters"));
 
         f.render_widget(list, area);
     }
@@ -393,11 +384,8 @@ impl FxChainEditorScreen {
             "".to_string()
         };
 
-        let paragraph = Paragraph::new(info).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Effect Info"),
-        );
+        let paragraph =
+            Paragraph::new(info).block(Block::default().borders(Borders::ALL).title("Effect Info"));
 
         f.render_widget(paragraph, area);
     }
@@ -424,7 +412,7 @@ impl FxChainEditorScreen {
         &self,
         app: &mut App,
         key: KeyCode,
-        track_index: usize,
+        target: FxTarget,
         selected_effect: usize,
         add_mode_selected: usize,
     ) -> Result<bool, Box<dyn std::error::Error>> {
@@ -432,53 +420,44 @@ impl FxChainEditorScreen {
         let total_types = effect_types.len();
 
         match key {
-            KeyCode::Up => {
-                if add_mode_selected > 0 {
-                    set_state(
-                        app,
-                        track_index,
-                        selected_effect,
-                        None,
-                        true,
-                        add_mode_selected - 1,
-                    );
-                }
+            KeyCode::Up if add_mode_selected > 0 => {
+                set_state(
+                    app,
+                    target,
+                    selected_effect,
+                    None,
+                    true,
+                    add_mode_selected - 1,
+                );
             }
-            KeyCode::Down => {
-                if add_mode_selected < total_types.saturating_sub(1) {
-                    set_state(
-                        app,
-                        track_index,
-                        selected_effect,
-                        None,
-                        true,
-                        add_mode_selected + 1,
-                    );
-                }
+            KeyCode::Down if add_mode_selected < total_types.saturating_sub(1) => {
+                set_state(
+                    app,
+                    target,
+                    selected_effect,
+                    None,
+                    true,
+                    add_mode_selected + 1,
+                );
             }
             KeyCode::Enter => {
                 // Add the selected effect type
                 if let Some(et) = effect_types.get(add_mode_selected) {
                     let effect = et.create_default();
-                    if app
-                        .session
-                        .add_effect_to_track(track_index, effect)
-                        .is_ok()
-                    {
-                        // Exit add mode and select the newly added effect
-                        let new_len = app
-                            .session
-                            .get_track(track_index)
-                            .map(|t| t.fx_chain.len())
-                            .unwrap_or(0);
-                        set_state(app, track_index, new_len, None, false, 0);
-                        app.status = format!("Added {}", et.name());
+                    match app.session.add_effect(target, effect) {
+                        Ok(()) => {
+                            // Exit add mode and select the newly added effect
+                            let new_len = app.session.fx_chain(target).map_or(0, |c| c.len());
+                            set_state(app, target, new_len, None, false, 0);
+                            app.status = format!("Added {}", et.name());
+                        }
+                        Err(e) => app.status = format!("Cannot add effect: {}", e),
                     }
                 }
             }
             KeyCode::Esc => {
                 // Exit add mode
-                set_state(app, track_index, selected_effect, None, false, 0);
+                set_state(app, target, selected_effect, None, false, 0);
             }
             _ => {}
         }
@@ -490,7 +469,7 @@ impl FxChainEditorScreen {
         &self,
         app: &mut App,
         key: KeyCode,
-        track_index: usize,
+        target: FxTarget,
         selected_effect: usize,
         editing_param: usize,
     ) -> Result<bool, Box<dyn std::error::Error>> {
@@ -499,37 +478,33 @@ impl FxChainEditorScreen {
         // Get current parameters
         let params = app
             .session
-            .get_track(track_index)
-            .and_then(|t| t.fx_chain.get(effect_idx))
+            .fx_chain(target)
+            .and_then(|c| c.get(effect_idx))
             .map(|e| e.parameters())
             .unwrap_or_default();
 
         let total_params = params.len();
 
         match key {
-            KeyCode::Up => {
-                if editing_param > 0 {
-                    set_state(
-                        app,
-                        track_index,
-                        selected_effect,
-                        Some(editing_param - 1),
-                        false,
-                        0,
-                    );
-                }
+            KeyCode::Up if editing_param > 0 => {
+                set_state(
+                    app,
+                    target,
+                    selected_effect,
+                    Some(editing_param - 1),
+                    false,
+                    0,
+                );
             }
-            KeyCode::Down => {
-                if editing_param < total_params.saturating_sub(1) {
-                    set_state(
-                        app,
-                        track_index,
-                        selected_effect,
-                        Some(editing_param + 1),
-                        false,
-                        0,
-                    );
-                }
+            KeyCode::Down if editing_param < total_params.saturating_sub(1) => {
+                set_state(
+                    app,
+                    target,
+                    selected_effect,
+                    Some(editing_param + 1),
+                    false,
+                    0,
+                );
             }
             KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Right => {
                 // Increment parameter value
@@ -539,7 +514,7 @@ impl FxChainEditorScreen {
                         if app
                             .session
                             .update_effect_param(
-                                track_index,
+                                target,
                                 effect_idx,
                                 param_name,
                                 &new_value.to_string(),
@@ -551,7 +526,7 @@ impl FxChainEditorScreen {
                     } else if let Ok(current) = param_value.parse::<f64>() {
                         let new_value = current + 0.1;
                         let _ = app.session.update_effect_param(
-                            track_index,
+                            target,
                             effect_idx,
                             param_name,
                             &format!("{:.1}", new_value),
@@ -566,7 +541,7 @@ impl FxChainEditorScreen {
                         let new_value = current - 1;
                         if new_value >= 0 {
                             let _ = app.session.update_effect_param(
-                                track_index,
+                                target,
                                 effect_idx,
                                 param_name,
                                 &new_value.to_string(),
@@ -575,7 +550,7 @@ impl FxChainEditorScreen {
                     } else if let Ok(current) = param_value.parse::<f64>() {
                         let new_value = (current - 0.1).max(0.0);
                         let _ = app.session.update_effect_param(
-                            track_index,
+                            target,
                             effect_idx,
                             param_name,
                             &format!("{:.1}", new_value),
@@ -585,7 +560,7 @@ impl FxChainEditorScreen {
             }
             KeyCode::Esc | KeyCode::Enter => {
                 // Exit parameter editing mode
-                set_state(app, track_index, selected_effect, None, false, 0);
+                set_state(app, target, selected_effect, None, false, 0);
             }
             _ => {}
         }
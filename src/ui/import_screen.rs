@@ -0,0 +1,237 @@
+use super::screen_trait::ScreenTrait;
+use super::{App, Screen};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod layout_config {
+    use ratatui::style::Color;
+
+    pub const TITLE: &str = "Import Audio";
+    pub const SELECTED_FG: Color = Color::Black;
+    pub const SELECTED_BG: Color = Color::Green;
+    pub const DIR_FG: Color = Color::Cyan;
+    pub const FILE_FG: Color = Color::White;
+    pub const PARENT_LABEL: &str = "..";
+    pub const DIR_SUFFIX: &str = "/";
+    pub const INSTRUCTIONS: &str =
+        "Up/Down: Select | Enter: Open Folder / Import at Playhead | Backspace: Parent Folder | Esc: Back";
+    // Files the browser lists
+    pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "wave"];
+}
+
+pub struct ImportAudioScreen;
+
+impl ImportAudioScreen {
+    /// Open the browser for importing onto `track`, in the folder audio was last
+    /// imported from.
+    pub fn open(app: &mut App, track: usize, scroll_offset: u64) {
+        let dir = app
+            .import_dir
+            .clone()
+            .filter(|d| d.is_dir())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        app.screen = Screen::ImportAudio {
+            track,
+            scroll_offset,
+            entries: list_entries(&dir),
+            dir,
+            selected: 0,
+        };
+    }
+}
+
+/// The parent folder first, then sub-folders and audio files by name. Hidden entries
+/// are left out.
+fn list_entries(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    if let Ok(read) = fs::read_dir(dir) {
+        for entry in read.flatten() {
+            let path = entry.path();
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+            } else if is_audio_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    dirs.sort();
+    files.sort();
+    dir.parent()
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(dirs)
+        .chain(files)
+        .collect()
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+        layout_config::AUDIO_EXTENSIONS
+            .iter()
+            .any(|ext| e.eq_ignore_ascii_case(ext))
+    })
+}
+
+fn entry_label(dir: &Path, entry: &Path) -> String {
+    if dir.parent() == Some(entry) {
+        return layout_config::PARENT_LABEL.to_string();
+    }
+    let name = entry
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if entry.is_dir() {
+        name + layout_config::DIR_SUFFIX
+    } else {
+        name
+    }
+}
+
+fn show_folder(app: &mut App, folder: PathBuf) {
+    if let Screen::ImportAudio {
+        ref mut dir,
+        ref mut entries,
+        ref mut selected,
+        ..
+    } = app.screen
+    {
+        *entries = list_entries(&folder);
+        *dir = folder;
+        *selected = 0;
+    }
+}
+
+fn back_to_daw(app: &mut App, selected_clip: Option<usize>) {
+    if let Screen::ImportAudio {
+        track,
+        scroll_offset,
+        ..
+    } = app.screen
+    {
+        app.screen = Screen::Daw {
+            selected_track: track,
+            scroll_offset,
+            selected_clip,
+        };
+    }
+}
+
+fn import(app: &mut App, track: usize, path: &Path) {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // The project keeps its own copy of the audio
+    match app
+        .session
+        .import_audio(track, path, app.project_dir.as_deref())
+    {
+        Ok(clip_idx) => {
+            app.import_dir = path.parent().map(Path::to_path_buf);
+            app.status = format!("Imported {} to track {}", name, track + 1);
+            back_to_daw(app, Some(clip_idx));
+        }
+        Err(e) => app.status = format!("Cannot import {}: {}", name, e),
+    }
+}
+
+impl ScreenTrait for ImportAudioScreen {
+    fn render(&self, f: &mut Frame, app: &App, area: Rect) {
+        let Screen::ImportAudio {
+            ref dir,
+            ref entries,
+            selected,
+            ..
+        } = app.screen
+        else {
+            return;
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(area);
+
+        let items: Vec<ListItem> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let style = if i == selected {
+                    Style::default()
+                        .fg(layout_config::SELECTED_FG)
+                        .bg(layout_config::SELECTED_BG)
+                } else if entry.is_dir() {
+                    Style::default().fg(layout_config::DIR_FG)
+                } else {
+                    Style::default().fg(layout_config::FILE_FG)
+                };
+                ListItem::new(entry_label(dir, entry)).style(style)
+            })
+            .collect();
+
+        let title = format!("{}: {}", layout_config::TITLE, dir.display());
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+        let mut state = ListState::default().with_selected(Some(selected));
+        f.render_stateful_widget(list, chunks[0], &mut state);
+
+        let instructions = Paragraph::new(layout_config::INSTRUCTIONS)
+            .style(Style::default().fg(Color::Gray))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(instructions, chunks[1]);
+    }
+
+    fn handle_input(
+        &self,
+        app: &mut App,
+        key: KeyCode,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Screen::ImportAudio {
+            track,
+            ref dir,
+            ref entries,
+            ref mut selected,
+            ..
+        } = app.screen
+        else {
+            return Ok(false);
+        };
+
+        match key {
+            KeyCode::Up if *selected > 0 => *selected -= 1,
+            KeyCode::Down if *selected + 1 < entries.len() => *selected += 1,
+            KeyCode::Enter => {
+                if let Some(entry) = entries.get(*selected).cloned() {
+                    if entry.is_dir() {
+                        show_folder(app, entry);
+                    } else {
+                        import(app, track, &entry);
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(parent) = dir.parent().map(Path::to_path_buf) {
+                    show_folder(app, parent);
+                }
+            }
+            KeyCode::Esc => back_to_daw(app, None),
+            _ => {}
+        }
+        Ok(false)
+    }
+}
@@ -139,7 +139,7 @@ impl ScreenTrait for MainMenuScreen {
                         return Ok(false);
                     }
 
-                    let sample_rate = AudioEngine::get_input_device()
+                    let sample_rate = AudioEngine::input_format()
                         .map(|d| d.sample_rate)
                         .unwrap_or(48000);
 
@@ -161,35 +161,29 @@ impl ScreenTrait for MainMenuScreen {
                 _ => {}
             },
             Screen::OpenProject { selected, projects } => match key {
-                KeyCode::Up => {
-                    if *selected > 0 {
-                        *selected -= 1;
-                    }
+                KeyCode::Up if *selected > 0 => {
+                    *selected -= 1;
                 }
-                KeyCode::Down => {
-                    if *selected < projects.len().saturating_sub(1) {
-                        *selected += 1;
-                    }
+                KeyCode::Down if *selected < projects.len().saturating_sub(1) => {
+                    *selected += 1;
                 }
-                KeyCode::Enter => {
-                    if *selected < projects.len() {
-                        let project_name = projects[*selected].clone();
-                        let project_dir = projects_dir.join(&project_name);
-
-                        match project::load_project(&project_dir) {
-                            Ok(session) => {
-                                app.session = session;
-                                app.project_dir = Some(project_dir);
-                                app.screen = Screen::Daw {
-                                    selected_track: 0,
-                                    scroll_offset: 0,
-                                    selected_clip: None,
-                                };
-                                app.status = format!("Project '{}' loaded", project_name);
-                            }
-                            Err(e) => {
-                                app.status = format!("Failed to load project: {}", e);
-                            }
+                KeyCode::Enter if *selected < projects.len() => {
+                    let project_name = projects[*selected].clone();
+                    let project_dir = projects_dir.join(&project_name);
+
+                    match project::load_project(&project_dir) {
+                        Ok(session) => {
+                            app.session = session;
+                            app.project_dir = Some(project_dir);
+                            app.screen = Screen::Daw {
+                                selected_track: 0,
+                                scroll_offset: 0,
+                                selected_clip: None,
+                            };
+                            app.status = format!("Project '{}' loaded", project_name);
+                        }
+                        Err(e) => {
+                            app.status = format!("Failed to load project: {}", e);
                         }
                     }
                 }
@@ -201,15 +195,11 @@ impl ScreenTrait for MainMenuScreen {
             Screen::MainMenu { selected } => {
                 let items = Self::menu_items();
                 match key {
-                    KeyCode::Up => {
-                        if *selected > 0 {
-                            *selected -= 1;
-                        }
+                    KeyCode::Up if *selected > 0 => {
+                        *selected -= 1;
                     }
-                    KeyCode::Down => {
-                        if *selected < items.len() - 1 {
-                            *selected += 1;
-                        }
+                    KeyCode::Down if *selected < items.len() - 1 => {
+                        *selected += 1;
                     }
                     KeyCode::Enter => match items[*selected] {
                         "New Project" => {
@@ -6,10 +6,13 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
 
 mod audio_preferences_screen;
+mod aux_buses_screen;
 pub(crate) mod daw_screen;
 mod debug_logger;
 mod event_handler;
+mod export_screen;
 mod fx_chain_editor_screen;
+mod import_screen;
 mod main_menu_screen;
 mod screen_trait;
 mod view;
@@ -37,17 +40,34 @@ pub enum Screen {
         input_selected: usize,
         output_selected: usize,
     },
+    AuxBuses {
+        selected_bus: usize,
+        selected_row: usize, // 0 = bus return, 1.. = send from track row - 1
+    },
     FxChainEditor {
-        track_index: usize,           // Which track we're editing
+        target: FxTarget,             // Which track or bus we're editing
         selected_effect: usize,       // Currently selected effect in chain (0 = "Add new")
         editing_param: Option<usize>, // If Some, we're editing a parameter
         add_mode: bool,               // If true, showing effect type picker
         add_mode_selected: usize,     // Selected effect type in add mode
     },
+    Export {
+        selected_row: usize,
+        selected_track: usize, // DAW selection to return to
+        scroll_offset: u64,
+    },
+    ImportAudio {
+        track: usize,          // Track the file lands on
+        scroll_offset: u64,    // DAW timeline position to return to
+        dir: PathBuf,          // Folder being browsed
+        entries: Vec<PathBuf>, // Parent folder, sub-folders, then audio files
+        selected: usize,
+    },
 }
 
 use crate::audio_engine::AudioEngine;
-use crate::session::Session;
+use crate::export::{ExportJob, ExportSettings};
+use crate::session::{FxTarget, Session};
 use std::path::PathBuf;
 
 pub struct App {
@@ -56,13 +76,20 @@ pub struct App {
     pub session: Session,
     pub debug_logger: DebugLogger,
     pub project_dir: Option<PathBuf>,
+    // Bars/beats grid over the DAW lanes
+    pub show_grid: bool,
+    // Folder the last audio file was imported from
+    pub import_dir: Option<PathBuf>,
+    // Export dialog choices, kept between openings
+    pub export_settings: Option<ExportSettings>,
+    pub export_job: Option<ExportJob>,
 }
 
 impl App {
     fn new(debug_mode: bool) -> Self {
         // Use input device sample rate — recordings are made at this rate,
         // so playback and monitoring must match it for correct pitch.
-        let sample_rate = AudioEngine::get_input_device()
+        let sample_rate = AudioEngine::input_format()
             .map(|d| d.sample_rate)
             .unwrap_or(48000);
 
@@ -75,6 +102,10 @@ impl App {
             session,
             debug_logger: DebugLogger::new(debug_mode),
             project_dir: None,
+            show_grid: true,
+            import_dir: None,
+            export_settings: None,
+            export_job: None,
         }
     }
 
@@ -6,8 +6,11 @@ use ratatui::{
 };
 
 use super::audio_preferences_screen::AudioPreferencesScreen;
+use super::aux_buses_screen::AuxBusesScreen;
 use super::daw_screen::DawScreen;
+use super::export_screen::ExportScreen;
 use super::fx_chain_editor_screen::FxChainEditorScreen;
+use super::import_screen::ImportAudioScreen;
 use super::main_menu_screen::MainMenuScreen;
 use super::screen_trait::ScreenTrait;
 use super::{App, Screen};
@@ -86,7 +89,10 @@ impl AppView {
             | Screen::OpenProject { .. } => MainMenuScreen.render(f, app, area),
             Screen::Daw { .. } => DawScreen.render(f, app, area),
             Screen::AudioPreferences { .. } => AudioPreferencesScreen.render(f, app, area),
+            Screen::AuxBuses { .. } => AuxBusesScreen.render(f, app, area),
             Screen::FxChainEditor { .. } => FxChainEditorScreen.render(f, app, area),
+            Screen::Export { .. } => ExportScreen.render(f, app, area),
+            Screen::ImportAudio { .. } => ImportAudioScreen.render(f, app, area),
         }
     }
 
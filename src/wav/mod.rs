@@ -3,6 +3,53 @@ use std::io::Cursor;
 use std::io::Read;
 use std::path::Path;
 
+mod resample;
+pub use resample::resample;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Sample encodings WAV files can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleFormat {
+    #[default]
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl SampleFormat {
+    pub const ALL: [SampleFormat; 3] = [
+        SampleFormat::Int16,
+        SampleFormat::Int24,
+        SampleFormat::Float32,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SampleFormat::Int16 => "16-bit",
+            SampleFormat::Int24 => "24-bit",
+            SampleFormat::Float32 => "32-bit float",
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        match self {
+            SampleFormat::Int16 => 16,
+            SampleFormat::Int24 => 24,
+            SampleFormat::Float32 => 32,
+        }
+    }
+
+    fn audio_format(&self) -> u16 {
+        match self {
+            SampleFormat::Float32 => FORMAT_FLOAT,
+            _ => FORMAT_PCM,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WavHeader {
     pub chunk_id: [u8; 4],     // "RIFF"
@@ -78,24 +125,28 @@ impl WavFile {
         if header.chunk_id != *b"RIFF" || header.format != *b"WAVE" {
             return Err("Not a valid WAV file".into());
         }
-        if header.audio_format != 1 {
-            return Err(format!(
-                "Unsupported audio format: {} (only PCM is supported)",
-                header.audio_format
-            )
-            .into());
+        // WAVE_FORMAT_EXTENSIBLE names the real format in its sub-format GUID
+        let mut sample_format = header.audio_format;
+        if sample_format == FORMAT_EXTENSIBLE && header.subchunk1_size >= 26 {
+            cursor.set_position(cursor.position() + 8);
+            sample_format = read_u16(&mut cursor)?;
         }
-        if header.bits_per_sample != 16 {
+        let supported = matches!(
+            (sample_format, header.bits_per_sample),
+            (FORMAT_PCM, 8 | 16 | 24 | 32) | (FORMAT_FLOAT, 32 | 64)
+        );
+        if !supported {
             return Err(format!(
-                "Unsupported bits per sample: {} (only 16-bit is supported)",
-                header.bits_per_sample
+                "Unsupported audio format: {} at {} bits (PCM and float are supported)",
+                sample_format, header.bits_per_sample
             )
             .into());
         }
 
-        // Search for the data chunk
+        // Search for the data chunk after the fmt chunk, whatever its size
         let mut data_chunk_size: u32 = 0;
-        let mut position = cursor.position() as usize;
+        let mut position =
+            20 + header.subchunk1_size as usize + (header.subchunk1_size & 1) as usize;
 
         while position + 8 <= bytes.len() {
             let chunk_id = &bytes[position..position + 4];
@@ -112,8 +163,8 @@ impl WavFile {
                 break;
             }
 
-            // Skip this chunk and move to the next one
-            position += 8 + chunk_size as usize;
+            // Skip this chunk (padded to an even size) and move to the next one
+            position += 8 + chunk_size as usize + (chunk_size & 1) as usize;
         }
 
         if data_chunk_size == 0 {
@@ -123,7 +174,31 @@ impl WavFile {
         let data_end = (position + data_chunk_size as usize).min(bytes.len());
         let audio_data = bytes[position..data_end].to_vec();
 
-        Ok(WavFile { header, audio_data })
+        if (sample_format, header.bits_per_sample) == (FORMAT_PCM, 16) {
+            // Saved back with a plain fmt chunk
+            header.audio_format = FORMAT_PCM;
+            header.subchunk1_size = 16;
+            return Ok(WavFile { header, audio_data });
+        }
+        // Everything else is held as 16-bit PCM like the rest of the project
+        let samples = decode_samples(&audio_data, sample_format, header.bits_per_sample);
+        let mut wav = WavFile::new(header.sample_rate, header.num_channels);
+        wav.from_f32_samples(&samples);
+        Ok(wav)
+    }
+
+    /// A copy at `sample_rate` with `channels` channels.
+    pub fn converted(&self, sample_rate: u32, channels: u16) -> WavFile {
+        let mut samples = self.to_f32_samples();
+        if channels != self.header.num_channels {
+            samples = crate::channels::remix(&samples, self.header.num_channels, channels);
+        }
+        if sample_rate != self.header.sample_rate {
+            samples = resample(&samples, channels, self.header.sample_rate, sample_rate);
+        }
+        let mut wav = WavFile::new(sample_rate, channels);
+        wav.from_f32_samples(&samples);
+        wav
     }
 
     fn to_bytes(&self) -> Vec<u8> {
@@ -170,6 +245,15 @@ impl WavFile {
         self.sample_count() / channels
     }
 
+    /// Decode a single interleaved sample without converting the whole file.
+    pub fn sample_at(&self, index: usize) -> f32 {
+        let i = index * 2;
+        match self.audio_data.get(i..i + 2) {
+            Some(bytes) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            None => 0.0,
+        }
+    }
+
     pub fn to_f32_samples(&self) -> Vec<f32> {
         let mut samples = Vec::with_capacity(self.audio_data.len() / 2);
 
@@ -239,7 +323,11 @@ impl WavFile {
         let mut samples = self.to_f32_samples();
 
         for effect in effects {
-            effect.apply(&mut samples, self.header.sample_rate)?;
+            effect.apply_interleaved(
+                &mut samples,
+                self.header.sample_rate,
+                self.header.num_channels,
+            )?;
         }
 
         self.from_f32_samples(&samples);
@@ -248,6 +336,35 @@ impl WavFile {
     }
 }
 
+/// Write interleaved samples to a WAV file in `format`. Integer formats are clipped
+/// to full scale; float keeps whatever headroom the samples have.
+pub fn write_samples<P: AsRef<Path>>(
+    path: P,
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    format: SampleFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let block_align = channels * format.bits() / 8;
+    let data = encode_samples(samples, format);
+    let mut bytes = Vec::with_capacity(44 + data.len());
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&format.audio_format().to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&format.bits().to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
 fn read_u32(cursor: &mut Cursor<&Vec<u8>>) -> Result<u32, std::io::Error> {
     let mut buffer = [0; 4];
     cursor.read_exact(&mut buffer)?;
@@ -259,3 +376,39 @@ fn read_u16(cursor: &mut Cursor<&Vec<u8>>) -> Result<u16, std::io::Error> {
     cursor.read_exact(&mut buffer)?;
     Ok(u16::from_le_bytes(buffer))
 }
+
+/// Interleaved samples from PCM (8-bit unsigned, 16/24/32-bit signed) or float data.
+fn decode_samples(data: &[u8], format: u16, bits: u16) -> Vec<f32> {
+    let width = (bits / 8) as usize;
+    data.chunks_exact(width)
+        .map(|b| match (format, bits) {
+            (FORMAT_FLOAT, 32) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            (FORMAT_FLOAT, _) => {
+                f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+            }
+            (_, 8) => (b[0] as f32 - 128.0) / 128.0,
+            (_, 16) => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (_, 24) => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+            _ => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+        })
+        .collect()
+}
+
+fn encode_samples(samples: &[f32], format: SampleFormat) -> Vec<u8> {
+    let mut data = Vec::with_capacity(samples.len() * (format.bits() / 8) as usize);
+    for &sample in samples {
+        match format {
+            SampleFormat::Int16 => {
+                let value = (sample.clamp(-1.0, 1.0) * 32768.0).clamp(-32768.0, 32767.0) as i16;
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            SampleFormat::Int24 => {
+                let value =
+                    (sample.clamp(-1.0, 1.0) * 8388608.0).clamp(-8388608.0, 8388607.0) as i32;
+                data.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            SampleFormat::Float32 => data.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+    data
+}
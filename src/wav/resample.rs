@@ -0,0 +1,60 @@
+use std::f64::consts::PI;
+
+// Source frames either side of each output frame that the interpolation filter reads
+const FILTER_HALF_WIDTH: usize = 16;
+
+/// Convert an interleaved buffer from one sample rate to another with a windowed-sinc
+/// filter. When lowering the rate the filter also cuts what the new rate cannot hold.
+pub fn resample(samples: &[f32], channels: u16, from_rate: u32, to_rate: u32) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    if from_rate == to_rate || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+    let in_frames = samples.len() / channels;
+    let out_frames = (in_frames as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    // Cutoff as a fraction of the source Nyquist; the filter widens to match
+    let cutoff = (to_rate as f64 / from_rate as f64).min(1.0);
+    let half_width = FILTER_HALF_WIDTH as f64 / cutoff;
+
+    let mut out = vec![0.0f32; out_frames * channels];
+    for (n, frame) in out.chunks_exact_mut(channels).enumerate() {
+        let position = n as f64 * step;
+        let first = (position - half_width).ceil().max(0.0) as usize;
+        let last = ((position + half_width).floor() as usize).min(in_frames.saturating_sub(1));
+        let mut weight_sum = 0.0;
+        for k in first..=last {
+            let x = position - k as f64;
+            let weight = cutoff * sinc(cutoff * x) * blackman(x / half_width);
+            weight_sum += weight;
+            let src = &samples[k * channels..(k + 1) * channels];
+            for (out, &s) in frame.iter_mut().zip(src) {
+                *out += s * weight as f32;
+            }
+        }
+        // Keep the level steady at the edges where part of the filter falls outside
+        if weight_sum > 0.0 {
+            for out in frame.iter_mut() {
+                *out /= weight_sum as f32;
+            }
+        }
+    }
+    out
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Blackman window over -1.0..=1.0
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    let t = (x + 1.0) * 0.5;
+    0.42 - 0.5 * (2.0 * PI * t).cos() + 0.08 * (4.0 * PI * t).cos()
+}
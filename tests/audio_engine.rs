@@ -0,0 +1,210 @@
+//! Device selection and capability reporting, with fake devices in place of the
+//! system's.
+
+use rust_audio::audio_engine::AudioEngine;
+use rust_audio::backend::{AudioBackend, CpalBackend};
+use rust_audio::device::{AudioDevice, DeviceCapabilities, DeviceProvider};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Devices that can be listed and asked about but not opened.
+#[derive(Clone, Default)]
+struct FakeDevices {
+    names: Arc<Mutex<Vec<String>>>,
+    default: Option<String>,
+    capability_queries: Arc<AtomicUsize>,
+}
+
+impl FakeDevices {
+    fn new(names: &[&str], default: Option<&str>) -> Self {
+        FakeDevices {
+            names: Arc::new(Mutex::new(names.iter().map(|n| n.to_string()).collect())),
+            default: default.map(str::to_string),
+            capability_queries: Arc::default(),
+        }
+    }
+
+    fn unplug(&self, name: &str) {
+        self.names.lock().unwrap().retain(|n| n != name);
+    }
+}
+
+impl DeviceProvider for FakeDevices {
+    fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(self.names.lock().unwrap().clone())
+    }
+
+    fn default(&self) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        Err("Fake devices cannot be opened".into())
+    }
+
+    fn default_name(&self) -> Option<String> {
+        self.default
+            .clone()
+            .filter(|name| self.names.lock().unwrap().contains(name))
+    }
+
+    fn by_index(&self, _: usize) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        Err("Fake devices cannot be opened".into())
+    }
+
+    fn by_name(&self, _: &str) -> Result<AudioDevice, Box<dyn std::error::Error>> {
+        Err("Fake devices cannot be opened".into())
+    }
+
+    fn capabilities(&self, name: &str) -> Result<DeviceCapabilities, Box<dyn std::error::Error>> {
+        self.capability_queries.fetch_add(1, Ordering::Relaxed);
+        if !self.names.lock().unwrap().iter().any(|n| n == name) {
+            return Err("Device not found".into());
+        }
+        Ok(DeviceCapabilities {
+            sample_rates: vec![44100, 48000],
+            channels: if name.contains("Stereo") {
+                vec![1, 2]
+            } else {
+                vec![1]
+            },
+            sample_formats: vec!["f32".to_string(), "i16".to_string()],
+            buffer_frames: Some(32..=4096),
+        })
+    }
+}
+
+fn engine(inputs: &FakeDevices, outputs: &FakeDevices) -> AudioEngine {
+    AudioEngine::new(Arc::new(inputs.clone()), Arc::new(outputs.clone()), None)
+}
+
+fn config_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rust_audio_{}_{}.json", name, std::process::id()))
+}
+
+#[test]
+fn lists_the_provided_devices_and_selects_the_defaults() {
+    let inputs = FakeDevices::new(&["Mic", "Stereo Interface"], Some("Stereo Interface"));
+    let outputs = FakeDevices::new(&["Speakers", "Headphones"], None);
+    let engine = engine(&inputs, &outputs);
+
+    assert_eq!(engine.input_devices(), ["Mic", "Stereo Interface"]);
+    assert_eq!(engine.output_devices(), ["Speakers", "Headphones"]);
+    assert_eq!(engine.selected_input(), Some("Stereo Interface"));
+    // Without a default the first device is used
+    assert_eq!(engine.selected_output(), Some("Speakers"));
+}
+
+#[test]
+fn no_devices_leaves_nothing_selected() {
+    let none = FakeDevices::new(&[], None);
+    let engine = engine(&none, &none);
+
+    assert!(engine.input_devices().is_empty());
+    assert_eq!(engine.selected_input(), None);
+    assert_eq!(engine.selected_output(), None);
+}
+
+#[test]
+fn only_listed_devices_can_be_selected() {
+    let inputs = FakeDevices::new(&["Mic", "Stereo Interface"], Some("Mic"));
+    let outputs = FakeDevices::new(&["Speakers"], None);
+    let mut engine = engine(&inputs, &outputs);
+
+    engine.set_input_device("Stereo Interface".to_string());
+    assert_eq!(engine.selected_input(), Some("Stereo Interface"));
+    engine.set_input_device("Missing".to_string());
+    assert_eq!(engine.selected_input(), Some("Stereo Interface"));
+}
+
+#[test]
+fn refresh_falls_back_when_the_selected_device_goes_away() {
+    let inputs = FakeDevices::new(&["Mic", "Stereo Interface"], Some("Mic"));
+    let outputs = FakeDevices::new(&["Speakers", "Headphones"], Some("Speakers"));
+    let mut engine = engine(&inputs, &outputs);
+    engine.set_input_device("Stereo Interface".to_string());
+    engine.set_output_device("Headphones".to_string());
+
+    inputs.unplug("Stereo Interface");
+    outputs.unplug("Speakers");
+    engine.refresh_devices();
+
+    assert_eq!(engine.input_devices(), ["Mic"]);
+    assert_eq!(engine.selected_input(), Some("Mic"));
+    // Still plugged in, so still selected
+    assert_eq!(engine.selected_output(), Some("Headphones"));
+}
+
+#[test]
+fn reports_device_capabilities() {
+    let inputs = FakeDevices::new(&["Mic", "Stereo Interface"], None);
+    let outputs = FakeDevices::new(&["Speakers"], None);
+    let mut engine = engine(&inputs, &outputs);
+
+    let mic = engine.input_capabilities("Mic").unwrap();
+    assert_eq!(mic.channels, [1]);
+    let interface = engine.input_capabilities("Stereo Interface").unwrap();
+    assert_eq!(interface.channels, [1, 2]);
+    assert_eq!(interface.sample_rates, [44100, 48000]);
+    assert_eq!(interface.sample_formats, ["f32", "i16"]);
+    assert_eq!(interface.buffer_frames, Some(32..=4096));
+
+    assert!(engine.output_capabilities("Speakers").is_ok());
+    assert!(engine.output_capabilities("Missing").is_err());
+}
+
+#[test]
+fn capabilities_are_asked_for_once_until_a_refresh() {
+    let inputs = FakeDevices::new(&["Mic"], None);
+    let outputs = FakeDevices::new(&["Speakers"], None);
+    let mut engine = engine(&inputs, &outputs);
+
+    for _ in 0..3 {
+        engine.input_capabilities("Mic").unwrap();
+    }
+    assert_eq!(inputs.capability_queries.load(Ordering::Relaxed), 1);
+
+    engine.refresh_devices();
+    engine.input_capabilities("Mic").unwrap();
+    assert_eq!(inputs.capability_queries.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn preferences_are_read_from_and_saved_to_the_config_path() {
+    let inputs = FakeDevices::new(&["Mic", "Stereo Interface"], Some("Mic"));
+    let outputs = FakeDevices::new(&["Speakers", "Headphones"], Some("Speakers"));
+    let path = config_file("config");
+    std::fs::write(
+        &path,
+        r#"{"input_device": "Stereo Interface", "output_device": "Headphones"}"#,
+    )
+    .unwrap();
+
+    let mut engine = AudioEngine::new(
+        Arc::new(inputs.clone()),
+        Arc::new(outputs.clone()),
+        Some(path.clone()),
+    );
+    assert_eq!(engine.selected_input(), Some("Stereo Interface"));
+    assert_eq!(engine.selected_output(), Some("Headphones"));
+
+    engine.set_input_device("Mic".to_string());
+    engine.save_config();
+    let engine = AudioEngine::new(Arc::new(inputs), Arc::new(outputs), Some(path.clone()));
+    assert_eq!(engine.selected_input(), Some("Mic"));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn devices_are_opened_through_the_providers() {
+    let inputs = FakeDevices::new(&["Mic"], Some("Mic"));
+    let outputs = FakeDevices::new(&["Speakers"], Some("Speakers"));
+    let backend = CpalBackend::new(Arc::new(inputs), Arc::new(outputs));
+
+    for result in [
+        backend.input_format(Some("Mic")),
+        backend.input_format(None),
+        backend.output_format(Some("Speakers")),
+    ] {
+        let error = result.unwrap_err().to_string();
+        assert_eq!(error, "Fake devices cannot be opened");
+    }
+}
@@ -0,0 +1,73 @@
+//! Clip edits on a single track: every piece they leave on the timeline must be
+//! addressable by its own id, since undo, crossfades and saved projects refer to
+//! clips that way.
+
+use rust_audio::track::{Clip, Track};
+use rust_audio::wav::WavFile;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+const SAMPLE_RATE: u32 = 48000;
+
+fn clip(id: &str, starts_at: u64, frames: usize) -> Clip {
+    let mut wav = WavFile::new(SAMPLE_RATE, 1);
+    wav.from_f32_samples(&vec![0.25; frames]);
+    Clip::new(id.to_string(), Arc::new(wav), starts_at)
+}
+
+fn assert_unique_ids(track: &Track) {
+    let mut seen = HashSet::new();
+    for clip in track.clips.iter().chain(track.takes.iter()) {
+        assert!(
+            seen.insert(clip.id.clone()),
+            "duplicate clip id {}",
+            clip.id
+        );
+    }
+}
+
+#[test]
+fn clearing_the_same_edge_twice_keeps_ids_unique() {
+    let mut track = Track::new("Vocals".to_string());
+    track.clips.push(clip("vocal", 0, 1000));
+
+    // Pull the head back over the cleared range, then clear it again: both
+    // clears leave a piece of "vocal" starting at frame 400
+    track.clear_range(300..400);
+    track.trim_clip_end(0, 200).unwrap();
+    track.clear_range(300..400);
+    assert_eq!(track.clips.len(), 3);
+    assert_unique_ids(&track);
+}
+
+#[test]
+fn comping_a_take_twice_at_the_same_start_keeps_ids_unique() {
+    let mut track = Track::new("Guitar".to_string());
+    track.add_recording(clip("first", 0, 1000), true);
+    track.add_recording(clip("second", 0, 1000), true);
+
+    // A muted section survives the next comp, which starts at the same frame
+    track.comp_take(0, 0..500).unwrap();
+    let section = track
+        .clips
+        .iter()
+        .position(|c| c.take.as_deref() == Some("first"))
+        .unwrap();
+    track.clips[section].muted = true;
+    track.comp_take(0, 0..300).unwrap();
+    assert_unique_ids(&track);
+}
+
+#[test]
+fn splitting_at_the_same_frame_twice_keeps_ids_unique() {
+    let mut track = Track::new("Bass".to_string());
+    track.clips.push(clip("bass", 0, 1000));
+
+    // Trim the first part back out past the split and split it there again
+    track.split_clip(0, 500).unwrap();
+    track.trim_clip_end(0, 200).unwrap();
+    let tail = track.split_clip(0, 500).unwrap();
+    assert_eq!(track.clips[tail].starts_at, 500);
+    assert_eq!(track.clips.len(), 3);
+    assert_unique_ids(&track);
+}
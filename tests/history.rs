@@ -0,0 +1,206 @@
+//! The undo stack: which edits fold together, how far back it reaches, which
+//! audio it keeps alive and how clip layouts come back.
+
+use rust_audio::history::{Edit, History, MAX_UNDO_STEPS};
+use rust_audio::session::Session;
+use rust_audio::track::{Clip, Track};
+use rust_audio::wav::WavFile;
+use std::sync::Arc;
+
+const SAMPLE_RATE: u32 = 48000;
+
+fn wav(frames: usize) -> Arc<WavFile> {
+    let mut wav = WavFile::new(SAMPLE_RATE, 1);
+    let samples: Vec<f32> = (0..frames).map(|i| (i % 100) as f32 / 200.0).collect();
+    wav.from_f32_samples(&samples);
+    Arc::new(wav)
+}
+
+fn gain(before: f64, after: f64) -> Vec<Edit> {
+    vec![Edit::MasterGain { before, after }]
+}
+
+fn undo_count(history: &mut History) -> usize {
+    let mut count = 0;
+    while history.undo().is_some() {
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn repeats_of_the_same_edit_coalesce() {
+    let mut history = History::new();
+    history.record("Master volume", gain(0.0, 1.0), true);
+    history.record("Master volume", gain(1.0, 2.0), true);
+    history.record("Master volume", gain(2.0, 3.0), true);
+
+    // One step, from the first start state to the last end state
+    let command = history.undo().unwrap();
+    assert_eq!(command.name, "Master volume");
+    match command.edits[..] {
+        [Edit::MasterGain { before, after }] => assert_eq!((before, after), (0.0, 3.0)),
+        _ => panic!("unexpected edits {:?}", command.edits),
+    }
+    assert!(history.undo().is_none());
+}
+
+#[test]
+fn edits_with_another_name_or_target_do_not_coalesce() {
+    let mut history = History::new();
+    let clips = |track| {
+        let mut scratch = History::new();
+        let layout = scratch.capture(&Track::new("Empty".to_string()));
+        vec![Edit::Clips {
+            track,
+            before: layout.clone(),
+            after: layout,
+        }]
+    };
+    history.record("Move clip", clips(0), true);
+    history.record("Move clip", clips(1), true);
+    history.record("Trim clip", clips(1), true);
+    // A step recorded without coalescing neither extends nor is extended
+    history.record("Trim clip", clips(1), false);
+    history.record("Trim clip", clips(1), true);
+    assert_eq!(undo_count(&mut history), 5);
+}
+
+#[test]
+fn a_redone_edit_is_not_extended_by_the_next_repeat() {
+    let mut history = History::new();
+    history.record("Master volume", gain(0.0, 1.0), true);
+    history.undo();
+    history.redo();
+    history.record("Master volume", gain(1.0, 2.0), true);
+    assert_eq!(undo_count(&mut history), 2);
+}
+
+#[test]
+fn the_oldest_step_is_dropped_past_the_limit() {
+    let mut history = History::new();
+    for step in 0..=MAX_UNDO_STEPS {
+        history.record(
+            &format!("Step {}", step),
+            gain(step as f64, step as f64 + 1.0),
+            false,
+        );
+    }
+    assert_eq!(
+        history.undo_name(),
+        Some(format!("Step {}", MAX_UNDO_STEPS).as_str())
+    );
+
+    let mut oldest = None;
+    let mut count = 0;
+    while let Some(command) = history.undo() {
+        oldest = Some(command.name);
+        count += 1;
+    }
+    assert_eq!(count, MAX_UNDO_STEPS);
+    assert_eq!(oldest.as_deref(), Some("Step 1"));
+}
+
+#[test]
+fn audio_no_step_uses_is_released() {
+    let audio = wav(1000);
+    let mut track = Track::new("Vocals".to_string());
+    track
+        .clips
+        .push(Clip::new("vocal".to_string(), Arc::clone(&audio), 0));
+
+    let mut history = History::new();
+    let before = history.capture(&track);
+    track.clips.clear();
+    let after = history.capture(&track);
+    history.record(
+        "Delete clip",
+        vec![Edit::Clips {
+            track: 0,
+            before,
+            after,
+        }],
+        false,
+    );
+    // Deleted from the track, but undo can still bring it back
+    assert_eq!(Arc::strong_count(&audio), 2);
+
+    // Once the step falls off the end of the stack, so does its audio
+    for step in 0..MAX_UNDO_STEPS {
+        history.record("Master volume", gain(step as f64, step as f64 + 1.0), false);
+    }
+    assert_eq!(Arc::strong_count(&audio), 1);
+}
+
+#[test]
+fn audio_of_discarded_redo_steps_is_released() {
+    let audio = wav(1000);
+    let mut track = Track::new("Vocals".to_string());
+    let mut history = History::new();
+    let before = history.capture(&track);
+    track
+        .clips
+        .push(Clip::new("vocal".to_string(), Arc::clone(&audio), 0));
+    let after = history.capture(&track);
+    history.record(
+        "Add clip",
+        vec![Edit::Clips {
+            track: 0,
+            before,
+            after,
+        }],
+        false,
+    );
+    history.undo();
+    track.clips.clear();
+    assert_eq!(Arc::strong_count(&audio), 2);
+
+    // A new edit throws the redo step away
+    history.record("Master volume", gain(0.0, 1.0), false);
+    assert_eq!(Arc::strong_count(&audio), 1);
+}
+
+#[test]
+fn restoring_a_clip_without_audio_fails_and_keeps_the_track() {
+    let mut track = Track::new("Vocals".to_string());
+    track
+        .clips
+        .push(Clip::new("vocal".to_string(), wav(1000), 0));
+    let layout = History::new().capture(&track);
+
+    // This history never saw the clip, so it has no audio for it
+    let mut other = Track::new("Other".to_string());
+    other
+        .clips
+        .push(Clip::new("other".to_string(), wav(500), 0));
+    assert!(History::new().restore(&layout, &mut other).is_err());
+    assert_eq!(other.clips.len(), 1);
+    assert_eq!(other.clips[0].id, "other");
+}
+
+#[test]
+fn split_undo_and_redo_give_back_identical_clips() {
+    let mut session = Session::new("Test".to_string(), SAMPLE_RATE);
+    session.add_track("Vocals".to_string()).unwrap();
+    session.tracks[0]
+        .clips
+        .push(Clip::new("vocal".to_string(), wav(1000), 100));
+    let layout = |session: &Session| History::new().capture(&session.tracks[0]);
+    let original = layout(&session);
+
+    session.split_clip(0, 0, 400).unwrap();
+    let split = layout(&session);
+    assert_eq!(split.clips.len(), 2);
+
+    assert_eq!(session.undo().unwrap(), "Split clip");
+    assert_eq!(layout(&session), original);
+    assert_eq!(session.redo().unwrap(), "Split clip");
+    assert_eq!(layout(&session), split);
+    assert_eq!(session.undo().unwrap(), "Split clip");
+    assert_eq!(layout(&session), original);
+
+    // Both parts still play the very same audio
+    session.redo().unwrap();
+    let clips = &session.tracks[0].clips;
+    assert!(Arc::ptr_eq(&clips[0].wav_data, &clips[1].wav_data));
+}
@@ -0,0 +1,95 @@
+//! Importing audio files: conversion to the session's format, and the copy kept in
+//! the project.
+
+use rust_audio::project;
+use rust_audio::session::Session;
+use rust_audio::wav::WavFile;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// A folder of its own in the temp folder, emptied first.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("rust_audio_import_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A second of mono sine at `sample_rate`, saved in `dir`.
+fn source_file(dir: &Path, sample_rate: u32) -> PathBuf {
+    let samples: Vec<f32> = (0..sample_rate)
+        .map(|i| 0.5 * (2.0 * PI * 440.0 * i as f32 / sample_rate as f32).sin())
+        .collect();
+    let mut wav = WavFile::new(sample_rate, 1);
+    wav.from_f32_samples(&samples);
+    let path = dir.join("source.wav");
+    wav.save_to_file(&path).unwrap();
+    path
+}
+
+fn session() -> Session {
+    let mut session = Session::new("Import".to_string(), SAMPLE_RATE);
+    session.add_track("Audio".to_string()).unwrap();
+    session
+}
+
+#[test]
+fn an_import_is_converted_to_the_session_format() {
+    let dir = temp_dir("convert");
+    let source = source_file(&dir, 44100);
+    let mut session = session();
+    session.transport.playhead_position = 1000;
+
+    let idx = session.import_audio(0, &source, None).unwrap();
+    let clip = &session.tracks[0].clips[idx];
+    assert_eq!(clip.starts_at, 1000);
+    assert_eq!(clip.wav_data.header.sample_rate, SAMPLE_RATE);
+    assert_eq!(clip.wav_data.header.num_channels, session.channels);
+    assert_eq!(clip.length, SAMPLE_RATE as u64);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn an_import_is_copied_into_the_project_straight_away() {
+    let dir = temp_dir("copy");
+    let source = source_file(&dir, 44100);
+    let project_dir = dir.join("project");
+    let mut session = session();
+
+    let idx = session
+        .import_audio(0, &source, Some(&project_dir))
+        .unwrap();
+    let clip = &session.tracks[0].clips[idx];
+    let copy = project_dir.join("clips").join(format!("{}.wav", clip.id));
+    let copied = WavFile::load_from_file(&copy).unwrap();
+    assert_eq!(copied.header.sample_rate, SAMPLE_RATE);
+    assert_eq!(copied.frame_count(), clip.wav_data.frame_count());
+
+    // The copy outlives the source, and saving uses it rather than writing another
+    fs::remove_file(&source).unwrap();
+    project::save_project(&session, &project_dir).unwrap();
+    assert_eq!(fs::read_dir(project_dir.join("clips")).unwrap().count(), 1);
+    let loaded = project::load_project(&project_dir).unwrap();
+    assert_eq!(
+        loaded.tracks[0].clips[0].wav_data.frame_count(),
+        copied.frame_count()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn an_import_that_cannot_be_copied_adds_no_clip() {
+    let dir = temp_dir("uncopyable");
+    let source = source_file(&dir, SAMPLE_RATE);
+    let mut session = session();
+
+    // A file where the project folder should be
+    assert!(session.import_audio(0, &source, Some(&source)).is_err());
+    assert!(session.tracks[0].clips.is_empty());
+    assert_ne!(session.history().undo_name(), Some("Import audio"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,196 @@
+//! Loudness measurement checked against EBU Tech 3341 and 3342 style test signals,
+//! and loudness-normalised exports measured back from the written file.
+
+use rust_audio::export::{self, ExportProgress, ExportSettings, Normalize};
+use rust_audio::loudness::{self, LoudnessReport};
+use rust_audio::session::Session;
+use rust_audio::track::Clip;
+use rust_audio::wav::{SampleFormat, WavFile};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+const SAMPLE_RATE: u32 = 48000;
+// EBU Tech 3341 allows +/-0.1 LU on its stationary test signals
+const LU_TOLERANCE: f64 = 0.1;
+
+/// A sine wave peaking at `dbfs`, on every one of `channels` channels.
+fn sine(frequency: f64, dbfs: f64, seconds: f64, channels: u16, phase: f64) -> Vec<f32> {
+    let amplitude = 10f64.powf(dbfs / 20.0);
+    let frames = (seconds * SAMPLE_RATE as f64) as usize;
+    (0..frames)
+        .flat_map(|i| {
+            let value =
+                amplitude * (2.0 * PI * frequency * i as f64 / SAMPLE_RATE as f64 + phase).sin();
+            std::iter::repeat_n(value as f32, channels as usize)
+        })
+        .collect()
+}
+
+fn stereo_sine(dbfs: f64, seconds: f64) -> Vec<f32> {
+    sine(1000.0, dbfs, seconds, 2, 0.0)
+}
+
+fn sample_peak_db(samples: &[f32]) -> f64 {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    20.0 * (peak as f64).log10()
+}
+
+fn assert_level(what: &str, actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() <= LU_TOLERANCE,
+        "{}: {:.3} instead of {:.1}",
+        what,
+        actual,
+        expected
+    );
+}
+
+fn assert_steady(report: &LoudnessReport, expected: f64) {
+    assert_level("integrated", report.integrated, expected);
+    assert!(!report.momentary.is_empty() && !report.short_term.is_empty());
+    for &level in &report.momentary {
+        assert_level("momentary", level, expected);
+    }
+    for &level in &report.short_term {
+        assert_level("short-term", level, expected);
+    }
+}
+
+#[test]
+fn a_stereo_sine_at_minus_23_dbfs_reads_minus_23_lufs() {
+    // Tech 3341 case 1: 1 kHz, -23 dBFS on both channels, 20 s
+    let report = loudness::analyze(&stereo_sine(-23.0, 20.0), 2, SAMPLE_RATE);
+    assert_steady(&report, -23.0);
+    assert!(report.loudness_range.abs() <= LU_TOLERANCE);
+}
+
+#[test]
+fn a_stereo_sine_at_minus_33_dbfs_reads_minus_33_lufs() {
+    // Tech 3341 case 2
+    let report = loudness::analyze(&stereo_sine(-33.0, 20.0), 2, SAMPLE_RATE);
+    assert_steady(&report, -33.0);
+}
+
+#[test]
+fn quiet_passages_are_gated_out_of_the_integrated_loudness() {
+    // Tech 3341 case 4 (-36, -23, -36 dBFS), shortened from 10, 60 and 10 s
+    let mut samples = stereo_sine(-36.0, 5.0);
+    samples.extend(stereo_sine(-23.0, 20.0));
+    samples.extend(stereo_sine(-36.0, 5.0));
+    let report = loudness::analyze(&samples, 2, SAMPLE_RATE);
+    assert_level("integrated", report.integrated, -23.0);
+}
+
+#[test]
+fn level_steps_give_a_known_loudness_range() {
+    // Tech 3342 cases 1 and 2: 20 s at -20 dBFS, then 20 s at -30 or -15 dBFS
+    for (second, expected) in [(-30.0, 10.0), (-15.0, 5.0)] {
+        let mut samples = stereo_sine(-20.0, 20.0);
+        samples.extend(stereo_sine(second, 20.0));
+        let report = loudness::analyze(&samples, 2, SAMPLE_RATE);
+        assert!(
+            (report.loudness_range - expected).abs() <= 1.0,
+            "LRA {:.2} instead of {:.0}",
+            report.loudness_range,
+            expected
+        );
+    }
+}
+
+#[test]
+fn true_peak_finds_peaks_between_samples() {
+    // A quarter of the sample rate, 45 degrees out: every sample lands at 0.707 of
+    // the wave's peak, so the sample peak reads 3 dB low
+    let samples = sine(SAMPLE_RATE as f64 / 4.0, -3.0, 1.0, 2, PI / 4.0);
+    let sample_peak = sample_peak_db(&samples);
+    assert!(
+        (sample_peak - -6.0).abs() < 0.05,
+        "sample peak {:.2}",
+        sample_peak
+    );
+
+    let report = loudness::analyze(&samples, 2, SAMPLE_RATE);
+    assert!(
+        report.true_peak > sample_peak + 2.5,
+        "true peak {:.2} dBTP, sample peak {:.2} dBFS",
+        report.true_peak,
+        sample_peak
+    );
+    assert!(
+        (report.true_peak - -3.0).abs() < 0.3,
+        "true peak {:.2}",
+        report.true_peak
+    );
+}
+
+#[test]
+fn true_peak_of_a_low_sine_is_its_sample_peak() {
+    let samples = stereo_sine(-6.0, 1.0);
+    let report = loudness::analyze(&samples, 2, SAMPLE_RATE);
+    assert!(
+        (report.true_peak - -6.0).abs() < 0.05,
+        "true peak {:.2}",
+        report.true_peak
+    );
+}
+
+/// Export `samples` (mono) as a float stereo mix normalised to `lufs` with a
+/// `ceiling` dBTP, and measure the file written.
+fn normalized_export(name: &str, samples: &[f32], lufs: f32, ceiling: f32) -> LoudnessReport {
+    let mut wav = WavFile::new(SAMPLE_RATE, 1);
+    wav.from_f32_samples(samples);
+    let mut session = Session::new("Loudness".to_string(), SAMPLE_RATE);
+    session.add_track("Tone".to_string()).unwrap();
+    session.tracks[0]
+        .clips
+        .push(Clip::new("tone".to_string(), Arc::new(wav), 0));
+
+    let mut settings = ExportSettings::new(SAMPLE_RATE, 2);
+    settings.format = SampleFormat::Float32;
+    settings.tail_seconds = Some(0.0);
+    settings.normalize = Normalize::Loudness { lufs, ceiling };
+    let renders = session.export_renders(&settings).unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "rust_audio_loudness_{}_{}.wav",
+        name,
+        std::process::id()
+    ));
+    export::export(renders, &settings, &path, &ExportProgress::default()).unwrap();
+    let written = WavFile::load_from_file(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    written.loudness()
+}
+
+#[test]
+fn a_normalised_export_lands_on_the_target_loudness() {
+    let tone = sine(1000.0, -30.0, 10.0, 1, 0.0);
+    let report = normalized_export("target", &tone, -16.0, -1.0);
+    assert_level("integrated", report.integrated, -16.0);
+    assert!(
+        report.true_peak <= -1.0,
+        "true peak {:.2}",
+        report.true_peak
+    );
+}
+
+#[test]
+fn a_normalised_export_stops_at_the_true_peak_ceiling() {
+    // A quiet tone with full-scale clicks: reaching -14 LUFS would push the clicks
+    // far past the ceiling, so the export stays quieter instead
+    let mut tone = sine(1000.0, -30.0, 10.0, 1, 0.0);
+    for click in tone.iter_mut().step_by(SAMPLE_RATE as usize / 2) {
+        *click = 0.9;
+    }
+    let report = normalized_export("ceiling", &tone, -14.0, -1.0);
+    assert!(
+        report.true_peak <= -1.0 + 0.01,
+        "true peak {:.2}",
+        report.true_peak
+    );
+    assert!(report.true_peak > -1.5, "true peak {:.2}", report.true_peak);
+    assert!(
+        report.integrated < -14.0 - 1.0,
+        "integrated {:.2}",
+        report.integrated
+    );
+}
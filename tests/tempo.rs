@@ -0,0 +1,158 @@
+//! Converting between frames and bars/beats across tempo and time signature
+//! changes, both ways, at the changes themselves and in between them.
+
+use rust_audio::tempo::{MusicalTime, TempoChange, TempoMap, DEFAULT_BPM, MAX_BPM, MIN_BPM};
+
+const SAMPLE_RATE: u32 = 48000;
+
+fn change(bar: u32, bpm: f64, numerator: u8, denominator: u8) -> TempoChange {
+    TempoChange {
+        bar,
+        bpm,
+        numerator,
+        denominator,
+    }
+}
+
+fn time(bar: u32, beat: u32, tick: u32) -> MusicalTime {
+    MusicalTime { bar, beat, tick }
+}
+
+/// 4/4 at 120 (24000-frame beats), 3/4 at 90 from bar 2 (32000-frame beats) and
+/// 7/8 at 150 from bar 4 (9600-frame eighth notes).
+fn map() -> TempoMap {
+    TempoMap::from_changes(vec![
+        change(0, 120.0, 4, 4),
+        change(2, 90.0, 3, 4),
+        change(4, 150.0, 7, 8),
+    ])
+}
+
+// Where the changes at bars 2 and 4 start
+const BAR_2: u64 = 2 * 96000;
+const BAR_4: u64 = BAR_2 + 2 * 96000;
+
+fn assert_both_ways(map: &TempoMap, frame: u64, expected: MusicalTime) {
+    assert_eq!(
+        map.frames_to_musical(frame, SAMPLE_RATE),
+        expected,
+        "frame {}",
+        frame
+    );
+    assert_eq!(
+        map.musical_to_frames(expected, SAMPLE_RATE),
+        frame,
+        "{}",
+        expected
+    );
+}
+
+#[test]
+fn a_single_tempo_counts_bars_and_beats() {
+    let map = TempoMap::default();
+    assert_both_ways(&map, 0, time(0, 0, 0));
+    assert_both_ways(&map, 24000, time(0, 1, 0));
+    assert_both_ways(&map, 96000, time(1, 0, 0));
+    assert_both_ways(&map, 96000 + 2 * 24000 + 12000, time(1, 2, 480));
+}
+
+#[test]
+fn changes_start_on_their_bar() {
+    let map = map();
+    assert_both_ways(&map, BAR_2, time(2, 0, 0));
+    assert_both_ways(&map, BAR_4, time(4, 0, 0));
+    assert_both_ways(&map, BAR_4 + 67200, time(5, 0, 0));
+}
+
+#[test]
+fn positions_between_changes_use_the_tempo_in_effect() {
+    let map = map();
+    // Half a beat into the last beat of bar 1, still at 120
+    assert_both_ways(&map, BAR_2 - 12000, time(1, 3, 480));
+    // Mid-bar in the 3/4 section
+    assert_both_ways(&map, BAR_2 + 96000 + 32000 + 16000, time(3, 1, 480));
+    // Mid-bar in the 7/8 section
+    assert_both_ways(&map, BAR_4 + 67200 + 3 * 9600 + 4800, time(5, 3, 480));
+}
+
+#[test]
+fn the_last_frame_before_a_change_belongs_to_the_bar_before() {
+    let map = map();
+    assert_eq!(
+        map.frames_to_musical(BAR_2 - 1, SAMPLE_RATE),
+        time(1, 3, 959)
+    );
+    assert_eq!(
+        map.frames_to_musical(BAR_4 - 1, SAMPLE_RATE),
+        time(3, 2, 959)
+    );
+}
+
+#[test]
+fn a_change_in_the_middle_of_a_passage_moves_only_what_follows() {
+    let mut map = TempoMap::default();
+    let before = map.musical_to_frames(time(1, 2, 0), SAMPLE_RATE);
+    // Switch to 6/8 from bar 3: a position partway through bar 3 moves with it
+    let mid_bar = time(3, 3, 0);
+    assert_eq!(
+        map.musical_to_frames(mid_bar, SAMPLE_RATE),
+        3 * 96000 + 3 * 24000
+    );
+    map.set_change(change(3, 120.0, 6, 8));
+
+    assert_eq!(map.musical_to_frames(time(1, 2, 0), SAMPLE_RATE), before);
+    // Three eighth notes of 12000 frames into bar 3
+    assert_both_ways(&map, 3 * 96000 + 3 * 12000, mid_bar);
+    assert_both_ways(&map, 3 * 96000 + 72000, time(4, 0, 0));
+}
+
+#[test]
+fn frames_round_trip_across_every_change() {
+    let map = map();
+    for frame in (0..BAR_4 + 3 * 67200).step_by(997) {
+        let musical = map.frames_to_musical(frame, SAMPLE_RATE);
+        let back = map.musical_to_frames(musical, SAMPLE_RATE);
+        // Ticks truncate, so the way back lands within a tick (at most 34 frames)
+        assert!(
+            frame.abs_diff(back) <= 34,
+            "{} -> {} -> {}",
+            frame,
+            musical,
+            back
+        );
+    }
+}
+
+#[test]
+fn from_changes_sorts_and_repairs_stored_changes() {
+    let map = TempoMap::from_changes(vec![
+        change(4, 150.0, 7, 8),
+        change(1, 90.0, 3, 4),
+        change(4, 100.0, 5, 4),
+        change(2, f64::NAN, 4, 4),
+        change(3, 120.0, 0, 4),
+        change(6, 1000.0, 4, 4),
+        change(8, 1.0, 4, 4),
+    ]);
+    let changes = map.changes();
+    // The first change is moved to bar 0, broken ones are dropped, the first of
+    // two at the same bar wins and tempos are clamped
+    assert_eq!(
+        changes,
+        &[
+            change(0, 90.0, 3, 4),
+            change(4, 150.0, 7, 8),
+            change(6, MAX_BPM, 4, 4),
+            change(8, MIN_BPM, 4, 4),
+        ]
+    );
+    // Bars 0 to 3 are all 3/4 at 90
+    assert_both_ways(&map, 4 * 96000, time(4, 0, 0));
+}
+
+#[test]
+fn from_changes_without_usable_changes_is_the_default() {
+    assert_eq!(TempoMap::from_changes(Vec::new()), TempoMap::default());
+    let map = TempoMap::from_changes(vec![change(0, f64::INFINITY, 4, 4)]);
+    assert_eq!(map.changes(), &[change(0, DEFAULT_BPM, 4, 4)]);
+}
@@ -0,0 +1,281 @@
+//! Play, record and overdub through the real session and mixer code, with the null
+//! backend standing in for the audio devices and a WAV file for the microphone.
+
+use rust_audio::audio_engine::AudioEngine;
+use rust_audio::backend::{Clock, NullBackend};
+use rust_audio::device::NoDevices;
+use rust_audio::export::ExportSettings;
+use rust_audio::session::{Session, TransportState};
+use rust_audio::track::Clip;
+use rust_audio::wav::WavFile;
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const SAMPLE_RATE: u32 = 48000;
+const CHANNELS: u16 = 2;
+// Samples go through 16-bit WAV data on the way
+const TOLERANCE: f32 = 1e-3;
+
+// The audio engine is global, so tests that install one take turns
+static ENGINE: Mutex<()> = Mutex::new(());
+
+struct Setup {
+    backend: Arc<NullBackend>,
+    _engine: MutexGuard<'static, ()>,
+}
+
+/// Install a fresh engine without devices or saved preferences, on `backend`.
+fn install_engine(backend: Arc<NullBackend>) -> MutexGuard<'static, ()> {
+    let guard = ENGINE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut engine = AudioEngine::new(Arc::new(NoDevices), Arc::new(NoDevices), None);
+    engine.set_backend(backend);
+    AudioEngine::set_global(engine);
+    guard
+}
+
+/// Point the engine at a fresh manual null backend, recording from `input` if given.
+fn setup(input: Option<&PathBuf>) -> Setup {
+    let backend = Arc::new(
+        NullBackend::new(SAMPLE_RATE, CHANNELS)
+            .with_clock(Clock::Manual)
+            .capturing(),
+    );
+    let guard = install_engine(backend.clone());
+    AudioEngine::global()
+        .lock()
+        .unwrap()
+        .set_input_file(input.map(|p| p.as_path()))
+        .unwrap();
+    Setup {
+        backend,
+        _engine: guard,
+    }
+}
+
+fn sine(frequency: f32, frames: usize, level: f32) -> Vec<f32> {
+    (0..frames)
+        .map(|i| level * (2.0 * PI * frequency * i as f32 / SAMPLE_RATE as f32).sin())
+        .collect()
+}
+
+fn mono_wav(samples: &[f32]) -> WavFile {
+    let mut wav = WavFile::new(SAMPLE_RATE, 1);
+    wav.from_f32_samples(samples);
+    wav
+}
+
+/// `samples` saved as a mono WAV in the temp folder.
+fn input_file(name: &str, samples: &[f32]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rust_audio_{}_{}.wav", name, std::process::id()));
+    mono_wav(samples).save_to_file(&path).unwrap();
+    path
+}
+
+fn session_with_clip(samples: &[f32], starts_at: u64) -> Session {
+    let mut session = Session::new("Test".to_string(), SAMPLE_RATE);
+    session.add_track("Backing".to_string()).unwrap();
+    session.tracks[0].clips.push(Clip::new(
+        "backing".to_string(),
+        Arc::new(mono_wav(samples)),
+        starts_at,
+    ));
+    session
+}
+
+/// The session's mix rendered offline, as the export would.
+fn offline_mix(session: &Session, frames: usize) -> Vec<f32> {
+    let mut renders = session
+        .export_renders(&ExportSettings::new(SAMPLE_RATE, CHANNELS))
+        .unwrap();
+    let mut mix = vec![0.0f32; frames * CHANNELS as usize];
+    renders[0].mixer.process(&mut mix);
+    mix
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() < TOLERANCE, "sample {}: {} != {}", i, a, e);
+    }
+}
+
+/// The recorded clip of a mono recording, in samples.
+fn recorded_samples(session: &Session, track: usize) -> (u64, Vec<f32>) {
+    let clips = &session.tracks[track].clips;
+    assert_eq!(clips.len(), 1, "expected one recorded clip");
+    (clips[0].starts_at, clips[0].wav_data.to_f32_samples())
+}
+
+#[test]
+fn playback_matches_offline_render_and_stops_at_the_end() {
+    let setup = setup(None);
+    let backing = sine(440.0, 12000, 0.5);
+    let mut session = session_with_clip(&backing, 2000);
+
+    session.start_playback().unwrap();
+    assert_eq!(session.transport.state, TransportState::Playing);
+
+    setup.backend.advance(10000);
+    session.check_playback_status();
+    assert_eq!(session.transport.playhead_position, 10000);
+    assert_eq!(session.transport.state, TransportState::Playing);
+
+    setup.backend.advance(10000);
+    session.check_playback_status();
+    assert_eq!(session.transport.state, TransportState::Stopped);
+
+    let output = setup.backend.take_output();
+    let expected = offline_mix(&session, 14000);
+    assert_close(&output[..expected.len()], &expected);
+    assert!(output[expected.len()..].iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn playback_follows_the_playhead() {
+    let setup = setup(None);
+    let backing = sine(220.0, 8000, 0.5);
+    let mut session = session_with_clip(&backing, 0);
+
+    session.transport.playhead_position = 4000;
+    session.start_playback().unwrap();
+    setup.backend.advance(2000);
+    session.check_playback_status();
+    assert_eq!(session.transport.playhead_position, 6000);
+
+    let output = setup.backend.take_output();
+    let expected = offline_mix(&session, 6000);
+    assert_close(&output, &expected[4000 * CHANNELS as usize..]);
+}
+
+#[test]
+fn recording_captures_the_input_file_at_the_playhead() {
+    let take = sine(330.0, 9600, 0.4);
+    let path = input_file("record", &take);
+    let setup = setup(Some(&path));
+
+    let mut session = Session::new("Test".to_string(), SAMPLE_RATE);
+    session.add_track("Vocal".to_string()).unwrap();
+    session.tracks[0].arm();
+    session.transport.playhead_position = 4800;
+
+    assert_eq!(session.start_recording().unwrap(), 1);
+    assert_eq!(session.transport.state, TransportState::Recording);
+    setup.backend.advance(12000);
+    session.check_playback_status();
+    assert_eq!(session.transport.playhead_position, 4800 + 12000);
+    session.stop_all_recording();
+    assert_eq!(session.transport.state, TransportState::Stopped);
+
+    let (starts_at, recorded) = recorded_samples(&session, 0);
+    assert_eq!(starts_at, 4800);
+    assert_eq!(recorded.len(), 12000);
+    assert_close(&recorded[..take.len()], &take);
+    assert!(recorded[take.len()..].iter().all(|s| s.abs() < TOLERANCE));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn recording_can_be_undone() {
+    let take = sine(330.0, 4800, 0.4);
+    let path = input_file("undo", &take);
+    let setup = setup(Some(&path));
+
+    let mut session = Session::new("Test".to_string(), SAMPLE_RATE);
+    session.add_track("Vocal".to_string()).unwrap();
+    session.tracks[0].arm();
+
+    session.start_recording().unwrap();
+    setup.backend.advance(4800);
+    session.stop_all_recording();
+    assert_eq!(session.tracks[0].clips.len(), 1);
+
+    session.undo().unwrap();
+    assert!(session.tracks[0].clips.is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn overdub_lines_up_with_the_backing_track() {
+    let backing = sine(220.0, 24000, 0.3);
+    let take = sine(550.0, 24000, 0.3);
+    let path = input_file("overdub", &take);
+    let setup = setup(Some(&path));
+
+    let mut session = session_with_clip(&backing, 0);
+    session.add_track("Overdub".to_string()).unwrap();
+    session.tracks[1].arm();
+
+    session.start_recording().unwrap();
+    setup.backend.advance(24000);
+    session.stop_all_recording();
+    setup.backend.take_output();
+
+    // The backing track is untouched and the take sits under it, sample for sample
+    assert_eq!(session.tracks[0].clips.len(), 1);
+    let (starts_at, recorded) = recorded_samples(&session, 1);
+    assert_eq!(starts_at, 0);
+    assert_close(&recorded, &take);
+
+    // Both play back together
+    session.tracks[1].disarm();
+    session.transport.reset_playhead();
+    session.start_playback().unwrap();
+    setup.backend.advance(24000);
+    let output = setup.backend.take_output();
+    let mix = offline_mix(&session, 24000);
+    assert_close(&output, &mix);
+    let backing_only = {
+        let mut session = session_with_clip(&backing, 0);
+        session.add_track("Overdub".to_string()).unwrap();
+        offline_mix(&session, 24000)
+    };
+    assert!(mix
+        .iter()
+        .zip(&backing_only)
+        .any(|(a, b)| (a - b).abs() > 0.1));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn input_file_is_resampled_to_the_session_rate() {
+    let take = sine(330.0, 4410, 0.4);
+    let path = std::env::temp_dir().join(format!("rust_audio_44k_{}.wav", std::process::id()));
+    let mut wav = WavFile::new(44100, 1);
+    wav.from_f32_samples(&take);
+    wav.save_to_file(&path).unwrap();
+    let setup = setup(Some(&path));
+
+    let mut session = Session::new("Test".to_string(), SAMPLE_RATE);
+    session.add_track("Vocal".to_string()).unwrap();
+    session.tracks[0].arm();
+    session.start_recording().unwrap();
+    setup.backend.advance(SAMPLE_RATE as usize / 5);
+    session.stop_all_recording();
+
+    // A tenth of a second at 44.1 kHz is a tenth of a second at 48 kHz
+    let (_, recorded) = recorded_samples(&session, 0);
+    let sounding = recorded.iter().rposition(|s| s.abs() > 0.01).unwrap();
+    assert!((sounding as i64 - 4800).abs() < 50, "{}", sounding);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn real_time_clock_plays_without_being_driven() {
+    let backend = Arc::new(NullBackend::new(SAMPLE_RATE, CHANNELS).capturing());
+    let _guard = install_engine(backend.clone());
+
+    let backing = sine(440.0, SAMPLE_RATE as usize, 0.5);
+    let mut session = session_with_clip(&backing, 0);
+    session.start_playback().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    session.check_playback_status();
+    session.stop_playback().unwrap();
+
+    assert!(session.transport.playhead_position > 0);
+    assert!(!backend.take_output().is_empty());
+}